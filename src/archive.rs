@@ -0,0 +1,508 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::attachment::Attachment;
+use crate::helpers::TryFromDatabase;
+use crate::note::{Note, NoteData, NoteSummary, NotesOrder, NotesTable};
+use crate::notebook::Notebook;
+use crate::tag::Tag;
+
+/// Bumped whenever the layout of the archives changes.
+const ARCHIVE_VERSION: u32 = 2;
+// Archives before this version hold the tags as a list of names
+const ARCHIVED_TEMPLATES_VERSION: u32 = 2;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const TAGS_ENTRY: &str = "tags.json";
+const LINKS_ENTRY: &str = "links.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+const IMPLICATIONS_ENTRY: &str = "implications.json";
+const NOTES_DIR: &str = "notes/";
+const ATTACHMENTS_DIR: &str = "attachments/";
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("The archive has no manifest.")]
+    MissingManifest,
+    #[error("The archive version {version} is newer than the supported {ARCHIVE_VERSION}.")]
+    UnsupportedVersion { version: u32 },
+    #[error("The front matter of the note is invalid.")]
+    InvalidFrontMatter,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    name: String,
+    /// Trashed tags aren't archived, only their number is kept.
+    #[serde(default)]
+    dropped_trashed_tags: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedTag {
+    name: String,
+    /// The name of the note new notes with the tag start from.
+    template: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchivedImplication {
+    antecedent: String,
+    consequent: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct ArchivedLink {
+    from: String,
+    to: String,
+}
+
+/// The front matter of an archived note, each value written as JSON on its own line.
+#[derive(Default)]
+struct NoteHeader {
+    name: String,
+    tags: Vec<String>,
+    attachments: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct UnarchiveReport {
+    pub name: String,
+    pub notes: usize,
+    /// The (entry, reason) of everything that couldn't be restored.
+    pub skipped: Vec<(String, String)>,
+    pub mismatched_links: usize,
+    pub dropped_trashed_tags: usize,
+}
+
+/// Write the notes, tags, implications, links, settings and attachments of a notebook in a zip,
+/// trashed tags are left out. Returns the number of archived notes.
+pub fn archive(notebook: &Notebook, path: &Path) -> Result<usize> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default();
+
+    let dropped_trashed_tags = Tag::list_trash(notebook.db())?.len();
+    if dropped_trashed_tags > 0 {
+        warn!("Leave the {dropped_trashed_tags} trashed tag(s) out of the archive.");
+    }
+    zip.start_file(MANIFEST_ENTRY, options)?;
+    serde_json::to_writer_pretty(
+        &mut zip,
+        &Manifest {
+            version: ARCHIVE_VERSION,
+            name: notebook.name.clone(),
+            dropped_trashed_tags,
+        },
+    )?;
+
+    let tags = Tag::search_by_name("", notebook.db())?;
+    let mut implications = Vec::new();
+    for tag in &tags {
+        implications.extend(
+            tag.consequents(notebook.db())?
+                .into_iter()
+                .map(|consequent| ArchivedImplication {
+                    antecedent: tag.name.clone(),
+                    consequent: consequent.name,
+                }),
+        );
+    }
+    let tags = tags
+        .into_iter()
+        .map(|tag| {
+            Ok(ArchivedTag {
+                template: tag.template(notebook.db())?.map(|note| note.name),
+                name: tag.name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    zip.start_file(TAGS_ENTRY, options)?;
+    serde_json::to_writer_pretty(&mut zip, &tags)?;
+    zip.start_file(IMPLICATIONS_ENTRY, options)?;
+    serde_json::to_writer_pretty(&mut zip, &implications)?;
+
+    let settings = notebook.settings();
+    let settings: BTreeMap<&str, &str> = settings.values().collect();
+    zip.start_file(SETTINGS_ENTRY, options)?;
+    serde_json::to_writer_pretty(&mut zip, &settings)?;
+
+    let summaries = NoteSummary::search_by_name("", NotesOrder::Name, None, notebook.db())?;
+    let mut links = Vec::new();
+    for (i, summary) in summaries.iter().enumerate() {
+        let Some(note) = Note::load_by_id(summary.id, notebook.db())? else {
+            continue;
+        };
+        let attachments = Attachment::list(note.id, notebook.db())?;
+
+        links.extend(
+            Note::list_links(note.id, notebook.db())?
+                .into_iter()
+                .map(|link| ArchivedLink {
+                    from: note.name.clone(),
+                    to: link.to,
+                }),
+        );
+
+        for (j, attachment) in attachments.iter().enumerate() {
+            zip.start_file(format!("{ATTACHMENTS_DIR}{i:05}/{j}"), options)?;
+            zip.write_all(&attachment.content(notebook.attachments_dir().as_path())?)?;
+        }
+
+        let header = NoteHeader {
+            name: note.name.clone(),
+            tags: Note::list_tags(note.id, notebook.db())?
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect(),
+            attachments: attachments
+                .into_iter()
+                .map(|attachment| attachment.filename)
+                .collect(),
+        };
+        zip.start_file(format!("{NOTES_DIR}{i:05}.md"), options)?;
+        zip.write_all(header.to_front_matter()?.as_bytes())?;
+        zip.write_all(note.content.as_bytes())?;
+    }
+
+    links.sort();
+    zip.start_file(LINKS_ENTRY, options)?;
+    serde_json::to_writer_pretty(&mut zip, &links)?;
+
+    zip.finish()?;
+    Ok(summaries.len())
+}
+
+/// Rebuild a notebook in `dir` from an archive, named after the archived one unless `name` is given.
+/// The new notebook is removed if the archive can't be read through.
+pub fn unarchive(path: &Path, name: Option<&str>, dir: &Path) -> Result<UnarchiveReport> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let manifest: Manifest = match zip.by_name(MANIFEST_ENTRY) {
+        Ok(entry) => serde_json::from_reader(entry)?,
+        Err(_) => return Err(ArchiveError::MissingManifest.into()),
+    };
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(ArchiveError::UnsupportedVersion {
+            version: manifest.version,
+        }
+        .into());
+    }
+
+    let name = name.unwrap_or(manifest.name.as_str()).to_owned();
+    let notebook = Notebook::new_notebook(name.as_str(), dir)?;
+    let mut report = UnarchiveReport {
+        name,
+        dropped_trashed_tags: manifest.dropped_trashed_tags,
+        ..UnarchiveReport::default()
+    };
+
+    if let Err(err) = restore(&mut zip, manifest.version, &notebook, &mut report) {
+        warn!("Remove the partially restored notebook {}.", report.name);
+        drop(notebook);
+        Notebook::delete_notebook(report.name.as_str(), dir)?;
+        return Err(err);
+    }
+
+    Ok(report)
+}
+
+fn restore(
+    zip: &mut ZipArchive<File>,
+    version: u32,
+    notebook: &Notebook,
+    report: &mut UnarchiveReport,
+) -> Result<()> {
+    let tags: Vec<ArchivedTag> = if version < ARCHIVED_TEMPLATES_VERSION {
+        serde_json::from_reader::<_, Vec<String>>(zip.by_name(TAGS_ENTRY)?)?
+            .into_iter()
+            .map(|name| ArchivedTag {
+                name,
+                template: None,
+            })
+            .collect()
+    } else {
+        serde_json::from_reader(zip.by_name(TAGS_ENTRY)?)?
+    };
+    for tag in &tags {
+        if let Some(error) = Tag::validate_name(tag.name.as_str(), notebook.db())? {
+            report
+                .skipped
+                .push((format!("tag {:?}", tag.name), error.to_string()));
+        } else {
+            Tag::new(tag.name.as_str(), notebook.db())?;
+        }
+    }
+
+    let settings: BTreeMap<String, String> = serde_json::from_reader(zip.by_name(SETTINGS_ENTRY)?)?;
+    for (key, value) in settings {
+        if let Err(err) = notebook.set_setting(key.as_str(), value.as_str()) {
+            report
+                .skipped
+                .push((format!("setting {key:?}"), err.to_string()));
+        }
+    }
+
+    let mut note_entries: Vec<String> = zip
+        .file_names()
+        .filter(|entry| {
+            entry.starts_with(NOTES_DIR)
+                && Path::new(entry)
+                    .extension()
+                    .is_some_and(|extension| extension == "md")
+        })
+        .map(str::to_owned)
+        .collect();
+    note_entries.sort();
+
+    for entry in note_entries {
+        let mut text = String::new();
+        zip.by_name(entry.as_str())?.read_to_string(&mut text)?;
+        let Some((header, content)) = NoteHeader::from_front_matter(text.as_str()) else {
+            report
+                .skipped
+                .push((entry, ArchiveError::InvalidFrontMatter.to_string()));
+            continue;
+        };
+
+        if let Some(error) = Note::validate_name(header.name.as_str(), notebook.db())? {
+            report.skipped.push((entry, error.to_string()));
+            continue;
+        }
+
+        info!("Restore note {}.", header.name);
+        let mut note_data = NoteData::try_from_database(
+            Note::new(header.name.as_str(), content.to_owned(), notebook.db())?,
+            notebook.db(),
+        )?;
+        for tag_name in &header.tags {
+            let tag = match Tag::load_by_name(tag_name.as_str(), notebook.db())? {
+                Some(tag) => tag,
+                None => Tag::new(tag_name.as_str(), notebook.db())?,
+            };
+            note_data.add_tag(tag, notebook.db())?;
+        }
+
+        let attachments_prefix = entry
+            .replacen(NOTES_DIR, ATTACHMENTS_DIR, 1)
+            .replace(".md", "/");
+        for (i, filename) in header.attachments.iter().enumerate() {
+            let attachment_entry = format!("{attachments_prefix}{i}");
+            let Ok(mut file) = zip.by_name(attachment_entry.as_str()) else {
+                report
+                    .skipped
+                    .push((attachment_entry, String::from("the attachment is missing")));
+                continue;
+            };
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            drop(file);
+
+            if let Err(err) = Attachment::store(
+                note_data.note.id,
+                filename.as_str(),
+                &content,
+                notebook.attachments_dir().as_path(),
+                notebook.db(),
+            ) {
+                report.skipped.push((attachment_entry, err.to_string()));
+            }
+        }
+
+        report.notes += 1;
+    }
+
+    // Restored after the notes they refer to
+    for ArchivedTag { name, template } in tags {
+        let Some(template) = template else {
+            continue;
+        };
+        let entry = format!("template {template:?} of tag {name:?}");
+        match (
+            Tag::load_by_name(name.as_str(), notebook.db())?,
+            Note::load_by_name(template.as_str(), notebook.db())?,
+        ) {
+            (Some(tag), Some(note)) => tag.set_template(Some(note.id), notebook.db())?,
+            _ => report
+                .skipped
+                .push((entry, String::from("the tag or the note doesn't exist"))),
+        }
+    }
+
+    // Restored after the notes, which already carry the tags they implied
+    let implications: Vec<ArchivedImplication> = match zip.by_name(IMPLICATIONS_ENTRY) {
+        Ok(entry) => serde_json::from_reader(entry)?,
+        Err(_) => Vec::new(),
+    };
+    for ArchivedImplication {
+        antecedent,
+        consequent,
+    } in implications
+    {
+        let entry = format!("implication {antecedent:?} → {consequent:?}");
+        match (
+            Tag::load_by_name(antecedent.as_str(), notebook.db())?,
+            Tag::load_by_name(consequent.as_str(), notebook.db())?,
+        ) {
+            (Some(antecedent), Some(consequent)) => {
+                if let Some(error) = antecedent.validate_implication(&consequent, notebook.db())? {
+                    report.skipped.push((entry, error.to_string()));
+                } else {
+                    antecedent.add_implication(&consequent, notebook.db())?;
+                }
+            }
+            _ => report
+                .skipped
+                .push((entry, String::from("the tag doesn't exist"))),
+        }
+    }
+
+    NotesTable::reindex_links(notebook.db())?;
+
+    // Links are rebuilt from the content, the archived ones only serve as a check
+    let archived_links: BTreeSet<ArchivedLink> =
+        serde_json::from_reader(zip.by_name(LINKS_ENTRY)?)?;
+    let mut restored_links = BTreeSet::new();
+    for summary in NoteSummary::search_by_name("", NotesOrder::Name, None, notebook.db())? {
+        restored_links.extend(
+            Note::list_links(summary.id, notebook.db())?
+                .into_iter()
+                .map(|link| ArchivedLink {
+                    from: summary.name.clone(),
+                    to: link.to,
+                }),
+        );
+    }
+    report.mismatched_links = archived_links.symmetric_difference(&restored_links).count();
+
+    Ok(())
+}
+
+impl NoteHeader {
+    fn to_front_matter(&self) -> Result<String> {
+        Ok(format!(
+            "---\nname: {}\ntags: {}\nattachments: {}\n---\n",
+            serde_json::to_string(&self.name)?,
+            serde_json::to_string(&self.tags)?,
+            serde_json::to_string(&self.attachments)?,
+        ))
+    }
+
+    fn from_front_matter(text: &str) -> Option<(Self, &str)> {
+        let (front_matter, content) = text.strip_prefix("---\n")?.split_once("\n---\n")?;
+
+        let mut header = NoteHeader::default();
+        for line in front_matter.lines() {
+            let (key, value) = line.split_once(": ")?;
+            match key {
+                "name" => header.name = serde_json::from_str(value).ok()?,
+                "tags" => header.tags = serde_json::from_str(value).ok()?,
+                "attachments" => header.attachments = serde_json::from_str(value).ok()?,
+                _ => {}
+            }
+        }
+
+        Some((header, content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    // The notes with their content and tags, the tags with their template, the implications
+    // and the links of a notebook.
+    type Snapshot = (
+        BTreeSet<(String, String, Vec<String>)>,
+        BTreeSet<(String, Option<String>)>,
+        BTreeSet<(String, String)>,
+        BTreeSet<(String, String)>,
+    );
+
+    fn snapshot(notebook: &Notebook) -> Snapshot {
+        let db = notebook.db();
+        let mut notes = BTreeSet::new();
+        let mut links = BTreeSet::new();
+        for summary in NoteSummary::search_by_name("", NotesOrder::Name, None, db).unwrap() {
+            let note = Note::load_by_id(summary.id, db).unwrap().unwrap();
+            let tags = Note::list_tags(note.id, db).unwrap();
+            links.extend(
+                Note::list_links(note.id, db)
+                    .unwrap()
+                    .into_iter()
+                    .map(|link| (note.name.clone(), link.to)),
+            );
+            notes.insert((
+                note.name,
+                note.content,
+                tags.into_iter().map(|tag| tag.name).collect(),
+            ));
+        }
+
+        let mut tags = BTreeSet::new();
+        let mut implications = BTreeSet::new();
+        for tag in Tag::search_by_name("", db).unwrap() {
+            implications.extend(
+                tag.consequents(db)
+                    .unwrap()
+                    .into_iter()
+                    .map(|consequent| (tag.name.clone(), consequent.name)),
+            );
+            let template = tag.template(db).unwrap().map(|note| note.name);
+            tags.insert((tag.name, template));
+        }
+
+        (notes, tags, implications, links)
+    }
+
+    #[test]
+    fn archives_round_trip() {
+        let dir = env::temp_dir().join(format!("foucault-test-{}-archive", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        let notebook = Notebook::new_notebook("original", &dir).unwrap();
+        let db = notebook.db();
+        let project = Tag::new("project", db).unwrap();
+        let work = Tag::new("work", db).unwrap();
+        project.add_implication(&work, db).unwrap();
+        Tag::new("forgotten", db).unwrap().trash(db).unwrap();
+
+        let template = Note::new("Template", String::from("# {{title}}"), db).unwrap();
+        project.set_template(Some(template.id), db).unwrap();
+        let mut plan = NoteData::try_from_database(
+            Note::new("Plan", String::from("See [[Notes/2024 été]]."), db).unwrap(),
+            db,
+        )
+        .unwrap();
+        plan.add_tag(project.clone(), db).unwrap();
+        Note::new("Notes/2024 été", String::from("Back to [[Plan]]."), db).unwrap();
+        NotesTable::reindex_links(db).unwrap();
+
+        let file = dir.join("original.zip");
+        assert_eq!(archive(&notebook, &file).unwrap(), 3);
+        let report = unarchive(&file, Some("restored"), &dir).unwrap();
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.mismatched_links, 0);
+        assert_eq!(report.dropped_trashed_tags, 1);
+
+        let restored = Notebook::open_notebook("restored", &dir).unwrap();
+        let original = snapshot(&notebook);
+        assert_eq!(original.1.len(), 2);
+        assert_eq!(original.3.len(), 2);
+        assert_eq!(snapshot(&restored), original);
+
+        drop((notebook, restored));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
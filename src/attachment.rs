@@ -0,0 +1,360 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::{env, io};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use rusqlite::{Connection, Row};
+use sea_query::{
+    ColumnDef, Expr, ForeignKey, ForeignKeyAction, Iden, Order, Query, SqliteQueryBuilder, Table,
+};
+
+use crate::helpers::DiscardResult;
+use crate::note::{NotesCharacters, NotesTable};
+
+pub const MAX_ATTACHMENT_SIZE: u64 = 1 << 26;
+
+#[derive(Iden)]
+pub struct AttachmentsTable;
+
+#[derive(Iden, Clone, Copy, Debug)]
+pub enum AttachmentsCharacters {
+    Id,
+    NoteId,
+    Filename,
+    Sha256,
+    Size,
+    CreatedAt,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub filename: String,
+    pub sha256: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Error, Debug)]
+pub enum AttachmentError {
+    #[error("No file was found at {path:?}")]
+    FileNotFound { path: PathBuf },
+    #[error("The file is larger than {} MiB", MAX_ATTACHMENT_SIZE >> 20)]
+    TooLarge,
+}
+
+impl Attachment {
+    pub fn attach(note_id: i64, file: &Path, dir: &Path, db: &Connection) -> Result<Self> {
+        if !file.is_file() {
+            return Err(AttachmentError::FileNotFound {
+                path: file.to_path_buf(),
+            }
+            .into());
+        }
+        if fs::metadata(file)?.len() > MAX_ATTACHMENT_SIZE {
+            return Err(AttachmentError::TooLarge.into());
+        }
+
+        let filename = file.file_name().map_or_else(
+            || String::from("attachment"),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        Attachment::store(note_id, filename.as_str(), &fs::read(file)?, dir, db)
+    }
+
+    /// Blobs are named by the hash of their content, so identical files are stored once.
+    pub fn store(
+        note_id: i64,
+        filename: &str,
+        content: &[u8],
+        dir: &Path,
+        db: &Connection,
+    ) -> Result<Self> {
+        if content.len() as u64 > MAX_ATTACHMENT_SIZE {
+            return Err(AttachmentError::TooLarge.into());
+        }
+
+        let sha256 = Sha256::digest(content)
+            .iter()
+            .fold(String::new(), |mut hash, byte| {
+                write!(hash, "{byte:02x}").expect("Writing to a string can't fail");
+                hash
+            });
+
+        let blob = dir.join(sha256.as_str());
+        if !blob.exists() {
+            fs::create_dir_all(dir)?;
+            // Write the blob aside first so an interrupted copy never looks complete
+            let partial = dir.join(format!("{sha256}.part"));
+            if let Err(err) =
+                fs::write(&partial, content).and_then(|()| fs::rename(&partial, &blob))
+            {
+                fs::remove_file(&partial).ok();
+                return Err(err.into());
+            }
+        }
+
+        let size = content.len() as u64;
+        let created_at = Utc::now();
+        db.execute_batch(
+            Query::insert()
+                .into_table(AttachmentsTable)
+                .columns([
+                    AttachmentsCharacters::NoteId,
+                    AttachmentsCharacters::Filename,
+                    AttachmentsCharacters::Sha256,
+                    AttachmentsCharacters::Size,
+                    AttachmentsCharacters::CreatedAt,
+                ])
+                .values([
+                    note_id.into(),
+                    filename.into(),
+                    sha256.as_str().into(),
+                    size.into(),
+                    created_at.timestamp().into(),
+                ])?
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+
+        Ok(Attachment {
+            id: db.last_insert_rowid(),
+            filename: filename.to_owned(),
+            sha256,
+            size,
+            created_at,
+        })
+    }
+
+    /// Attachments are ordered by filename.
+    pub fn list(note_id: i64, db: &Connection) -> Result<Vec<Self>> {
+        db.prepare(
+            Query::select()
+                .from(AttachmentsTable)
+                .columns([
+                    AttachmentsCharacters::Id,
+                    AttachmentsCharacters::Filename,
+                    AttachmentsCharacters::Sha256,
+                    AttachmentsCharacters::Size,
+                    AttachmentsCharacters::CreatedAt,
+                ])
+                .and_where(Expr::col(AttachmentsCharacters::NoteId).eq(note_id))
+                .order_by(AttachmentsCharacters::Filename, Order::Asc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], Attachment::from_row)?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    pub fn content(&self, dir: &Path) -> Result<Vec<u8>> {
+        fs::read(dir.join(self.sha256.as_str())).map_err(anyhow::Error::from)
+    }
+
+    /// Copy the attachment to a temporary file and open it with the default application.
+    pub fn open(&self, dir: &Path) -> Result<()> {
+        let file = env::temp_dir().join(format!("foucault-{}-{}", self.id, self.filename));
+        fs::copy(dir.join(self.sha256.as_str()), &file)?;
+        opener::open(file.as_path())?;
+        Ok(())
+    }
+
+    pub fn detach(self, dir: &Path, db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Query::delete()
+                .from_table(AttachmentsTable)
+                .and_where(Expr::col(AttachmentsCharacters::Id).eq(self.id))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+        Attachment::collect_garbage(dir, db).discard_result()
+    }
+
+    /// Remove the blobs, and leftover partial copies, no attachment refers to.
+    /// Returns the number of files removed.
+    pub fn collect_garbage(dir: &Path, db: &Connection) -> Result<usize> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        let referenced: HashSet<String> = db
+            .prepare(
+                Query::select()
+                    .distinct()
+                    .from(AttachmentsTable)
+                    .column(AttachmentsCharacters::Sha256)
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            let is_referenced = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| referenced.contains(name));
+            if path.is_file() && !is_referenced {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    // Expects the id, filename, hash, size and creation time columns in that order.
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Attachment {
+            id: row.get(0)?,
+            filename: row.get(1)?,
+            sha256: row.get(2)?,
+            size: row.get(3)?,
+            created_at: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+        })
+    }
+}
+
+impl AttachmentsTable {
+    pub fn create(db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Table::create()
+                .if_not_exists()
+                .table(AttachmentsTable)
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::Id)
+                        .integer()
+                        .primary_key()
+                        .auto_increment(),
+                )
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::NoteId)
+                        .integer()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::Filename)
+                        .string()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::Sha256)
+                        .string()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::Size)
+                        .integer()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(AttachmentsCharacters::CreatedAt)
+                        .integer()
+                        .not_null(),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(AttachmentsTable, AttachmentsCharacters::NoteId)
+                        .to(NotesTable, NotesCharacters::Id)
+                        .on_update(ForeignKeyAction::Cascade)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .build(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .discard_result()
+    }
+}
+
+/// Sizes are shown with binary units.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut unit = 0;
+    let mut scaled = size;
+    while scaled >= 1024 && unit < UNITS.len() - 1 {
+        scaled /= 1024;
+        unit += 1;
+    }
+    format!("{scaled} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::note::Note;
+    use crate::notebook::test_database;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("foucault-test-{}-{name}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn oversized_files_are_refused() {
+        let db = test_database();
+        let dir = test_dir("oversized");
+        let note = Note::new("Note", String::new(), &db).unwrap();
+
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("large.bin");
+        fs::File::create(&file)
+            .unwrap()
+            .set_len(MAX_ATTACHMENT_SIZE + 1)
+            .unwrap();
+        let attached = Attachment::attach(note.id, &file, &dir.join("blobs"), &db).unwrap_err();
+        assert!(matches!(
+            attached.downcast_ref::<AttachmentError>(),
+            Some(AttachmentError::TooLarge)
+        ));
+
+        let content = vec![0; usize::try_from(MAX_ATTACHMENT_SIZE).unwrap() + 1];
+        let stored = Attachment::store(note.id, "large.bin", &content, &dir, &db).unwrap_err();
+        assert!(matches!(
+            stored.downcast_ref::<AttachmentError>(),
+            Some(AttachmentError::TooLarge)
+        ));
+
+        assert!(!dir.join("blobs").exists());
+        assert!(Attachment::list(note.id, &db).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn blobs_are_removed_with_their_last_attachment() {
+        let db = test_database();
+        let dir = test_dir("cleanup");
+        let first = Note::new("First", String::new(), &db).unwrap();
+        let second = Note::new("Second", String::new(), &db).unwrap();
+
+        let attachment = Attachment::store(first.id, "a.txt", b"shared", &dir, &db).unwrap();
+        Attachment::store(second.id, "b.txt", b"shared", &dir, &db).unwrap();
+        let blob = dir.join(attachment.sha256.as_str());
+        fs::write(dir.join("interrupted.part"), b"partial").unwrap();
+
+        attachment.detach(&dir, &db).unwrap();
+        assert!(blob.exists());
+        assert!(!dir.join("interrupted.part").exists());
+
+        let second_id = second.id;
+        second.delete(&db).unwrap();
+        assert!(Attachment::list(second_id, &db).unwrap().is_empty());
+        assert!(blob.exists());
+        assert_eq!(Attachment::collect_garbage(&dir, &db).unwrap(), 1);
+        assert!(!blob.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::{stdout, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::Result;
+use log::info;
+
+// Tried in order, the first one available wins.
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["clip.exe"],
+];
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Copy through the system clipboard tools, or ask the terminal to do it when there are none.
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for command in CLIPBOARD_COMMANDS {
+        if pipe_to(command, text) {
+            info!("Copy {} bytes with {}.", text.len(), command[0]);
+            return Ok(());
+        }
+    }
+
+    // The OSC 52 sequence is understood by most terminals, even over ssh
+    info!("Copy {} bytes through the terminal.", text.len());
+    let mut stdout = stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn pipe_to(command: &[&str], text: &str) -> bool {
+    let Ok(mut child) = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    child.wait().is_ok_and(|status| status.success()) && written
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            encoded.push(if i <= chunk.len() {
+                BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    encoded
+}
@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::{env, fs};
+
+use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, TimeZone};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml::{Table, Value};
+
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, BorderType, Borders, Padding};
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+const NOTEBOOKS_TABLE: &str = "notebooks";
+const EDITOR_KEY: &str = "editing.editor";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub layout: LayoutConfig,
+    pub editing: EditingConfig,
+    pub format: FormatConfig,
+    pub theme: ThemeConfig,
+    #[serde(skip)]
+    pub notebook: Option<String>,
+    #[serde(skip)]
+    pub sources: BTreeMap<String, ConfigSource>,
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    Notebook,
+    Environment,
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub padding: u16,
+    pub borders: BorderKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderKind {
+    Rounded,
+    Plain,
+    Double,
+    Thick,
+    None,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EditingConfig {
+    pub confirm_discard: bool,
+    pub confirm_quit: bool,
+    pub fork_tags: bool,
+    pub editor: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    pub date_format: String,
+    pub thousands_separator: String,
+}
+
+/// Colors are ratatui color names (`white`, `lightblue`, ...), `#rrggbb` or indexes.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub selection_fg: String,
+    pub selection_bg: String,
+    pub highlight_symbol: String,
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("No config directory is available.")]
+    NoConfigDirectory,
+    #[error("No editor is configured, set editing.editor or $EDITOR.")]
+    NoEditor,
+    #[error("Config flags are written KEY=VALUE, got {flag:?}.")]
+    InvalidFlag { flag: String },
+    #[error("Unknown config key {key}, valid keys are {valid}.")]
+    UnknownKey { key: String, valid: String },
+}
+
+impl Config {
+    pub fn file_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("foucault").join("config.toml"))
+    }
+
+    /// Merge, from lowest to highest precedence, the defaults, the global values of the config
+    /// file, its `[notebooks.<name>]` overrides, the environment and the `KEY=VALUE` flags.
+    pub fn load(notebook: Option<&str>, flags: &[String]) -> Result<Self> {
+        let mut values = flatten(&Table::try_from(Config::default())?);
+        let mut sources: BTreeMap<String, ConfigSource> = values
+            .keys()
+            .map(|key| (key.clone(), ConfigSource::Default))
+            .collect();
+        let mut unknown_keys = Vec::new();
+
+        let mut file = Config::read_file()?;
+        let overrides = file.remove(NOTEBOOKS_TABLE);
+        let mut layers = vec![(flatten(&file), ConfigSource::File)];
+        if let Some(overrides) = notebook.and_then(|name| {
+            overrides
+                .as_ref()
+                .and_then(|overrides| overrides.get(name))
+                .and_then(Value::as_table)
+        }) {
+            layers.push((flatten(overrides), ConfigSource::Notebook));
+        }
+
+        let mut environment = Table::new();
+        for key in sources.keys() {
+            if let Some((_, value)) = env_value(key.as_str()) {
+                environment.insert(key.clone(), parse_value(value.as_str(), &values[key]));
+            }
+        }
+        layers.push((environment.into_iter().collect(), ConfigSource::Environment));
+
+        let mut flag_values = BTreeMap::new();
+        for flag in flags {
+            let (key, value) = flag
+                .split_once('=')
+                .ok_or_else(|| ConfigError::InvalidFlag { flag: flag.clone() })?;
+            let key = key.trim();
+            let Some(default) = values.get(key) else {
+                return Err(ConfigError::UnknownKey {
+                    key: key.to_owned(),
+                    valid: valid_keys(&values),
+                }
+                .into());
+            };
+            flag_values.insert(key.to_owned(), parse_value(value.trim(), default));
+        }
+        layers.push((flag_values, ConfigSource::Flag));
+
+        for (layer, source) in layers {
+            for (key, value) in layer {
+                if let Some(slot) = values.get_mut(&key) {
+                    *slot = value;
+                    sources.insert(key, source);
+                } else {
+                    unknown_keys.push(key);
+                }
+            }
+        }
+
+        if !unknown_keys.is_empty() {
+            warn!(
+                "Unknown config keys {}, valid keys are {}.",
+                unknown_keys.join(", "),
+                valid_keys(&values)
+            );
+        }
+
+        let mut config: Config = unflatten(values).try_into()?;
+        for color in [&config.theme.selection_fg, &config.theme.selection_bg] {
+            if Color::from_str(color).is_err() {
+                warn!("Unknown color {color:?}, the default selection colors are used.");
+            }
+        }
+        config.notebook = notebook.map(str::to_owned);
+        config.sources = sources;
+        config.unknown_keys = unknown_keys;
+        Ok(config)
+    }
+
+    /// Each value is written back where it was loaded from, values set by the environment
+    /// or flags are left out.
+    pub fn save(&self) -> Result<()> {
+        let path = Config::file_path().ok_or(ConfigError::NoConfigDirectory)?;
+        let mut file = Config::read_file()?;
+
+        for (key, value) in flatten(&Table::try_from(self)?) {
+            let (section, name) = key
+                .split_once('.')
+                .expect("Config keys should be in a section");
+            match (self.source(key.as_str()), self.notebook.as_deref()) {
+                (ConfigSource::Environment | ConfigSource::Flag, _) => {}
+                (ConfigSource::Notebook, Some(notebook)) => {
+                    insert_value(
+                        &mut file,
+                        &[NOTEBOOKS_TABLE, notebook, section, name],
+                        value,
+                    );
+                }
+                _ => insert_value(&mut file, &[section, name], value),
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        info!("Save config to {}.", path.display());
+        fs::write(path, toml::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+
+    pub fn source(&self, key: &str) -> ConfigSource {
+        self.sources
+            .get(key)
+            .copied()
+            .unwrap_or(ConfigSource::Default)
+    }
+
+    /// The effective values with where they come from, one `key = value` per line.
+    pub fn describe(&self) -> Result<String> {
+        let mut description = String::new();
+        for (key, value) in flatten(&Table::try_from(self)?) {
+            let source = match (self.source(key.as_str()), self.notebook.as_deref()) {
+                (ConfigSource::Notebook, Some(notebook)) => format!("notebook {notebook}"),
+                (ConfigSource::Environment, _) => match env_value(key.as_str()) {
+                    Some((variable, _)) => format!("environment {variable}"),
+                    None => String::from("environment"),
+                },
+                (source, _) => source.name().to_owned(),
+            };
+            writeln!(description, "{key} = {value}  # {source}")?;
+        }
+        Ok(description)
+    }
+
+    fn read_file() -> Result<Table> {
+        match Config::file_path() {
+            Some(file) if file.exists() => {
+                info!("Load config from {}.", file.display());
+                Ok(toml::from_str(fs::read_to_string(file)?.as_str())?)
+            }
+            _ => Ok(Table::new()),
+        }
+    }
+}
+
+impl ConfigSource {
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::File => "config file",
+            ConfigSource::Notebook => "notebook",
+            ConfigSource::Environment => "environment",
+            ConfigSource::Flag => "flag",
+        }
+    }
+}
+
+fn flatten(table: &Table) -> BTreeMap<String, Value> {
+    let mut values = BTreeMap::new();
+    for (section, value) in table {
+        match value {
+            Value::Table(section_table) => {
+                for (name, value) in section_table {
+                    values.insert(format!("{section}.{name}"), value.clone());
+                }
+            }
+            _ => {
+                values.insert(section.clone(), value.clone());
+            }
+        }
+    }
+    values
+}
+
+fn unflatten(values: BTreeMap<String, Value>) -> Table {
+    let mut table = Table::new();
+    for (key, value) in values {
+        let path: Vec<&str> = key.split('.').collect();
+        insert_value(&mut table, &path, value);
+    }
+    table
+}
+
+fn insert_value(table: &mut Table, path: &[&str], value: Value) {
+    let Some((name, sections)) = path.split_last() else {
+        return;
+    };
+
+    let mut table = table;
+    for section in sections {
+        let entry = table
+            .entry(*section)
+            .or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        table = entry
+            .as_table_mut()
+            .expect("The entry was just made a table");
+    }
+    table.insert((*name).to_owned(), value);
+}
+
+/// Text values are taken as is, others are parsed as TOML so `FOUCAULT_LAYOUT_PADDING=2` is a number.
+fn parse_value(text: &str, default: &Value) -> Value {
+    if default.is_str() {
+        return Value::String(text.to_owned());
+    }
+    toml::from_str::<Table>(format!("value = {text}").as_str())
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(text.to_owned()))
+}
+
+/// The variable setting a key, `FOUCAULT_<SECTION>_<NAME>` or `EDITOR` for the editor, and its value.
+fn env_value(key: &str) -> Option<(String, String)> {
+    let variable = format!("FOUCAULT_{}", key.replace('.', "_").to_uppercase());
+    let fallback = (key == EDITOR_KEY).then(|| String::from("EDITOR"));
+    [Some(variable), fallback]
+        .into_iter()
+        .flatten()
+        .find_map(|variable| {
+            env::var(variable.as_str())
+                .ok()
+                .map(|value| (variable, value))
+        })
+}
+
+fn valid_keys(values: &BTreeMap<String, Value>) -> String {
+    values.keys().cloned().collect::<Vec<_>>().join(", ")
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            padding: 1,
+            borders: BorderKind::Rounded,
+        }
+    }
+}
+
+impl LayoutConfig {
+    pub fn frame_size(self) -> u16 {
+        let borders = if self.borders == BorderKind::None {
+            0
+        } else {
+            2
+        };
+        2 * self.padding + borders
+    }
+
+    pub fn block(self) -> Block<'static> {
+        let block = Block::default().padding(Padding::uniform(self.padding));
+
+        match self.borders {
+            BorderKind::Rounded => block.borders(Borders::ALL).border_type(BorderType::Rounded),
+            BorderKind::Plain => block.borders(Borders::ALL).border_type(BorderType::Plain),
+            BorderKind::Double => block.borders(Borders::ALL).border_type(BorderType::Double),
+            BorderKind::Thick => block.borders(Borders::ALL).border_type(BorderType::Thick),
+            BorderKind::None => block,
+        }
+    }
+}
+
+impl BorderKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            BorderKind::Rounded => "rounded",
+            BorderKind::Plain => "plain",
+            BorderKind::Double => "double",
+            BorderKind::Thick => "thick",
+            BorderKind::None => "none",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            BorderKind::Rounded => BorderKind::Plain,
+            BorderKind::Plain => BorderKind::Double,
+            BorderKind::Double => BorderKind::Thick,
+            BorderKind::Thick => BorderKind::None,
+            BorderKind::None => BorderKind::Rounded,
+        }
+    }
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            thousands_separator: String::new(),
+        }
+    }
+}
+
+impl FormatConfig {
+    pub fn is_valid_date_format(format: &str) -> bool {
+        !StrftimeItems::new(format).any(|item| item == Item::Error)
+    }
+
+    pub fn date<Tz>(&self, date: &DateTime<Tz>) -> String
+    where
+        Tz: TimeZone,
+        Tz::Offset: Display,
+    {
+        let mut formatted = String::new();
+        if write!(formatted, "{}", date.format(self.date_format.as_str())).is_err() {
+            formatted = date.format(DEFAULT_DATE_FORMAT).to_string();
+        }
+        formatted
+    }
+
+    pub fn count(&self, count: impl Into<u64>) -> String {
+        let digits = count.into().to_string();
+        if self.thousands_separator.is_empty() {
+            return digits;
+        }
+
+        let mut formatted = String::with_capacity(digits.len() * 2);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                formatted.push_str(self.thousands_separator.as_str());
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+}
+
+impl Default for EditingConfig {
+    fn default() -> Self {
+        EditingConfig {
+            confirm_discard: true,
+            confirm_quit: true,
+            fork_tags: true,
+            editor: String::new(),
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            selection_fg: String::from("black"),
+            selection_bg: String::from("white"),
+            highlight_symbol: String::from(">> "),
+        }
+    }
+}
+
+impl ThemeConfig {
+    pub fn selection_style(&self) -> Style {
+        Style::default()
+            .fg(Color::from_str(self.selection_fg.as_str()).unwrap_or(Color::Black))
+            .bg(Color::from_str(self.selection_bg.as_str()).unwrap_or(Color::White))
+    }
+}
@@ -14,10 +14,11 @@ use ratatui::prelude::CrosstermBackend;
 use ratatui::widgets::Clear;
 use ratatui::Terminal;
 
+use crate::config::Config;
 use crate::notebook::Notebook;
 use crate::states::State;
 
-pub fn explore(notebook: &Notebook) -> Result<()> {
+pub fn explore(notebook: &Notebook, mut config: Config, initial_state: State) -> Result<()> {
     info!("Explore notebook : {}", notebook.name);
 
     enable_raw_mode().expect("Prepare terminal");
@@ -33,16 +34,19 @@ pub fn explore(notebook: &Notebook) -> Result<()> {
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
     let mut forced_redraw = false;
 
-    let mut state = State::Nothing;
+    let mut state = initial_state;
 
     loop {
         {
             if event::poll(Duration::from_millis(50))? {
                 if let Event::Key(key) = event::read()? {
                     if key.kind == KeyEventKind::Press {
-                        state = state.run(key, notebook, &mut forced_redraw)?;
+                        state = state.run(key, notebook, &mut config, &mut forced_redraw)?;
+                        notebook.refresh_note_count()?;
                     }
                 }
+            } else {
+                state.tick(notebook)?;
             }
 
             if matches!(state, State::Exit) {
@@ -56,7 +60,7 @@ pub fn explore(notebook: &Notebook) -> Result<()> {
             }
             forced_redraw = false;
 
-            state.draw(notebook, &mut terminal)?;
+            state.draw(notebook, &config, &mut terminal)?;
         }
     }
 
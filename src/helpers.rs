@@ -7,6 +7,8 @@ use ratatui::widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph};
 use ratatui::Frame;
 
 use rusqlite::Connection;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 pub fn create_popup_proportion(proportion: (u16, u16), rect: Rect) -> Rect {
     let vertical = Layout::new(
@@ -104,14 +106,32 @@ pub fn draw_text_prompt(
     title: &str,
     text: &str,
     valid: bool,
+    message: Option<&str>,
     main_rect: ratatui::prelude::Rect,
-) {
-    let popup_area = create_popup_size((30, 5), main_rect);
+) -> Rect {
+    let popup_area = match message {
+        Some(message) => create_popup_size(
+            (
+                u16::try_from(message.graphemes(true).count() + 6)
+                    .unwrap_or(u16::MAX)
+                    .clamp(30, main_rect.width),
+                6,
+            ),
+            main_rect,
+        ),
+        None => create_popup_size((30, 5), main_rect),
+    };
 
-    let new_note_entry = Paragraph::new(Line::from(vec![
+    let mut lines = vec![Line::from(vec![
         Span::raw(text).style(Style::default().add_modifier(Modifier::UNDERLINED))
-    ]))
-    .block(
+    ])];
+    if let Some(message) = message {
+        lines.push(Line::from(vec![
+            Span::raw(message).style(Style::default().add_modifier(Modifier::DIM))
+        ]));
+    }
+
+    let new_note_entry = Paragraph::new(lines).block(
         Block::default()
             .title(title)
             .borders(Borders::ALL)
@@ -122,6 +142,8 @@ pub fn draw_text_prompt(
 
     frame.render_widget(Clear, popup_area);
     frame.render_widget(new_note_entry, popup_area);
+
+    popup_area
 }
 
 pub trait Capitalize<'a> {
@@ -148,6 +170,15 @@ pub trait TryFromDatabase<T>: Sized {
     fn try_from_database(value: T, db: &Connection) -> Result<Self>;
 }
 
+/// Whether the error comes from a UNIQUE constraint, e.g. when another instance inserted the same name first.
+pub fn is_unique_violation(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<rusqlite::Error>(),
+        Some(rusqlite::Error::SqliteFailure(failure, _))
+            if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+    )
+}
+
 pub trait DiscardResult {
     fn discard_result(self) -> Result<()>;
 }
@@ -160,3 +191,452 @@ where
         self.map_err(Into::<anyhow::Error>::into).map(|_| ())
     }
 }
+
+/// The Levenshtein distance between the two texts, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != *b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[derive(Clone, Debug)]
+pub struct EditableText {
+    text: String,
+    cursor: usize,
+}
+
+impl EditableText {
+    pub fn new(text: String) -> Self {
+        let cursor = text.graphemes(true).count();
+        Self { text, cursor }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.text.as_str()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn byte_index(&self, grapheme: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(grapheme)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index(self.cursor);
+        self.text.insert(index, c);
+        self.cursor = self.text[..index + c.len_utf8()].graphemes(true).count();
+    }
+
+    pub fn remove_char(&mut self) {
+        if self.cursor > 0 {
+            let start = self.byte_index(self.cursor - 1);
+            let end = self.byte_index(self.cursor);
+            self.text.replace_range(start..end, "");
+            self.cursor -= 1;
+        }
+    }
+
+    pub fn del_char(&mut self) {
+        if self.cursor < self.len() {
+            let start = self.byte_index(self.cursor);
+            let end = self.byte_index(self.cursor + 1);
+            self.text.replace_range(start..end, "");
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.cursor = self.len();
+    }
+
+    // The (start, length) of every line, counted in graphemes and without the line break.
+    pub fn lines_bounds(&self) -> Vec<(usize, usize)> {
+        let mut bounds = Vec::new();
+        let mut start = 0;
+        let mut length = 0;
+
+        for grapheme in self.text.graphemes(true) {
+            if grapheme == "\n" || grapheme == "\r\n" {
+                bounds.push((start, length));
+                start += length + 1;
+                length = 0;
+            } else {
+                length += 1;
+            }
+        }
+        bounds.push((start, length));
+
+        bounds
+    }
+
+    // The cursor position as a (line, column) pair, counted in graphemes.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        let bounds = self.lines_bounds();
+        let line = bounds
+            .iter()
+            .rposition(|(start, _)| *start <= self.cursor)
+            .unwrap_or(0);
+        (line, self.cursor - bounds[line].0)
+    }
+
+    pub fn move_up(&mut self) {
+        let (line, column) = self.cursor_position();
+        if line > 0 {
+            let (start, length) = self.lines_bounds()[line - 1];
+            self.cursor = start + column.min(length);
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        let (line, column) = self.cursor_position();
+        if let Some((start, length)) = self.lines_bounds().get(line + 1) {
+            self.cursor = start + column.min(*length);
+        }
+    }
+
+    /// The rows of the text wrapped at `width` terminal cells.
+    /// A line filling exactly `width` cells gets an empty row after it for the cursor.
+    pub fn wrapped_rows(&self, width: usize) -> Vec<WrappedRow> {
+        let graphemes: Vec<&str> = self.text.graphemes(true).collect();
+        let mut rows = Vec::new();
+
+        for (start, length) in self.lines_bounds() {
+            let mut row = WrappedRow {
+                start,
+                length: 0,
+                width: 0,
+                last: false,
+            };
+            for grapheme in &graphemes[start..start + length] {
+                let grapheme_width = grapheme.width();
+                if row.length > 0 && row.width + grapheme_width > width {
+                    let next = row.start + row.length;
+                    rows.push(row);
+                    row = WrappedRow {
+                        start: next,
+                        length: 0,
+                        width: 0,
+                        last: false,
+                    };
+                }
+                row.length += 1;
+                row.width += grapheme_width;
+            }
+            if row.width >= width && row.length > 0 {
+                let next = row.start + row.length;
+                rows.push(row);
+                row = WrappedRow {
+                    start: next,
+                    length: 0,
+                    width: 0,
+                    last: false,
+                };
+            }
+            row.last = true;
+            rows.push(row);
+        }
+
+        rows
+    }
+
+    /// The cursor position among the rows wrapped at `width`, as a (row, cell column) pair.
+    pub fn wrapped_cursor(&self, width: usize) -> (usize, usize) {
+        let rows = self.wrapped_rows(width);
+        let row = rows
+            .iter()
+            .position(|row| {
+                self.cursor >= row.start
+                    && (self.cursor < row.start + row.length
+                        || (row.last && self.cursor == row.start + row.length))
+            })
+            .unwrap_or(rows.len() - 1);
+        let column = self
+            .text
+            .graphemes(true)
+            .skip(rows[row].start)
+            .take(self.cursor - rows[row].start)
+            .map(UnicodeWidthStr::width)
+            .sum();
+        (row, column)
+    }
+
+    pub fn move_row_up(&mut self, width: usize) {
+        let (row, column) = self.wrapped_cursor(width);
+        if row > 0 {
+            self.move_to_row(row - 1, column, width);
+        }
+    }
+
+    pub fn move_row_down(&mut self, width: usize) {
+        let (row, column) = self.wrapped_cursor(width);
+        self.move_to_row(row + 1, column, width);
+    }
+
+    // Put the cursor on the grapheme of the row the closest to the left of the cell column.
+    fn move_to_row(&mut self, row: usize, column: usize, width: usize) {
+        let Some(row) = self.wrapped_rows(width).get(row).copied() else {
+            return;
+        };
+        let max = if row.last {
+            row.length
+        } else {
+            row.length.saturating_sub(1)
+        };
+        let mut offset = 0;
+        let mut cells = 0;
+        for grapheme in self.text.graphemes(true).skip(row.start).take(max) {
+            cells += grapheme.width();
+            if cells > column {
+                break;
+            }
+            offset += 1;
+        }
+        self.cursor = row.start + offset;
+    }
+
+    pub fn move_to_line_start(&mut self) {
+        let (line, _) = self.cursor_position();
+        self.cursor = self.lines_bounds()[line].0;
+    }
+
+    pub fn move_to_line_end(&mut self) {
+        let (line, _) = self.cursor_position();
+        let (start, length) = self.lines_bounds()[line];
+        self.cursor = start + length;
+    }
+}
+
+/// A row of wrapped text, its start and length in graphemes and its width in cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WrappedRow {
+    pub start: usize,
+    pub length: usize,
+    pub width: usize,
+    pub last: bool,
+}
+
+impl From<EditableText> for String {
+    fn from(editable_text: EditableText) -> Self {
+        editable_text.text
+    }
+}
+
+/// A small xorshift generator, enough to pick random edits reproducibly.
+#[cfg(test)]
+pub struct Random(pub u64);
+
+#[cfg(test)]
+impl Random {
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        usize::try_from(self.0 % bound as u64).expect("The value is below a usize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(edit_distance("meetng", "meeting"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("日本", "日本語"), 1);
+    }
+
+    #[test]
+    fn wrapping_counts_cells() {
+        let text = EditableText::new(String::from("日本語テキスト"));
+        let rows = text.wrapped_rows(6);
+        assert_eq!(
+            rows.iter().map(|row| row.length).collect::<Vec<_>>(),
+            vec![3, 3, 1]
+        );
+        assert!(rows.iter().all(|row| row.width <= 6));
+        assert_eq!(text.wrapped_cursor(6), (2, 2));
+    }
+
+    #[test]
+    fn cursor_leaves_a_full_row() {
+        let text = EditableText::new(String::from("abcd\nef"));
+        let mut start = text.clone();
+        start.move_to_start();
+        start.move_to_line_end();
+        assert_eq!(start.wrapped_cursor(4), (1, 0));
+        assert_eq!(text.wrapped_cursor(4), (2, 2));
+    }
+
+    #[test]
+    fn rows_move_the_cursor_inside_lines() {
+        let mut text = EditableText::new(String::from("abcdefgh\nij"));
+        text.move_to_start();
+        text.move_right();
+        text.move_row_down(4);
+        assert_eq!(text.cursor(), 5);
+        text.move_row_down(4);
+        assert_eq!(text.cursor_position(), (0, 8));
+        text.move_row_down(4);
+        assert_eq!(text.cursor_position(), (1, 0));
+        text.move_right();
+        text.move_row_up(4);
+        assert_eq!(text.cursor(), 8);
+        text.move_row_up(4);
+        assert_eq!(text.cursor(), 4);
+    }
+
+    #[test]
+    fn rows_move_by_cells_across_wide_graphemes() {
+        let mut text = EditableText::new(String::from("ab\n日本"));
+        text.move_to_start();
+        text.move_right();
+        text.move_row_down(10);
+        assert_eq!(text.cursor_position(), (1, 0));
+        text.move_right();
+        text.move_row_up(10);
+        assert_eq!(text.cursor_position(), (0, 2));
+    }
+
+    // Characters that never join the graphemes around them
+    const STANDALONE: [char; 6] = ['a', ' ', '\n', 'é', '日', '😀'];
+    // Characters that may join the grapheme before or after them
+    const JOINING: [char; 4] = ['\u{301}', '\u{200d}', '\r', '\u{fe0f}'];
+
+    // Apply a random operation to the text, returning it along with the character it may insert.
+    fn random_operation(
+        random: &mut Random,
+        text: &mut EditableText,
+        alphabet: &[char],
+    ) -> (usize, char) {
+        let operation = random.below(7);
+        let c = alphabet[random.below(alphabet.len())];
+        match operation {
+            0 | 1 => text.insert_char(c),
+            2 => text.remove_char(),
+            3 => text.del_char(),
+            4 => text.move_left(),
+            5 => text.move_right(),
+            _ => text.move_up(),
+        }
+        (operation, c)
+    }
+
+    #[test]
+    fn random_operations_match_a_grapheme_model() {
+        for seed in 1..=200 {
+            let mut random = Random(seed);
+            let mut text = EditableText::new(String::new());
+            let mut graphemes: Vec<String> = Vec::new();
+            let mut cursor = 0;
+
+            for _ in 0..100 {
+                match random_operation(&mut random, &mut text, &STANDALONE) {
+                    (0 | 1, c) => {
+                        graphemes.insert(cursor, c.to_string());
+                        cursor += 1;
+                    }
+                    (2, _) if cursor > 0 => {
+                        cursor -= 1;
+                        graphemes.remove(cursor);
+                    }
+                    (3, _) if cursor < graphemes.len() => {
+                        graphemes.remove(cursor);
+                    }
+                    (4, _) => cursor = cursor.saturating_sub(1),
+                    (5, _) => cursor = (cursor + 1).min(graphemes.len()),
+                    (6, _) => {
+                        let (line, _) = text.cursor_position();
+                        cursor = text.cursor();
+                        assert_eq!(
+                            graphemes[..cursor].iter().filter(|g| *g == "\n").count(),
+                            line,
+                            "seed {seed}"
+                        );
+                    }
+                    _ => {}
+                }
+                assert_eq!(text.as_str(), graphemes.concat(), "seed {seed}");
+                assert_eq!(text.cursor(), cursor, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn random_operations_keep_the_cursor_in_bounds() {
+        let alphabet: Vec<char> = STANDALONE.into_iter().chain(JOINING).collect();
+        for seed in 1..=200 {
+            let mut random = Random(seed);
+            let mut text = EditableText::new(String::new());
+
+            for _ in 0..100 {
+                random_operation(&mut random, &mut text, &alphabet);
+                assert!(text.cursor() <= text.len(), "seed {seed}");
+
+                let (line, column) = text.cursor_position();
+                let (start, length) = text.lines_bounds()[line];
+                assert_eq!(start + column, text.cursor(), "seed {seed}");
+                assert!(column <= length, "seed {seed}");
+
+                let (row, _) = text.wrapped_cursor(5);
+                assert!(row < text.wrapped_rows(5).len(), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn forward_delete_removes_whole_emoji() {
+        let mut text = EditableText::new(String::from("a👩‍👩‍👧🇫🇷❤️b"));
+        text.move_to_start();
+        text.move_right();
+
+        text.del_char();
+        assert_eq!(text.as_str(), "a🇫🇷❤️b");
+        text.del_char();
+        assert_eq!(text.as_str(), "a❤️b");
+        text.del_char();
+        assert_eq!(text.as_str(), "ab");
+        assert_eq!(text.cursor(), 1);
+
+        text.move_to_end();
+        text.del_char();
+        assert_eq!(text.as_str(), "ab");
+        assert_eq!(text.cursor(), 2);
+    }
+}
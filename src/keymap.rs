@@ -0,0 +1,213 @@
+pub struct Binding {
+    pub keys: &'static str,
+    pub action: &'static str,
+}
+
+pub struct StateBindings {
+    pub state: &'static str,
+    pub bindings: &'static [Binding],
+}
+
+const fn bind(keys: &'static str, action: &'static str) -> Binding {
+    Binding { keys, action }
+}
+
+pub const KEYMAP: &[StateBindings] = &[
+    StateBindings {
+        state: "Everywhere",
+        bindings: &[
+            bind("?", "Show this help (outside of text inputs)"),
+            bind("Esc twice", "Discard the text typed in a prompt"),
+        ],
+    },
+    StateBindings {
+        state: "Home",
+        bindings: &[
+            bind("c", "Create a note"),
+            bind("s", "Search notes"),
+            bind("t", "Manage tags"),
+            bind("i", "Notebook statistics"),
+            bind(",", "Notebook settings"),
+            bind("p", "Preferences"),
+            bind("q / Esc", "Quit"),
+        ],
+    },
+    StateBindings {
+        state: "Notes search",
+        bindings: &[
+            bind("Any character", "Edit the search pattern"),
+            bind("Up / Down", "Move the selection, past the end to load more"),
+            bind("Enter", "Open the selected note"),
+            bind("Tab", "Order the results by name / size"),
+            bind("Alt+letter", "Jump to the notes starting with the letter"),
+            bind("PageUp / PageDown", "Move the selection by a page"),
+            bind("Home / End", "Select the first / last note"),
+            bind("Ctrl+f", "Toggle typing the start of a note name to jump to"),
+            bind("Tab (while jumping)", "Jump to the next matching note"),
+            bind("Esc", "Stop jumping, or back home"),
+        ],
+    },
+    StateBindings {
+        state: "Note viewer",
+        bindings: &[
+            bind("Arrows / h j k l", "Move the selection"),
+            bind("g / E", "Select the first / last element"),
+            bind("Ctrl+Up / Ctrl+Down", "Select the previous / next section"),
+            bind("Enter", "Follow the selected link"),
+            bind("e", "Edit in the external editor"),
+            bind("I", "Edit inline"),
+            bind("a", "Append a timestamped entry and edit it inline"),
+            bind("Ctrl+y", "Copy the selected block, code blocks as written"),
+            bind("v", "Toggle the source view"),
+            bind("Ctrl+v", "Pick a note to view beside this one"),
+            bind("z", "Toggle zen mode"),
+            bind("f", "Toggle focus mode, showing only the current section"),
+            bind("w", "Toggle line wrapping"),
+            bind("H / L", "Scroll left / right when lines aren't wrapped"),
+            bind("t", "Manage the note tags"),
+            bind("F", "Manage the note attachments"),
+            bind("b", "Show the links from and to the note"),
+            bind("r", "Rename the note"),
+            bind("c", "Copy the note into a new one"),
+            bind("d", "Delete the note"),
+            bind("s", "Search notes"),
+            bind("Esc", "Back home"),
+            bind("q", "Quit, confirming if editor changes couldn't be saved"),
+        ],
+    },
+    StateBindings {
+        state: "Note viewer (source view)",
+        bindings: &[
+            bind("Up / Down / j / k", "Scroll by a line"),
+            bind("PageUp / PageDown", "Scroll by a page"),
+            bind("g / E", "Go to the top / bottom"),
+        ],
+    },
+    StateBindings {
+        state: "Inline editor",
+        bindings: &[
+            bind("Arrows / Home / End", "Move the cursor"),
+            bind("Ctrl+s", "Save"),
+            bind("Ctrl+r", "Revert the changes"),
+            bind("Esc", "Stop editing"),
+        ],
+    },
+    StateBindings {
+        state: "Split view",
+        bindings: &[
+            bind("Tab", "Focus the other pane"),
+            bind("Ctrl+v", "Keep only the focused note"),
+            bind("Esc", "Close the focused pane"),
+            bind("Other keys", "As in the note viewer, on the focused pane"),
+        ],
+    },
+    StateBindings {
+        state: "Note links",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter", "Open the linked note, creating it if dangling"),
+            bind("Esc", "Back to the note"),
+        ],
+    },
+    StateBindings {
+        state: "Note attachments",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter", "Open the selected attachment"),
+            bind("a", "Attach a file by its path"),
+            bind("d", "Detach the selected attachment"),
+            bind("Esc", "Back to the note"),
+        ],
+    },
+    StateBindings {
+        state: "Note tags",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("a", "Add a tag"),
+            bind("s", "Replace the selected tag"),
+            bind("d", "Remove the selected tag"),
+            bind("Enter", "List the notes with the selected tag"),
+            bind("Esc", "Back to the note"),
+        ],
+    },
+    StateBindings {
+        state: "Tags manager",
+        bindings: &[
+            bind("Tab", "Toggle the filter editing"),
+            bind("Up / Down", "Move the selection"),
+            bind("c", "Create a tag"),
+            bind("d", "Delete the selected tag"),
+            bind("i", "Edit the tags implied by the selected tag"),
+            bind("p", "Set the template note of the selected tag"),
+            bind("T", "Browse the deleted tags"),
+            bind("Enter", "List the notes with the selected tag"),
+            bind("Esc", "Back home"),
+        ],
+    },
+    StateBindings {
+        state: "Tag implications",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("a", "Imply another tag"),
+            bind("d", "Stop implying the selected tag"),
+            bind("Esc", "Back to the tags"),
+        ],
+    },
+    StateBindings {
+        state: "Deleted tags",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter / r", "Restore the selected tag"),
+            bind("d", "Delete the selected tag for good"),
+            bind("Esc", "Back to the tags"),
+        ],
+    },
+    StateBindings {
+        state: "Tag notes",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter", "Open the selected note"),
+            bind("c", "Create a note with the tag, filled from its template"),
+            bind("w", "Toggle the frequent terms"),
+            bind("1 - 9", "Only list the notes containing a frequent term"),
+            bind("Backspace", "Clear the term filter"),
+            bind("D", "Create a digest note of the tag"),
+            bind("Space", "Mark / unmark the selected note"),
+            bind("u", "Remove the tag from the marked notes"),
+            bind("Esc", "Back home"),
+        ],
+    },
+    StateBindings {
+        state: "Notebook settings",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter", "Edit / save the selected setting"),
+            bind("Tab", "Cycle the choices while editing"),
+            bind("Esc", "Cancel editing / back home"),
+        ],
+    },
+    StateBindings {
+        state: "Preferences",
+        bindings: &[
+            bind("Up / Down", "Move the selection"),
+            bind("Enter / Space", "Edit or toggle the selected field"),
+            bind("Esc", "Cancel editing / back home"),
+        ],
+    },
+    StateBindings {
+        state: "Prompts",
+        bindings: &[
+            bind(
+                "Tab",
+                "Switch the choice / complete or suggest the tag name / toggle rewriting references",
+            ),
+            bind("Shift+Tab", "Previous tag suggestion"),
+            bind(
+                "Any character",
+                "Type the note name to delete a linked note",
+            ),
+            bind("Enter", "Confirm"),
+            bind("Esc", "Cancel"),
+        ],
+    },
+];
@@ -1,10 +1,16 @@
+use std::fmt::{self, Display, Formatter};
+
 use anyhow::Result;
 
 use rusqlite::Connection;
-use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Iden, SqliteQueryBuilder, Table};
+use sea_query::{
+    ColumnDef, Expr, ForeignKey, ForeignKeyAction, Func, Iden, Index, Query, SimpleExpr,
+    SqliteQueryBuilder, Table,
+};
 
 use crate::helpers::DiscardResult;
-use crate::note::{NotesCharacters, NotesTable};
+use crate::markdown::ParsedMarkdown;
+use crate::note::{Note, NotesCharacters, NotesTable};
 
 #[derive(Iden)]
 pub struct LinksTable;
@@ -22,6 +28,74 @@ pub struct Link {
     pub to: String,
 }
 
+/// The change to a note's links, with the names of the linked notes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinksDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl LinksDiff {
+    pub fn compute(current: &[Link], computed: &[Link]) -> Self {
+        let mut added: Vec<String> = computed
+            .iter()
+            .filter(|link| !current.contains(link))
+            .map(|link| link.to.clone())
+            .collect();
+        let mut removed: Vec<String> = current
+            .iter()
+            .filter(|link| !computed.contains(link))
+            .map(|link| link.to.clone())
+            .collect();
+        added.sort();
+        added.dedup();
+        removed.sort();
+        removed.dedup();
+
+        LinksDiff {
+            added,
+            removed,
+            unchanged: computed
+                .iter()
+                .filter(|link| current.contains(link))
+                .count(),
+        }
+    }
+
+    pub fn changes(&self) -> usize {
+        self.added.len() + self.removed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes() == 0
+    }
+}
+
+impl Display for LinksDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} link(s) added, {} removed.",
+            self.added.len(),
+            self.removed.len()
+        )
+    }
+}
+
+impl Link {
+    pub fn from_markdown(from: i64, content: &ParsedMarkdown) -> Vec<Link> {
+        content
+            .list_links()
+            .into_iter()
+            .map(|to| Link {
+                from,
+                to: Note::normalize_name(to),
+            })
+            .collect()
+    }
+}
+
 impl LinksTable {
     pub fn create(db: &Connection) -> Result<()> {
         db.execute_batch(
@@ -48,4 +122,60 @@ impl LinksTable {
         )
         .discard_result()
     }
+
+    pub fn create_indexes(db: &Connection) -> Result<()> {
+        db.execute_batch(
+            [
+                Index::create()
+                    .if_not_exists()
+                    .name("links_from_id_index")
+                    .table(LinksTable)
+                    .col(LinksCharacters::FromId)
+                    .build(SqliteQueryBuilder),
+                Index::create()
+                    .if_not_exists()
+                    .name("links_to_name_index")
+                    .table(LinksTable)
+                    .col(LinksCharacters::ToName)
+                    .build(SqliteQueryBuilder),
+            ]
+            .join(";")
+            .as_str(),
+        )
+        .discard_result()
+    }
+
+    pub fn outgoing_count() -> SimpleExpr {
+        SimpleExpr::SubQuery(
+            None,
+            Box::new(
+                Query::select()
+                    .expr(Func::count(Expr::col((LinksTable, LinksCharacters::Id))))
+                    .from(LinksTable)
+                    .and_where(
+                        Expr::col((LinksTable, LinksCharacters::FromId))
+                            .equals((NotesTable, NotesCharacters::Id)),
+                    )
+                    .to_owned()
+                    .into_sub_query_statement(),
+            ),
+        )
+    }
+
+    pub fn incoming_count() -> SimpleExpr {
+        SimpleExpr::SubQuery(
+            None,
+            Box::new(
+                Query::select()
+                    .expr(Func::count(Expr::col((LinksTable, LinksCharacters::Id))))
+                    .from(LinksTable)
+                    .and_where(
+                        Expr::col((LinksTable, LinksCharacters::ToName))
+                            .equals((NotesTable, NotesCharacters::Name)),
+                    )
+                    .to_owned()
+                    .into_sub_query_statement(),
+            ),
+        )
+    }
 }
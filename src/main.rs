@@ -1,28 +1,49 @@
 #![deny(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::too_many_lines)]
+mod archive;
+mod attachment;
+mod clipboard;
+mod config;
 mod explore;
 mod helpers;
+mod keymap;
 mod links;
 mod markdown;
 mod note;
 mod notebook;
 mod notebook_selector;
+mod settings;
 mod states;
+mod stats;
 mod tag;
+mod vault;
 
-use std::path::PathBuf;
-use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
 
 use anyhow::Result;
-use log::{error, info};
+use chrono::Local;
+use log::{error, info, warn};
 
 use clap::{Parser, Subcommand};
 use question::{Answer, Question};
+use thiserror::Error;
 
+use crate::archive::{archive, unarchive};
+use crate::attachment::{format_size, Attachment};
+use crate::config::Config;
 use crate::explore::explore;
+use crate::helpers::TryFromDatabase;
+use crate::links::Link;
+use crate::markdown::{parse, strip_comments};
+use crate::note::{Note, NoteData, NoteError, NoteSummary, NotesOrder, NotesTable};
 use crate::notebook::Notebook;
 use crate::notebook_selector::open_selector;
+use crate::states::State;
+use crate::stats::NotebookStats;
+use crate::tag::{Tag, TagError};
+use crate::vault::import_vault;
 
 #[derive(Parser)]
 #[command(
@@ -33,6 +54,12 @@ use crate::notebook_selector::open_selector;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Override a config value, takes precedence over the config file and the environment
+    #[arg(short, long = "config", value_name = "KEY=VALUE", global = true)]
+    config: Vec<String>,
+    /// Where the notebooks are stored, defaults to the user data directory
+    #[arg(long, value_name = "DIR", global = true)]
+    data_dir: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -44,10 +71,106 @@ enum Commands {
     },
     Open {
         name: String,
+        /// A note to open directly in the viewer
+        note: Option<String>,
+        /// Same as the NOTE argument
+        #[arg(long = "note", value_name = "NOTE", conflicts_with = "note")]
+        note_flag: Option<String>,
     },
     Delete {
         name: String,
     },
+    /// Rebuild the links between notes from their content
+    Reindex {
+        name: String,
+    },
+    /// Move a note to another notebook
+    MoveNote {
+        name: String,
+        note: String,
+        destination: String,
+        /// Give the note another name in the destination notebook
+        #[arg(long, value_name = "NEW_NAME")]
+        rename: Option<String>,
+    },
+    /// Export a note and the notes it links to as markdown files
+    ExportTree {
+        name: String,
+        note: String,
+        /// How many links to follow from the note
+        #[arg(short, long, default_value_t = 1)]
+        depth: usize,
+        /// The directory to write the notes in
+        output: PathBuf,
+        /// Keep the html comments of the notes
+        #[arg(long)]
+        include_comments: bool,
+    },
+    /// Import the markdown files of an Obsidian vault, their folders becoming tags
+    ImportVault {
+        name: String,
+        /// The root directory of the vault
+        vault: PathBuf,
+    },
+    /// Reclaim the space left by deleted notes in the notebook file
+    Compact {
+        name: String,
+    },
+    /// Copy the notebook database into a timestamped file
+    Backup {
+        name: String,
+        /// The directory to write the backup in, the current directory by default
+        output: Option<PathBuf>,
+    },
+    /// Write the whole notebook in a portable zip archive
+    Archive {
+        name: String,
+        file: PathBuf,
+    },
+    /// Rebuild a notebook from an archive
+    Unarchive {
+        file: PathBuf,
+        /// Give the notebook another name than the archived one
+        #[arg(long = "as", value_name = "NEW_NAME")]
+        new_name: Option<String>,
+    },
+    /// Print the id and name of the notes matching a pattern
+    Search {
+        name: String,
+        /// Matched anywhere in the note names, every note matches an empty one
+        #[arg(default_value = "")]
+        pattern: String,
+        /// Only search the notes with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+        /// Match the pattern in the content of the notes instead of their names
+        #[arg(long)]
+        content: bool,
+        /// Print the notes as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the size of a notebook
+    Stats {
+        name: String,
+        /// Print the daily snapshots as CSV instead
+        #[arg(long)]
+        history: bool,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the effective configuration and where each value comes from
+    Show {
+        /// Apply the overrides of this notebook
+        notebook: Option<String>,
+    },
 }
 
 fn main() -> Result<()> {
@@ -55,26 +178,9 @@ fn main() -> Result<()> {
 
     info!("Start foucault");
 
-    let app_dir_path: PathBuf = {
-        if let Some(data_dir) = dirs::data_dir() {
-            data_dir.join("foucault")
-        } else {
-            error!("User data directory is unavailable.");
-            unimplemented!();
-        }
-    };
-
-    if !app_dir_path.exists() {
-        if fs::create_dir(&app_dir_path).is_err() {
-            error!("Unable to create app directory.");
-            todo!();
-        }
-    } else if !app_dir_path.is_dir() {
-        error!("Another file already exists.");
-        todo!();
-    }
-
     let cli = Cli::parse();
+    let app_dir_path =
+        || app_dir(cli.data_dir.as_deref()).unwrap_or_else(|err| exit_no_data_dir(&err));
 
     if let Some(command) = &cli.command {
         match command {
@@ -86,13 +192,31 @@ fn main() -> Result<()> {
                         &env::current_dir().expect("The current directory isn't accessible"),
                     )?;
                 } else {
-                    Notebook::new_notebook(name.trim(), &app_dir_path)?;
-                };
+                    Notebook::new_notebook(name.trim(), &app_dir_path())?;
+                }
                 println!("Notebook {name} was successfully created.");
             }
-            Commands::Open { name } => {
+            Commands::Open {
+                name,
+                note,
+                note_flag,
+            } => {
                 info!("Open notebook {name}.");
-                explore(&Notebook::open_notebook(name, &app_dir_path)?)?;
+                let notebook = match app_dir(cli.data_dir.as_deref()) {
+                    Ok(dir) => Notebook::open_notebook(name, &dir)?,
+                    // Notebooks of the current directory don't need the data directory
+                    Err(err) => Notebook::open_notebook(name, &env::current_dir()?)
+                        .unwrap_or_else(|_| exit_no_data_dir(&err)),
+                };
+                let initial_state = match note.as_ref().or(note_flag.as_ref()) {
+                    Some(note_name) => open_note(note_name, &notebook)?,
+                    None => State::Nothing,
+                };
+                explore(
+                    &notebook,
+                    Config::load(Some(name), &cli.config)?,
+                    initial_state,
+                )?;
             }
             Commands::Delete { name } => {
                 info!("Delete notebook {name}.");
@@ -106,18 +230,418 @@ fn main() -> Result<()> {
                     Answer::YES
                 ) {
                     println!("Proceed.");
-                    Notebook::delete_notebook(name, &app_dir_path)?;
+                    Notebook::delete_notebook(name, &app_dir_path())?;
                 } else {
                     println!("Cancel.");
                 }
             }
+            Commands::Reindex { name } => {
+                info!("Reindex links of notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let changed = NotesTable::reindex_links(notebook.db())?;
+                println!("Notebook {name} was reindexed, {changed} link(s) changed.");
+            }
+            Commands::MoveNote {
+                name,
+                note,
+                destination,
+                rename,
+            } => {
+                if name == destination {
+                    error!("The note is already in notebook {name}.");
+                    return Ok(());
+                }
+
+                info!("Move note {note} from notebook {name} to notebook {destination}.");
+                let dir = app_dir_path();
+                let source = Notebook::open_notebook(name, &dir)?;
+                let destination = Notebook::open_notebook(destination, &dir)?;
+                move_note(
+                    load_note(note, &source)?,
+                    &source,
+                    &destination,
+                    rename.as_deref(),
+                )?;
+            }
+            Commands::ExportTree {
+                name,
+                note,
+                depth,
+                output,
+                include_comments,
+            } => {
+                info!("Export notes linked from {note} in notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                export_tree(
+                    load_note(note, &notebook)?,
+                    *depth,
+                    output,
+                    *include_comments,
+                    &notebook,
+                )?;
+            }
+            Commands::ImportVault { name, vault } => {
+                info!("Import vault {} into notebook {name}.", vault.display());
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let report = import_vault(vault, &notebook)?;
+
+                for (path, new_name) in &report.renamed {
+                    println!("{} was imported as {new_name}", path.display());
+                }
+                for (note, target) in &report.embeds {
+                    eprintln!("The embed of {target} in {note} was turned into a link");
+                }
+                for (path, reason) in &report.failed {
+                    eprintln!("{} wasn't imported : {reason}", path.display());
+                }
+                println!(
+                    "{} note(s) imported, {} unchanged, {} failed.",
+                    report.imported,
+                    report.unchanged,
+                    report.failed.len()
+                );
+            }
+            Commands::Compact { name } => {
+                info!("Compact notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let before = notebook.file_size()?;
+                notebook.compact().inspect_err(|_| {
+                    error!("Unable to compact the notebook \"{name}\", is it open elsewhere ?");
+                })?;
+                let after = notebook.file_size()?;
+                println!(
+                    "Notebook {name} was compacted from {} to {}.",
+                    format_size(before),
+                    format_size(after)
+                );
+            }
+            Commands::Backup { name, output } => {
+                info!("Backup notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let output = match output {
+                    Some(output) => output.clone(),
+                    None => env::current_dir()?,
+                };
+                fs::create_dir_all(&output)?;
+
+                let backup_path = output.join(format!(
+                    "{name}-{}.book",
+                    Local::now().format("%Y%m%d-%H%M%S")
+                ));
+                if backup_path.exists() {
+                    error!("The backup {} already exists.", backup_path.display());
+                    return Ok(());
+                }
+                notebook.backup(&backup_path)?;
+                println!(
+                    "Notebook {name} was backed up to {} ({}).",
+                    backup_path.display(),
+                    format_size(fs::metadata(&backup_path)?.len())
+                );
+                if notebook.attachments_dir().exists() {
+                    warn!(
+                        "The attachments aren't part of the backup, they are kept in {}.",
+                        notebook.attachments_dir().display()
+                    );
+                }
+            }
+            Commands::Archive { name, file } => {
+                info!("Archive notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let count = archive(&notebook, file)?;
+                println!(
+                    "{count} note(s) of notebook {name} were archived to {}.",
+                    file.display()
+                );
+            }
+            Commands::Unarchive { file, new_name } => {
+                info!("Unarchive {}.", file.display());
+                let report = unarchive(file, new_name.as_deref(), &app_dir_path())?;
+
+                for (entry, reason) in &report.skipped {
+                    eprintln!("{entry} was skipped : {reason}");
+                }
+                if report.mismatched_links > 0 {
+                    warn!(
+                        "{} link(s) differ from the archived ones.",
+                        report.mismatched_links
+                    );
+                }
+                if report.dropped_trashed_tags > 0 {
+                    warn!(
+                        "{} trashed tag(s) were left out of the archive.",
+                        report.dropped_trashed_tags
+                    );
+                }
+                println!(
+                    "Notebook {} was restored with {} note(s).",
+                    report.name, report.notes
+                );
+            }
+            Commands::Search {
+                name,
+                pattern,
+                tag,
+                content,
+                json,
+            } => {
+                info!("Search notes matching {pattern:?} in notebook {name}.");
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                let notes = search_notes(pattern, tag.as_deref(), *content, &notebook)?;
+                if *json {
+                    println!("{}", serde_json::to_string_pretty(&notes)?);
+                } else {
+                    for note in notes {
+                        println!("{}\t{}", note.id, note.name);
+                    }
+                }
+            }
+            Commands::Stats { name, history } => {
+                let notebook = Notebook::open_notebook(name, &app_dir_path())?;
+                if *history {
+                    println!("{}", NotebookStats::csv_header());
+                    for stats in NotebookStats::history(notebook.db())? {
+                        println!("{}", stats.to_csv());
+                    }
+                } else {
+                    let stats = NotebookStats::current(notebook.db())?;
+                    println!("Notes : {}", stats.note_count);
+                    println!("Links : {}", stats.link_count);
+                    println!("Size  : {}", format_size(stats.total_bytes));
+                }
+            }
+            Commands::Config {
+                command: ConfigCommands::Show { notebook },
+            } => {
+                let config = Config::load(notebook.as_deref(), &cli.config)?;
+                if !config.unknown_keys.is_empty() {
+                    eprintln!(
+                        "Unknown keys were ignored : {}",
+                        config.unknown_keys.join(", ")
+                    );
+                }
+                if let Some(file) = Config::file_path() {
+                    println!("# {}", file.display());
+                }
+                print!("{}", config.describe()?);
+            }
         }
     } else {
         info!("Open default notebook manager.");
 
-        if let Some(name) = open_selector(&app_dir_path)? {
+        let dir = app_dir_path();
+        if let Some(name) = open_selector(&dir, &Config::load(None, &cli.config)?.theme)? {
             info!("Open notebook selected : {name}.");
-            explore(&Notebook::open_notebook(name.as_str(), &app_dir_path)?)?;
+            explore(
+                &Notebook::open_notebook(name.as_str(), &dir)?,
+                Config::load(Some(name.as_str()), &cli.config)?,
+                State::Nothing,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Failures to use the data directory exit with this code, other failures with 1.
+const NO_DATA_DIR_EXIT_CODE: i32 = 2;
+const CLOSE_MATCHES_SHOWN: usize = 5;
+
+#[derive(Error, Debug)]
+enum DataDirError {
+    #[error("No user data directory is available, pass one with --data-dir.")]
+    Unavailable,
+    #[error("Unable to create the data directory {path:?}, create it or pass another one with --data-dir.")]
+    Uncreatable { path: PathBuf },
+    #[error("{path:?} is not a directory, pass another data directory with --data-dir.")]
+    NotADirectory { path: PathBuf },
+    #[error("The data directory {path:?} is read-only, pass a writable one with --data-dir.")]
+    ReadOnly { path: PathBuf },
+}
+
+fn app_dir(data_dir: Option<&Path>) -> Result<PathBuf, DataDirError> {
+    let path = match data_dir {
+        Some(data_dir) => data_dir.to_path_buf(),
+        None => dirs::data_dir()
+            .ok_or(DataDirError::Unavailable)?
+            .join("foucault"),
+    };
+
+    if !path.exists() {
+        fs::create_dir_all(&path).map_err(|_| DataDirError::Uncreatable { path: path.clone() })?;
+    } else if !path.is_dir() {
+        return Err(DataDirError::NotADirectory { path });
+    }
+
+    // Sqlite writes its journal next to the notebooks, so even reading needs write access
+    let probe = path.join(".foucault-write-check");
+    if fs::write(&probe, []).is_err() || fs::remove_file(&probe).is_err() {
+        return Err(DataDirError::ReadOnly { path });
+    }
+
+    Ok(path)
+}
+
+fn exit_no_data_dir(err: &DataDirError) -> ! {
+    error!("{err}");
+    process::exit(NO_DATA_DIR_EXIT_CODE);
+}
+
+fn open_note(name: &str, notebook: &Notebook) -> Result<State> {
+    let note = load_note(name, notebook)?;
+    info!("Open note {}.", note.name);
+    State::note_viewing(note, notebook)
+}
+
+fn load_note(name: &str, notebook: &Notebook) -> Result<Note> {
+    if let Some(note) = Note::load_by_name(name, notebook.db())? {
+        return Ok(note);
+    }
+
+    error!("The note \"{name}\" was not found.");
+
+    let close_matches = NoteSummary::search_closest(name, CLOSE_MATCHES_SHOWN, notebook.db())?;
+    if !close_matches.is_empty() {
+        eprintln!("Close matches :");
+        for summary in &close_matches {
+            eprintln!("  {}", summary.name);
+        }
+    }
+
+    Err(NoteError::NoteDoesNotExist.into())
+}
+
+fn search_notes(
+    pattern: &str,
+    tag: Option<&str>,
+    content: bool,
+    notebook: &Notebook,
+) -> Result<Vec<NoteSummary>> {
+    let Some(tag) = tag else {
+        return if content {
+            NoteSummary::search_by_content(pattern, notebook.db())
+        } else {
+            NoteSummary::search_by_name(pattern, NotesOrder::Name, None, notebook.db())
+        };
+    };
+
+    let Some(tag) = Tag::load_by_name(tag, notebook.db())? else {
+        return Err(TagError::TagDoesNotExist {
+            name: tag.to_owned(),
+        }
+        .into());
+    };
+    if content {
+        tag.search_notes(pattern, notebook.db())
+    } else {
+        // Like the LIKE of the name search, only ASCII letters ignore their case
+        let pattern = pattern.to_ascii_lowercase();
+        let mut notes = tag.get_notes(notebook.db())?;
+        notes.retain(|note| note.name.to_ascii_lowercase().contains(pattern.as_str()));
+        Ok(notes)
+    }
+}
+
+fn export_tree(
+    note: Note,
+    depth: usize,
+    output: &Path,
+    include_comments: bool,
+    notebook: &Notebook,
+) -> Result<()> {
+    let (notes, dangling) = note.linked_notes(depth, notebook.db())?;
+
+    fs::create_dir_all(output)?;
+    for note in &notes {
+        info!("Export note {}.", note.name);
+        let file = output.join(format!("{}.md", note.name.replace(['/', '\\'], "_")));
+        if include_comments {
+            note.export_content(&file)?;
+        } else {
+            fs::write(file, strip_comments(note.content.as_str()))?;
+        }
+    }
+
+    for name in dangling {
+        warn!("Skip dangling link to note {name}.");
+        eprintln!("No note named \"{name}\", skipped.");
+    }
+
+    println!(
+        "{} note(s) were exported to {}.",
+        notes.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn move_note(
+    note: Note,
+    source: &Notebook,
+    destination: &Notebook,
+    new_name: Option<&str>,
+) -> Result<()> {
+    let note_data = NoteData::try_from_database(note, source.db())?;
+    let name = new_name.unwrap_or(note_data.note.name.as_str());
+
+    // The note is only removed from the source once fully written in the destination
+    let transaction = destination.db().unchecked_transaction()?;
+    let moved_note = match Note::new(name, note_data.note.content.clone(), &transaction) {
+        Ok(note) => note,
+        Err(err) => {
+            if matches!(
+                err.downcast_ref::<NoteError>(),
+                Some(NoteError::NoteAlreadyExists { .. })
+            ) {
+                eprintln!("Use --rename to give the note another name.");
+            }
+            return Err(err);
+        }
+    };
+    let mut moved_note_data = NoteData::try_from_database(moved_note, &transaction)?;
+
+    for tag in &note_data.tags {
+        let tag = match Tag::load_by_name(tag.name.as_str(), &transaction)? {
+            Some(tag) => tag,
+            None => Tag::new(tag.name.as_str(), &transaction)?,
+        };
+        moved_note_data.add_tag(tag, &transaction)?;
+    }
+
+    let links = Link::from_markdown(
+        moved_note_data.note.id,
+        &parse(moved_note_data.note.content.as_str()),
+    );
+    moved_note_data.update_links(&links, &transaction)?;
+    transaction.commit()?;
+
+    for attachment in Attachment::list(note_data.note.id, source.db())? {
+        Attachment::store(
+            moved_note_data.note.id,
+            attachment.filename.as_str(),
+            &attachment.content(source.attachments_dir().as_path())?,
+            destination.attachments_dir().as_path(),
+            destination.db(),
+        )?;
+    }
+
+    let old_name = note_data.note.name.clone();
+    note_data.note.delete(source.db())?;
+    Attachment::collect_garbage(source.attachments_dir().as_path(), source.db())?;
+    println!(
+        "Note {old_name} was moved to notebook {} as {}.",
+        destination.name, moved_note_data.note.name
+    );
+
+    let dangling = Note::list_backlinks(old_name.as_str(), source.db())?;
+    if !dangling.is_empty() {
+        warn!(
+            "Links to note {old_name} are left dangling in notebook {}.",
+            source.name
+        );
+        eprintln!("These notes of {} still link to {old_name} :", source.name);
+        for name in dangling {
+            eprintln!("  {name}");
         }
     }
 
@@ -1,14 +1,20 @@
 pub mod elements;
 
+use std::ops::Range;
+
+use markdown::mdast::Node;
 use markdown::{to_mdast, ParseOptions};
 
 use ratatui::prelude::Alignment;
 use ratatui::style::{Color, Modifier};
 
 use crate::markdown::elements::{
-    BlockElement, BlockElements, RenderedBlock, SelectableInlineElements,
+    comment_text, BlockElement, BlockElements, InlineElement, RenderedBlock,
+    SelectableInlineElements,
 };
 
+// The scheme of the markdown links pointing to notes, as cross-references do.
+const NOTE_SCHEME: &str = "note:";
 const HEADER_COLOR: [Color; 6] = [
     Color::Red,
     Color::Green,
@@ -52,11 +58,204 @@ const RICH_TEXT_COLOR: [Color; 6] = [
     Color::Yellow,    // Blockquote
 ];
 
+struct SourceBlock {
+    range: Range<usize>,
+    blocks: usize,
+    standalone: bool,
+    definition: bool,
+}
+
+impl SourceBlock {
+    fn from_node(node: &Node, offset: usize) -> Option<Self> {
+        let position = node.position()?;
+        Some(SourceBlock {
+            range: position.start.offset + offset..position.end.offset + offset,
+            blocks: 0,
+            // Lists, code and html blocks may span blank lines or swallow the following ones
+            standalone: !matches!(node, Node::List(_) | Node::Code(_) | Node::Html(_)),
+            definition: matches!(node, Node::Definition(_)),
+        })
+    }
+}
+
 pub struct ParsedMarkdown {
+    source: String,
+    source_blocks: Vec<SourceBlock>,
     parsed_content: Vec<BlockElements<SelectableInlineElements>>,
 }
 
+fn parse_blocks(
+    content: &str,
+    offset: usize,
+) -> Option<(
+    Vec<SourceBlock>,
+    Vec<BlockElements<SelectableInlineElements>>,
+)> {
+    let Node::Root(root) = to_mdast(content, &ParseOptions::default()).unwrap() else {
+        unreachable!("The markdown parser should always return a root node.");
+    };
+
+    let mut source_blocks = Vec::with_capacity(root.children.len());
+    let mut parsed_content = Vec::new();
+    for (i, node) in root.children.iter().enumerate() {
+        let mut source_block = SourceBlock::from_node(node, offset)?;
+        let blocks = match node {
+            // Only the very top of the note may hold metadata
+            Node::Paragraph(_) if i == 0 && offset == 0 => {
+                parse_top_paragraph(node, &content[source_block.range.clone()])
+            }
+            _ => BlockElements::parse_node(node),
+        };
+        source_block.blocks = blocks.len();
+        source_blocks.push(source_block);
+        parsed_content.extend(blocks);
+    }
+
+    Some((source_blocks, parsed_content))
+}
+
+fn parse_top_paragraph(node: &Node, source: &str) -> Vec<BlockElements<SelectableInlineElements>> {
+    let Some((metadata, rest)) = BlockElements::parse_metadata(source) else {
+        return BlockElements::parse_node(node);
+    };
+
+    let mut blocks = vec![metadata];
+    if !rest.trim().is_empty() {
+        blocks.extend(BlockElements::parse_node(
+            &to_mdast(rest, &ParseOptions::default()).unwrap(),
+        ));
+    }
+    blocks
+}
+
+fn blank_line_count(whitespace: impl Iterator<Item = char>) -> usize {
+    whitespace
+        .take_while(|c| c.is_whitespace())
+        .filter(|c| *c == '\n')
+        .count()
+}
+
 impl ParsedMarkdown {
+    pub fn update(&mut self, content: &str) {
+        let mut start = self
+            .source
+            .bytes()
+            .zip(content.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !self.source.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        let max_suffix = self.source.len().min(content.len()) - start;
+        let mut suffix = self
+            .source
+            .bytes()
+            .rev()
+            .zip(content.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !self.source.is_char_boundary(self.source.len() - suffix) {
+            suffix -= 1;
+        }
+
+        if start == self.source.len() && start == content.len() {
+            return;
+        }
+
+        self.replace_range(
+            start..self.source.len() - suffix,
+            &content[start..content.len() - suffix],
+        );
+    }
+
+    pub fn replace_range(&mut self, range: Range<usize>, new_text: &str) {
+        let mut source = self.source.clone();
+        source.replace_range(range.clone(), new_text);
+
+        if !self.try_replace_range(&range, new_text, source.as_str()) {
+            *self = parse(source.as_str());
+        }
+    }
+
+    fn try_replace_range(&mut self, range: &Range<usize>, new_text: &str, source: &str) -> bool {
+        if self.source_blocks.iter().any(|block| block.definition) {
+            return false;
+        }
+
+        let first = self
+            .source_blocks
+            .iter()
+            .position(|block| block.range.end >= range.start)
+            .unwrap_or(self.source_blocks.len());
+        let last = first
+            + self.source_blocks[first..]
+                .iter()
+                .take_while(|block| block.range.start <= range.end)
+                .count();
+
+        let previous = first.checked_sub(1).map(|i| &self.source_blocks[i]);
+        let next = self.source_blocks.get(last);
+        // A list or a code block may change the meaning of blocks further down, even past blank
+        // lines, so only the blocks following standalone ones can be parsed on their own
+        if (previous.is_none() && next.is_none())
+            || self
+                .source_blocks
+                .iter()
+                .take(last + 1)
+                .any(|block| !block.standalone)
+        {
+            return false;
+        }
+
+        let shift = |offset: usize| offset - range.end + range.start + new_text.len();
+        let region_start = previous.map_or(0, |block| block.range.end);
+        let region_end = next.map_or(source.len(), |block| shift(block.range.start));
+        let region = &source[region_start..region_end];
+        let old_region =
+            &self.source[region_start..next.map_or(self.source.len(), |block| block.range.start)];
+
+        // The neighbours only keep their meaning when a blank line parts them from the edited
+        // blocks, both before and after the edit, e.g. a setext underline or a lazy line doesn't
+        for region in [region, old_region] {
+            if (previous.is_some() && blank_line_count(region.chars()) < 2)
+                || (next.is_some() && blank_line_count(region.chars().rev()) < 2)
+            {
+                return false;
+            }
+        }
+
+        let Some((source_blocks, parsed_content)) = parse_blocks(region, region_start) else {
+            return false;
+        };
+        if source_blocks.iter().any(|block| block.definition)
+            || (next.is_some() && source_blocks.iter().any(|block| !block.standalone))
+        {
+            return false;
+        }
+
+        let first_block = self.source_blocks[..first]
+            .iter()
+            .map(|block| block.blocks)
+            .sum::<usize>();
+        let last_block = first_block
+            + self.source_blocks[first..last]
+                .iter()
+                .map(|block| block.blocks)
+                .sum::<usize>();
+
+        for block in &mut self.source_blocks[last..] {
+            block.range = shift(block.range.start)..shift(block.range.end);
+        }
+        self.source_blocks.splice(first..last, source_blocks);
+        self.parsed_content
+            .splice(first_block..last_block, parsed_content);
+        source.clone_into(&mut self.source);
+
+        true
+    }
+
     pub fn get_element(&self, el: (usize, usize)) -> Option<&SelectableInlineElements> {
         if let Some(block) = &self.parsed_content.get(el.1) {
             block.get_content().get(el.0)
@@ -85,26 +284,310 @@ impl ParsedMarkdown {
     pub fn render_blocks(&self, max_len: usize) -> Vec<RenderedBlock> {
         self.parsed_content
             .iter()
-            .map(BlockElement::render_lines)
-            .map(|block| block.wrap_lines(max_len))
+            .map(|block| match block {
+                BlockElements::UnformatedText { .. } | BlockElements::CodeBlock { .. } => {
+                    block.render_lines().hard_wrap_lines(max_len)
+                }
+                _ => block.render_lines().wrap_lines(max_len),
+            })
+            .collect()
+    }
+
+    pub fn list_headers(&self) -> Vec<(usize, String)> {
+        self.parsed_content
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| matches!(block, BlockElements::Heading { .. }))
+            .map(|(i, block)| {
+                (
+                    i,
+                    block
+                        .get_content()
+                        .iter()
+                        .map(InlineElement::inner_text)
+                        .collect(),
+                )
+            })
             .collect()
     }
 
+    pub fn header_index(&self, header: &str) -> Option<usize> {
+        let header = header.trim().to_lowercase();
+        self.list_headers()
+            .into_iter()
+            .find(|(_, text)| text.trim().to_lowercase() == header)
+            .map(|(i, _)| i)
+    }
+
+    /// Range of blocks from the header above `block` to the next header, end excluded.
+    /// Blocks before the first header form their own section.
+    pub fn section_bounds(&self, block: usize) -> (usize, usize) {
+        let is_heading = |block: &BlockElements<_>| matches!(block, BlockElements::Heading { .. });
+        let length = self.parsed_content.len();
+
+        let start = self.parsed_content[..(block + 1).min(length)]
+            .iter()
+            .rposition(is_heading)
+            .unwrap_or(0);
+        let end = self
+            .parsed_content
+            .iter()
+            .skip(start + 1)
+            .position(is_heading)
+            .map_or(length, |i| start + 1 + i);
+
+        (start, end)
+    }
+
+    /// The code of a code block as written, otherwise the text of the block line by line.
+    pub fn block_text(&self, block: usize) -> Option<String> {
+        Some(match self.parsed_content.get(block)? {
+            BlockElements::CodeBlock { code, .. } => code.clone(),
+            BlockElements::UnformatedText { content }
+            | BlockElements::HiddenComment { content } => content
+                .iter()
+                .map(InlineElement::inner_text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            block => block
+                .get_content()
+                .iter()
+                .map(InlineElement::inner_text)
+                .collect(),
+        })
+    }
+
     pub fn block_count(&self) -> usize {
         self.parsed_content.len()
     }
 
     pub fn block_length(&self, block: usize) -> usize {
-        self.parsed_content[block].len()
+        self.parsed_content.get(block).map_or(0, BlockElement::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parsed_content.is_empty()
     }
 }
 
 pub fn parse(content: &str) -> ParsedMarkdown {
+    let (source_blocks, parsed_content) = parse_blocks(content, 0).unwrap_or_else(|| {
+        (
+            Vec::new(),
+            BlockElements::parse_node(&to_mdast(content, &ParseOptions::default()).unwrap()),
+        )
+    });
+
     ParsedMarkdown {
-        parsed_content: BlockElements::parse_node(
-            &to_mdast(content, &ParseOptions::default()).unwrap(),
-        ),
+        source: content.to_owned(),
+        source_blocks,
+        parsed_content,
+    }
+}
+
+/// Byte ranges of the code blocks and inline code spans, where cross-references are not parsed.
+pub fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    fn collect(node: &Node, ranges: &mut Vec<Range<usize>>) {
+        match node {
+            Node::Code(_) | Node::InlineCode(_) => {
+                if let Some(position) = node.position() {
+                    ranges.push(position.start.offset..position.end.offset);
+                }
+            }
+            _ => {
+                for child in node.children().into_iter().flatten() {
+                    collect(child, ranges);
+                }
+            }
+        }
+    }
+
+    let mut ranges = Vec::new();
+    collect(
+        &to_mdast(content, &ParseOptions::default()).unwrap(),
+        &mut ranges,
+    );
+    ranges
+}
+
+/// The byte ranges of the `note:` link destinations as written in the source,
+/// along with their url, for inline links, autolinks and link definitions.
+pub fn note_link_ranges(content: &str) -> Vec<(Range<usize>, String)> {
+    fn collect(node: &Node, content: &str, ranges: &mut Vec<(Range<usize>, String)>) {
+        let (url, label_end) = match node {
+            Node::Link(link) => (
+                &link.url,
+                node.children()
+                    .and_then(|children| children.last())
+                    .and_then(Node::position)
+                    .map(|position| position.end.offset),
+            ),
+            Node::Definition(definition) => (&definition.url, None),
+            _ => {
+                for child in node.children().into_iter().flatten() {
+                    collect(child, content, ranges);
+                }
+                return;
+            }
+        };
+        let Some(position) = node.position() else {
+            return;
+        };
+        if !url.starts_with(NOTE_SCHEME) {
+            return;
+        }
+
+        let source = &content[position.start.offset..position.end.offset];
+        let destination = if source.starts_with('<') {
+            // An autolink
+            Some(1..source.len() - 1)
+        } else {
+            let label_end = match (node, label_end) {
+                (Node::Definition(_), _) => source.find("]:").map(|end| end + 2),
+                (_, Some(end)) => content[end..position.end.offset]
+                    .find("](")
+                    .map(|start| end - position.start.offset + start + 2),
+                (_, None) => source.find("](").map(|start| start + 2),
+            };
+            label_end.and_then(|label_end| destination_range(source, label_end))
+        };
+        if let Some(destination) = destination {
+            ranges.push((
+                position.start.offset + destination.start..position.start.offset + destination.end,
+                url.clone(),
+            ));
+        }
+    }
+
+    let mut ranges = Vec::new();
+    collect(
+        &to_mdast(content, &ParseOptions::default()).unwrap(),
+        content,
+        &mut ranges,
+    );
+    ranges
+}
+
+// The range of the destination starting after `start` in a link source, without its angle brackets.
+fn destination_range(source: &str, start: usize) -> Option<Range<usize>> {
+    let start = start + source[start..].len() - source[start..].trim_start().len();
+    let rest = &source[start..];
+    if let Some(inner) = rest.strip_prefix('<') {
+        let mut escaped = false;
+        let end = inner.find(|c| {
+            let closes = c == '>' && !escaped;
+            escaped = c == '\\' && !escaped;
+            closes
+        })?;
+        return Some(start + 1..start + 1 + end);
     }
+
+    let mut depth = 0_usize;
+    let mut escaped = false;
+    let end = rest
+        .find(|c: char| {
+            let ends = !escaped && (c.is_whitespace() || (c == ')' && depth == 0));
+            if !escaped && c == '(' {
+                depth += 1;
+            } else if !escaped && c == ')' {
+                depth = depth.saturating_sub(1);
+            }
+            escaped = c == '\\' && !escaped;
+            ends
+        })
+        .unwrap_or(rest.len());
+    Some(start..start + end)
+}
+
+/// The note and heading of a `[text](note:Name#Heading)` link, its parts percent-decoded.
+pub fn note_link_dest(url: &str) -> Option<(String, Option<String>)> {
+    let (dest, heading) = match url.strip_prefix(NOTE_SCHEME)?.split_once('#') {
+        Some((dest, heading)) => (dest, Some(percent_decode(heading)?)),
+        None => (url.strip_prefix(NOTE_SCHEME)?, None),
+    };
+    let dest = percent_decode(dest)?;
+    (!dest.trim().is_empty()).then(|| {
+        (
+            dest,
+            heading
+                .map(|heading| heading.trim().to_owned())
+                .filter(|heading| !heading.is_empty()),
+        )
+    })
+}
+
+/// The `note:` url of a note, escaping what a link destination can't hold.
+pub fn note_link_url(name: &str, heading: Option<&str>) -> String {
+    let mut url = format!("{NOTE_SCHEME}{}", percent_encode(name));
+    if let Some(heading) = heading {
+        url.push('#');
+        url.push_str(percent_encode(heading).as_str());
+    }
+    url
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(format!("%{byte:02X}").as_str());
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Remove the html comments, along with the line break ending the lines they fill.
+pub fn strip_comments(content: &str) -> String {
+    fn collect(node: &Node, ranges: &mut Vec<Range<usize>>) {
+        match node {
+            Node::Html(html) if comment_text(html.value.as_str()).is_some() => {
+                if let Some(position) = node.position() {
+                    ranges.push(position.start.offset..position.end.offset);
+                }
+            }
+            _ => {
+                for child in node.children().into_iter().flatten() {
+                    collect(child, ranges);
+                }
+            }
+        }
+    }
+
+    let mut ranges = Vec::new();
+    collect(
+        &to_mdast(content, &ParseOptions::default()).unwrap(),
+        &mut ranges,
+    );
+
+    let mut stripped = content.to_owned();
+    for mut range in ranges.into_iter().rev() {
+        let fills_line = content[..range.start].ends_with('\n') || range.start == 0;
+        if fills_line && content[range.end..].starts_with('\n') {
+            range.end += 1;
+        }
+        stripped.replace_range(range, "");
+    }
+    stripped
 }
 
 pub fn lines(blocks: &[RenderedBlock]) -> usize {
@@ -119,3 +602,290 @@ pub fn combine(blocks: &[RenderedBlock]) -> RenderedBlock {
         .collect::<Vec<_>>()
         .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::text::Line;
+
+    use super::*;
+    use crate::helpers::Random;
+    use crate::markdown::elements::InlineElements;
+
+    const FRAGMENTS: &[&str] = &[
+        "para text",
+        "\n",
+        "\n\n",
+        "# Heading",
+        "===",
+        "---",
+        "> quote",
+        "quote",
+        "1. num",
+        "- item",
+        "  two sp",
+        "    code",
+        "```",
+        "<!-- c -->",
+        "<div>",
+        "[[Cross]]",
+        "[link](note:Other)",
+        "[def]: https://example.com",
+        "Key: value",
+        " ",
+        "*",
+        "|a|b|\n|-|-|",
+    ];
+
+    fn rendered(markdown: &ParsedMarkdown) -> Vec<Vec<Line<'static>>> {
+        markdown
+            .render_blocks(80)
+            .iter()
+            .map(|block| block.to_vec())
+            .collect()
+    }
+
+    fn assert_update_matches_parse(before: &str, after: &str) {
+        let mut updated = parse(before);
+        updated.update(after);
+        assert_eq!(
+            rendered(&updated),
+            rendered(&parse(after)),
+            "updating {before:?} to {after:?}"
+        );
+        assert_eq!(updated.list_links(), parse(after).list_links());
+    }
+
+    fn cross_refs(markdown: &ParsedMarkdown) -> Vec<(String, Option<String>)> {
+        markdown
+            .parsed_content
+            .iter()
+            .flat_map(|block| block.get_content().iter())
+            .filter_map(|element| match &element.element {
+                InlineElements::CrossRef { dest, heading, .. } => {
+                    Some((dest.clone(), heading.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn texts(block: &RenderedBlock) -> Vec<String> {
+        block
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prose_wraps_at_words_and_code_at_width() {
+        let markdown = parse("alpha beta gamma overlong\n\n```\nalpha beta gamma\n```");
+        let blocks = markdown.render_blocks(10);
+
+        assert_eq!(texts(&blocks[0]), ["alpha beta", "gamma", "overlong", ""]);
+        assert_eq!(texts(&blocks[1]), ["alpha beta", " gamma"]);
+        assert_eq!(
+            texts(&parse("unbreakableword").render_blocks(5)[0]),
+            ["unbre", "akabl", "eword", ""]
+        );
+    }
+
+    #[test]
+    fn multi_line_comments_stay_hidden() {
+        let mut markdown = parse("Before\n\n<!-- First\n\n[[Hidden]]\n# Not a heading\n-->\nAfter");
+        assert!(markdown.list_links().is_empty());
+        assert!(markdown.list_headers().is_empty());
+
+        let blocks = markdown.render_blocks(80);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(texts(&blocks[1]), ["‹comment›"]);
+
+        markdown.select((0, 1), true);
+        assert_eq!(
+            texts(&markdown.render_blocks(80)[1]),
+            ["First", "", "[[Hidden]]", "# Not a heading"]
+        );
+    }
+
+    #[test]
+    fn stripping_multi_line_comments_keeps_the_text() {
+        assert_eq!(
+            strip_comments("Before\n\n<!-- First\n\nSecond -->\nAfter"),
+            "Before\n\nAfter"
+        );
+        assert_eq!(
+            strip_comments("Inline <!-- across\nlines --> text"),
+            "Inline  text"
+        );
+        assert_eq!(
+            strip_comments("```\n<!-- code -->\n```"),
+            "```\n<!-- code -->\n```"
+        );
+    }
+
+    fn has_metadata(content: &str) -> bool {
+        parse(content)
+            .parsed_content
+            .iter()
+            .any(|block| matches!(block, BlockElements::Metadata { .. }))
+    }
+
+    #[test]
+    fn prose_with_colons_is_not_metadata() {
+        assert!(!has_metadata("Note: this is prose"));
+        assert!(!has_metadata("Note: this is prose\nthat goes on: and on"));
+        assert!(!has_metadata(
+            "In short, the rule: be brief\nAnd, again: be brief"
+        ));
+        assert!(!has_metadata("Note:without a space\nTodo:neither"));
+        assert!(!has_metadata("Intro\n\nAuthor: Someone\nDate: Today"));
+        assert!(!has_metadata("```\nAuthor: Someone\nDate: Today\n```"));
+
+        assert!(has_metadata(
+            "Author: Someone\nDate: Today\n\nNote: this is prose"
+        ));
+    }
+
+    const AWKWARD_NAMES: [&str; 6] = [
+        "Plain",
+        "With  spaces",
+        "Folder/Sub note",
+        "Été 日本 😀",
+        "Paren (x) <y>",
+        "100% [done] #1",
+    ];
+
+    #[test]
+    fn note_link_urls_round_trip() {
+        for name in AWKWARD_NAMES {
+            for heading in [None, Some("Part #2 été")] {
+                let url = note_link_url(name, heading);
+                assert_eq!(
+                    note_link_dest(url.as_str()),
+                    Some((name.to_owned(), heading.map(str::to_owned))),
+                    "{url}"
+                );
+                assert_eq!(
+                    parse(format!("[text]({url})").as_str()).list_links(),
+                    [name],
+                    "{url}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn note_link_ranges_find_every_destination_form() {
+        let content = "[a](note:A) [b]( note:B \"title\") [c](<note:C D>) <note:E>\n\n\
+                       [`code`](note:F(1)) `[g](note:G)` [h](https://example.com)\n\n\
+                       [ref]: note:H";
+        let destinations: Vec<&str> = note_link_ranges(content)
+            .into_iter()
+            .map(|(range, _)| &content[range])
+            .collect();
+        assert_eq!(
+            destinations,
+            [
+                "note:A",
+                "note:B",
+                "note:C D",
+                "note:E",
+                "note:F(1)",
+                "note:H"
+            ]
+        );
+    }
+
+    #[test]
+    fn cross_refs_split_the_heading_fragment() {
+        let markdown =
+            parse("[[Note#Some heading]] [[Note# padded ]] [[Plain]] [[Empty#]] [[A#B#C]]");
+        assert_eq!(
+            cross_refs(&markdown),
+            [
+                (String::from("Note"), Some(String::from("Some heading"))),
+                (String::from("Note"), Some(String::from("padded"))),
+                (String::from("Plain"), None),
+                (String::from("Empty#"), None),
+                (String::from("A"), Some(String::from("B#C"))),
+            ]
+        );
+        assert_eq!(
+            markdown.list_links(),
+            ["Note", "Note", "Plain", "Empty#", "A"]
+        );
+    }
+
+    #[test]
+    fn headings_are_found_ignoring_case() {
+        let markdown = parse("Intro\n\n# First part\n\ntext\n\n## Second Part\n");
+        assert_eq!(markdown.header_index("second part"), Some(3));
+        assert_eq!(markdown.header_index("  FIRST PART "), Some(1));
+        assert_eq!(markdown.header_index("Missing"), None);
+    }
+
+    #[test]
+    fn setext_underline_edit_matches_parse() {
+        assert_update_matches_parse(
+            "[[Cross]1. num\nquote\n===\n---\n> quote\n<!-- c -->\n",
+            "[[Cross]1. num\nquote\n===para text\n\n---\n> quote\n<!-- c -->\n",
+        );
+    }
+
+    #[test]
+    fn list_item_edit_matches_parse() {
+        assert_update_matches_parse(
+            "1. num\n  two sp\n\n\npara text\n\n> quote\n",
+            "1.- item\n num\n  two sp\n\n\npara text\n\n> quote\n",
+        );
+    }
+
+    fn boundary(random: &mut Random, text: &str) -> usize {
+        let mut index = random.below(text.len() + 1);
+        while !text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+
+    fn fragments(random: &mut Random, count: usize) -> String {
+        (0..count)
+            .map(|_| FRAGMENTS[random.below(FRAGMENTS.len())])
+            .collect::<Vec<_>>()
+            .join(if random.below(2) == 0 { "\n" } else { "\n\n" })
+    }
+
+    #[test]
+    fn random_edits_match_parse() {
+        for seed in 1..=300 {
+            let mut random = Random(seed);
+            let count = 2 + random.below(8);
+            let mut content = fragments(&mut random, count);
+            let mut markdown = parse(content.as_str());
+
+            for _ in 0..20 {
+                let start = boundary(&mut random, content.as_str());
+                let end = start + boundary(&mut random, &content[start..]).min(12);
+                let mut end = end.min(content.len());
+                while !content.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let count = random.below(3);
+                let inserted = fragments(&mut random, count);
+
+                let before = content.clone();
+                content.replace_range(start..end, inserted.as_str());
+                markdown.update(content.as_str());
+                assert_eq!(
+                    rendered(&markdown),
+                    rendered(&parse(content.as_str())),
+                    "seed {seed}, updating {before:?} to {content:?}"
+                );
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::ops::Deref;
 
-use markdown::mdast;
+use markdown::{mdast, to_mdast, ParseOptions};
 
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
@@ -9,8 +9,8 @@ use ratatui::widgets::Paragraph;
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::markdown::{
-    BLOCKQUOTE, BLOCKQUOTE_ALIGNEMENT, CROSS_REF, HEADER_ALIGNEMENT, HEADER_COLOR, HEADER_MODIFIER,
-    HYPERLINK, ITALIC, RICH_TEXT_COLOR, STRONG, TEXT,
+    note_link_dest, BLOCKQUOTE, BLOCKQUOTE_ALIGNEMENT, CROSS_REF, HEADER_ALIGNEMENT, HEADER_COLOR,
+    HEADER_MODIFIER, HYPERLINK, ITALIC, RICH_TEXT_COLOR, STRONG, TEXT,
 };
 
 const TEXT_STYLE: Style = Style::new().fg(RICH_TEXT_COLOR[TEXT]);
@@ -33,6 +33,13 @@ const BLOCKQUOTE_STYLE: Style = Style::new()
     .fg(RICH_TEXT_COLOR[BLOCKQUOTE])
     .add_modifier(Modifier::ITALIC);
 
+const COMMENT_STYLE: Style = Style::new().add_modifier(Modifier::DIM);
+const COMMENT_MARKER: &str = "‹comment›";
+
+const METADATA_KEY_STYLE: Style = Style::new().add_modifier(Modifier::BOLD);
+// A single line is too likely to be prose, like "Note: this is a remark"
+const METADATA_MIN_LINES: usize = 2;
+
 const HEADING_STYLE: [Style; 6] = [
     Style::new()
         .add_modifier(Modifier::union(HEADER_MODIFIER[0], Modifier::UNDERLINED))
@@ -67,6 +74,9 @@ pub trait InlineElement: Sized {
     fn inner_text(&self) -> &str {
         self.get_inner_span().content.as_ref()
     }
+    fn is_selected(&self) -> bool {
+        false
+    }
     fn into_span(self) -> Span<'static> {
         self.get_inner_span().clone()
     }
@@ -110,7 +120,92 @@ impl RenderedBlock {
         Paragraph::new(self.content)
     }
 
+    /// Break lines at the last whitespace before `max_len` graphemes,
+    /// words longer than a line are still broken at `max_len`.
     pub fn wrap_lines(self, max_len: usize) -> Self {
+        let max_len = max_len.max(1);
+        let new_content: Vec<Line<'static>> = self
+            .content
+            .into_iter()
+            .flat_map(|line| {
+                let graphemes: Vec<(&str, Style)> = line
+                    .spans
+                    .iter()
+                    .flat_map(|span| {
+                        UnicodeSegmentation::graphemes(span.content.as_ref(), true)
+                            .map(|grapheme| (grapheme, span.style))
+                    })
+                    .collect();
+
+                let mut new_lines: Vec<Vec<(&str, Style)>> = vec![Vec::new()];
+                // Position of the last whitespace following a word in the current line
+                let mut break_point: Option<usize> = None;
+
+                for (grapheme, style) in graphemes {
+                    let current_line = new_lines.last_mut().unwrap();
+
+                    if grapheme == "\n" || grapheme == "\r\n" {
+                        new_lines.push(Vec::new());
+                        break_point = None;
+                        continue;
+                    }
+
+                    let is_whitespace = grapheme.chars().all(char::is_whitespace);
+                    if current_line.len() == max_len {
+                        if is_whitespace {
+                            new_lines.push(Vec::new());
+                            break_point = None;
+                            continue;
+                        }
+
+                        let carried = match break_point.take() {
+                            Some(position) => {
+                                let carried = current_line.split_off(position + 1);
+                                current_line.pop();
+                                carried
+                            }
+                            None => Vec::new(),
+                        };
+                        new_lines.push(carried);
+                    }
+
+                    let current_line = new_lines.last_mut().unwrap();
+                    if is_whitespace
+                        && current_line
+                            .last()
+                            .is_some_and(|(last, _)| !last.chars().all(char::is_whitespace))
+                    {
+                        break_point = Some(current_line.len());
+                    }
+                    current_line.push((grapheme, style));
+                }
+
+                new_lines
+                    .into_iter()
+                    .map(|graphemes| {
+                        let mut spans: Vec<Span<'static>> = Vec::new();
+                        for (grapheme, style) in graphemes {
+                            match spans.last_mut() {
+                                Some(span) if span.style == style => {
+                                    span.content.to_mut().push_str(grapheme);
+                                }
+                                _ => spans.push(Span::raw(grapheme.to_owned()).style(style)),
+                            }
+                        }
+                        Line {
+                            alignment: line.alignment,
+                            ..Line::from(spans)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Self {
+            content: new_content,
+        }
+    }
+
+    pub fn hard_wrap_lines(self, max_len: usize) -> Self {
         let new_content: Vec<Line<'static>> = self
             .content
             .into_iter()
@@ -177,10 +272,21 @@ impl Deref for RenderedBlock {
 
 #[derive(Debug, Clone)]
 pub enum InlineElements {
-    RawText { span: Span<'static> },
-    RichText { span: Span<'static> },
-    HyperLink { span: Span<'static>, dest: String },
-    CrossRef { span: Span<'static>, dest: String },
+    RawText {
+        span: Span<'static>,
+    },
+    RichText {
+        span: Span<'static>,
+    },
+    HyperLink {
+        span: Span<'static>,
+        dest: String,
+    },
+    CrossRef {
+        span: Span<'static>,
+        dest: String,
+        heading: Option<String>,
+    },
 }
 
 impl InlineElement for InlineElements {
@@ -206,18 +312,34 @@ impl InlineElement for InlineElements {
                 .filter(|el| !el.is_empty())
                 .map(|el| ChainInlineElement::patch_style(el, STRONG_STYLE))
                 .collect(),
-            mdast::Node::Link(link) => vec![InlineElements::HyperLink {
-                span: Span::raw(
-                    link.children
-                        .iter()
-                        .flat_map(InlineElements::parse_node)
-                        .filter(|el| !el.is_empty())
-                        .map(|el| el.inner_text().to_string())
-                        .collect::<String>(),
-                )
-                .style(HYPER_LINK_STYLE),
-                dest: link.url.to_string(),
-            }],
+            mdast::Node::Link(link) => {
+                let text = link
+                    .children
+                    .iter()
+                    .flat_map(InlineElements::parse_node)
+                    .filter(|el| !el.is_empty())
+                    .map(|el| el.inner_text().to_string())
+                    .collect::<String>();
+                match note_link_dest(link.url.as_str()) {
+                    Some((dest, heading)) => vec![InlineElements::CrossRef {
+                        span: Span::raw(format!(
+                            "[{}]",
+                            if text.is_empty() {
+                                dest.as_str()
+                            } else {
+                                text.as_str()
+                            }
+                        ))
+                        .style(CROSS_REF_STYLE),
+                        dest,
+                        heading,
+                    }],
+                    None => vec![InlineElements::HyperLink {
+                        span: Span::raw(text).style(HYPER_LINK_STYLE),
+                        dest: link.url.clone(),
+                    }],
+                }
+            }
             mdast::Node::Text(text) => parse_cross_links(text.value.as_str()),
             _ => Vec::new(),
         }
@@ -301,6 +423,10 @@ impl InlineElement for SelectableInlineElements {
         self.element.get_inner_span_mut()
     }
 
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+
     fn into_span(self) -> Span<'static> {
         let span = self.element.into_span();
 
@@ -316,11 +442,79 @@ pub enum BlockElements<T>
 where
     T: InlineElement,
 {
-    Paragraph { content: Vec<T> },
-    Heading { content: Vec<T>, level: u8 },
-    BlockQuote { content: Vec<T> },
-    ListItem { content: Vec<T> },
-    UnformatedText { content: Vec<T> },
+    Paragraph {
+        content: Vec<T>,
+    },
+    Heading {
+        content: Vec<T>,
+        level: u8,
+    },
+    BlockQuote {
+        content: Vec<T>,
+    },
+    ListItem {
+        content: Vec<T>,
+    },
+    UnformatedText {
+        content: Vec<T>,
+    },
+    /// `code` keeps the block as written, its lines are only split for display.
+    CodeBlock {
+        code: String,
+        content: Vec<T>,
+    },
+    HiddenComment {
+        content: Vec<T>,
+    },
+    /// `rows` holds the number of elements of each key value.
+    Metadata {
+        keys: Vec<String>,
+        rows: Vec<usize>,
+        content: Vec<T>,
+    },
+}
+
+impl<T> BlockElements<T>
+where
+    T: InlineElement + Clone,
+{
+    /// Parse the leading `Key: value` lines of `text`, returning the text left after them.
+    pub fn parse_metadata(text: &str) -> Option<(Self, &str)> {
+        let mut keys = Vec::new();
+        let mut rows = Vec::new();
+        let mut content = Vec::new();
+        let mut consumed = 0;
+
+        for line in text.split_inclusive('\n') {
+            let Some((key, value)) = metadata_entry(line.trim_end_matches(['\r', '\n'])) else {
+                break;
+            };
+            let value: Vec<T> = to_mdast(value, &ParseOptions::default())
+                .unwrap()
+                .children()
+                .into_iter()
+                .flatten()
+                .flat_map(|block| block.children().into_iter().flatten())
+                .flat_map(InlineElement::parse_node)
+                .collect();
+
+            keys.push(key.to_owned());
+            rows.push(value.len());
+            content.extend(value);
+            consumed += line.len();
+        }
+
+        (keys.len() >= METADATA_MIN_LINES).then(|| {
+            (
+                Self::Metadata {
+                    keys,
+                    rows,
+                    content,
+                },
+                &text[consumed..],
+            )
+        })
+    }
 }
 
 impl<T> BlockElement<T> for BlockElements<T>
@@ -376,7 +570,8 @@ where
                         .collect(),
                 })
                 .collect(),
-            mdast::Node::Code(code) if code.lang.is_none() => vec![Self::UnformatedText {
+            mdast::Node::Code(code) if code.lang.is_none() => vec![Self::CodeBlock {
+                code: code.value.clone(),
                 content: code
                     .value
                     .lines()
@@ -384,6 +579,23 @@ where
                     .map(InlineElement::raw)
                     .collect(),
             }],
+            mdast::Node::Html(html) => match comment_text(html.value.as_str()) {
+                Some(comment) => vec![Self::HiddenComment {
+                    content: comment
+                        .lines()
+                        .map(String::from)
+                        .map(InlineElement::raw)
+                        .collect(),
+                }],
+                None => vec![Self::UnformatedText {
+                    content: html
+                        .value
+                        .lines()
+                        .map(String::from)
+                        .map(InlineElement::raw)
+                        .collect(),
+                }],
+            },
             _ => Vec::new(),
         }
     }
@@ -394,7 +606,10 @@ where
             | Self::Heading { content, .. }
             | Self::BlockQuote { content }
             | Self::ListItem { content }
-            | Self::UnformatedText { content } => content,
+            | Self::UnformatedText { content }
+            | Self::CodeBlock { content, .. }
+            | Self::HiddenComment { content }
+            | Self::Metadata { content, .. } => content,
         }
     }
 
@@ -404,7 +619,10 @@ where
             | Self::Heading { content, .. }
             | Self::BlockQuote { content }
             | Self::ListItem { content }
-            | Self::UnformatedText { content } => content,
+            | Self::UnformatedText { content }
+            | Self::CodeBlock { content, .. }
+            | Self::HiddenComment { content }
+            | Self::Metadata { content, .. } => content,
         }
     }
 
@@ -414,7 +632,10 @@ where
             | Self::Heading { content, .. }
             | Self::BlockQuote { content }
             | Self::ListItem { content }
-            | Self::UnformatedText { content } => content,
+            | Self::UnformatedText { content }
+            | Self::CodeBlock { content, .. }
+            | Self::HiddenComment { content }
+            | Self::Metadata { content, .. } => content,
         }
     }
 
@@ -459,17 +680,82 @@ where
                     .chain(content.iter().cloned().map(InlineElement::into_span))
                     .collect::<Vec<_>>(),
             )],
-            BlockElements::UnformatedText { content } => content
+            BlockElements::UnformatedText { content }
+            | BlockElements::CodeBlock { content, .. } => content
                 .iter()
                 .cloned()
                 .map(InlineElement::into_span)
                 .map(|span| Line::from(vec![span]))
                 .collect(),
+            BlockElements::HiddenComment { content }
+                if content.iter().any(InlineElement::is_selected) =>
+            {
+                content
+                    .iter()
+                    .cloned()
+                    .map(|el| ChainInlineElement::patch_style(el, COMMENT_STYLE))
+                    .map(InlineElement::into_span)
+                    .map(|span| Line::from(vec![span]))
+                    .collect()
+            }
+            BlockElements::HiddenComment { .. } => {
+                vec![Line::from(vec![Span::styled(
+                    COMMENT_MARKER,
+                    COMMENT_STYLE,
+                )])]
+            }
+            BlockElements::Metadata {
+                keys,
+                rows,
+                content,
+            } => {
+                let key_width = keys
+                    .iter()
+                    .map(|key| key.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                let mut values = content.iter().cloned();
+                keys.iter()
+                    .zip(rows)
+                    .map(|(key, length)| {
+                        Line::from(
+                            [Span::styled(
+                                format!("{key:key_width$}  "),
+                                METADATA_KEY_STYLE,
+                            )]
+                            .into_iter()
+                            .chain(values.by_ref().take(*length).map(InlineElement::into_span))
+                            .collect::<Vec<_>>(),
+                        )
+                    })
+                    .chain([Line::default()])
+                    .collect()
+            }
         }
         .into()
     }
 }
 
+/// The text of an html node made of a single comment, trimmed of its blank lines.
+pub fn comment_text(html: &str) -> Option<&str> {
+    let comment = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    (!comment.contains("-->")).then(|| comment.trim())
+}
+
+/// Split a `Key: value` line, keys are words separated by spaces or dashes,
+/// capitalized when they hold several words.
+fn metadata_entry(line: &str) -> Option<(&str, &str)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    let (key, value) = line.split_once(": ")?;
+    let mut chars = key.chars();
+    let first = chars.next()?;
+    // Lowercase keys made of several words are mostly prose, like "that goes on: and on"
+    let is_prose = first.is_lowercase() && key.contains(' ');
+    (is_word_char(first) && !is_prose && chars.all(|c| is_word_char(c) || c == ' ' || c == '-'))
+        .then(|| (key, value.trim()))
+}
+
 fn parse_cross_links(text: &str) -> Vec<InlineElements> {
     let mut content_iter = text.chars().peekable();
     let mut escape = false;
@@ -480,9 +766,20 @@ fn parse_cross_links(text: &str) -> Vec<InlineElements> {
     while let Some(c) = content_iter.next() {
         if cross_ref {
             if c == ']' && matches!(content_iter.peek(), Some(']')) {
-                spans.push(InlineElements::CrossRef {
-                    span: Span::raw(format!("[{current_span}]")).style(CROSS_REF_STYLE),
-                    dest: current_span,
+                let span = Span::raw(format!("[{current_span}]")).style(CROSS_REF_STYLE);
+                spans.push(match current_span.split_once('#') {
+                    Some((dest, heading)) if !heading.trim().is_empty() => {
+                        InlineElements::CrossRef {
+                            span,
+                            dest: dest.to_string(),
+                            heading: Some(heading.trim().to_string()),
+                        }
+                    }
+                    _ => InlineElements::CrossRef {
+                        span,
+                        dest: current_span,
+                        heading: None,
+                    },
                 });
                 current_span = String::new();
                 cross_ref = false;
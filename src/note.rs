@@ -1,14 +1,21 @@
+use std::collections::{HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
+use std::iter;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use serde::Serialize;
 use thiserror::Error;
 
-use rusqlite::{Connection, OptionalExtension};
-use sea_query::{ColumnDef, Expr, Iden, JoinType, Order, Query, SqliteQueryBuilder, Table};
+use rusqlite::{Connection, OptionalExtension, Row};
+use sea_query::{
+    ColumnDef, Cond, Expr, Func, Iden, JoinType, Order, Query, SimpleExpr, SqliteQueryBuilder,
+    Table,
+};
 
-use crate::helpers::{DiscardResult, TryFromDatabase};
-use crate::links::{Link, LinksCharacters, LinksTable};
+use crate::helpers::{edit_distance, is_unique_violation, DiscardResult, TryFromDatabase};
+use crate::links::{Link, LinksCharacters, LinksDiff, LinksTable};
+use crate::markdown::{code_ranges, note_link_dest, note_link_ranges, note_link_url, parse};
 use crate::tag::{Tag, TagsCharacters, TagsJoinCharacters, TagsJoinTable, TagsTable};
 
 #[derive(Iden)]
@@ -28,11 +35,19 @@ pub struct Note {
     pub content: String,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NotesOrder {
+    Name,
+    Size,
+}
+
+#[derive(Debug, Serialize)]
 pub struct NoteSummary {
     pub id: i64,
     pub name: String,
-    pub tags: Vec<Tag>,
+    pub outgoing_links: u32,
+    pub incoming_links: u32,
+    pub content_len: u32,
 }
 
 #[derive(Debug)]
@@ -46,10 +61,21 @@ pub struct NoteData {
 pub enum NoteError {
     #[error("No such note exists")]
     NoteDoesNotExist,
+    #[error("The file {file:?} isn't valid UTF-8")]
+    InvalidEncoding { file: PathBuf },
+    #[error("The name is empty")]
+    EmptyName,
+    #[error("A note named {name:?} already exists")]
+    NoteAlreadyExists { name: String },
 }
 
 impl Note {
-    pub fn new(name: String, content: String, db: &Connection) -> Result<Self> {
+    pub fn normalize_name(name: &str) -> String {
+        name.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    pub fn new(name: &str, content: String, db: &Connection) -> Result<Self> {
+        let name = Note::normalize_name(name);
         db.execute_batch(
             Query::insert()
                 .into_table(NotesTable)
@@ -57,13 +83,83 @@ impl Note {
                 .values([name.as_str().into(), content.as_str().into()])?
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
-        )?;
+        )
+        .map_err(anyhow::Error::from)
+        .map_err(|err| {
+            if is_unique_violation(&err) {
+                NoteError::NoteAlreadyExists { name: name.clone() }.into()
+            } else {
+                err
+            }
+        })?;
 
-        Ok(Self {
+        let note = Self {
             id: db.last_insert_rowid(),
             name,
             content,
-        })
+        };
+        note.index_backlinks(db)?;
+
+        Ok(note)
+    }
+
+    /// Add the missing links of the notes whose content references this one,
+    /// as they may have been written before it existed.
+    /// Returns the number of links added.
+    pub fn index_backlinks(&self, db: &Connection) -> Result<usize> {
+        let candidates: Vec<Note> = db
+            .prepare(
+                Query::select()
+                    .from(NotesTable)
+                    .columns([
+                        NotesCharacters::Id,
+                        NotesCharacters::Name,
+                        NotesCharacters::Content,
+                    ])
+                    .and_where(Expr::col(NotesCharacters::Id).ne(self.id))
+                    // The names in note: links may be percent-encoded, so their words aren't filtered
+                    .cond_where(
+                        Cond::any()
+                            .add(self.name.split_whitespace().fold(
+                                Cond::all().add(Expr::col(NotesCharacters::Content).like("%[[%")),
+                                |cond, word| {
+                                    cond.add(
+                                        Expr::col(NotesCharacters::Content)
+                                            .like(format!("%{word}%")),
+                                    )
+                                },
+                            ))
+                            .add(Expr::col(NotesCharacters::Content).like("%note:%")),
+                    )
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    content: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut added = 0;
+        for note in candidates {
+            let references_self = Link::from_markdown(note.id, &parse(note.content.as_str()))
+                .iter()
+                .any(|link| link.to == self.name);
+            if !references_self {
+                continue;
+            }
+
+            let mut note_data = NoteData::try_from_database(note, db)?;
+            if note_data.links.iter().all(|link| link.to != self.name) {
+                note_data.add_link(self.name.as_str(), db)?;
+                added += 1;
+            }
+        }
+
+        Ok(added)
     }
 
     pub fn load_by_id(id: i64, db: &Connection) -> Result<Option<Self>> {
@@ -83,11 +179,12 @@ impl Note {
     }
 
     pub fn load_by_name(name: &str, db: &Connection) -> Result<Option<Self>> {
+        let name = Note::normalize_name(name);
         db.query_row(
             Query::select()
                 .from(NotesTable)
                 .columns([NotesCharacters::Id, NotesCharacters::Content])
-                .and_where(Expr::col(NotesCharacters::Name).eq(name))
+                .and_where(Expr::col(NotesCharacters::Name).eq(name.as_str()))
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
             [],
@@ -95,13 +192,7 @@ impl Note {
         )
         .optional()
         .map_err(anyhow::Error::from)
-        .map(|res| {
-            res.map(|(id, content)| Note {
-                id,
-                name: name.to_string(),
-                content,
-            })
-        })
+        .map(|res| res.map(|(id, content)| Note { id, name, content }))
     }
 
     pub fn update(&self, db: &Connection) -> Result<()> {
@@ -119,6 +210,18 @@ impl Note {
         .map_err(anyhow::Error::from)
     }
 
+    pub fn rename(&mut self, name: &str, db: &Connection) -> Result<()> {
+        let old_name = std::mem::replace(&mut self.name, Note::normalize_name(name));
+        self.update(db).map_err(|err| {
+            let name = std::mem::replace(&mut self.name, old_name);
+            if is_unique_violation(&err) {
+                NoteError::NoteAlreadyExists { name }.into()
+            } else {
+                err
+            }
+        })
+    }
+
     pub fn delete(self, db: &Connection) -> Result<()> {
         db.execute_batch(
             Query::delete()
@@ -135,7 +238,10 @@ impl Note {
     }
 
     pub fn import_content(&mut self, file: &Path) -> Result<()> {
-        self.content = String::from_utf8(fs::read(file)?)?;
+        self.content =
+            String::from_utf8(fs::read(file)?).map_err(|_| NoteError::InvalidEncoding {
+                file: file.to_path_buf(),
+            })?;
         Ok(())
     }
 
@@ -144,7 +250,7 @@ impl Note {
             Query::select()
                 .from(NotesTable)
                 .column(NotesCharacters::Id)
-                .and_where(Expr::col(NotesCharacters::Name).eq(name))
+                .and_where(Expr::col(NotesCharacters::Name).eq(Note::normalize_name(name)))
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
@@ -152,6 +258,18 @@ impl Note {
         .map_err(anyhow::Error::from)
     }
 
+    pub fn validate_name(name: &str, db: &Connection) -> Result<Option<NoteError>> {
+        let name = Note::normalize_name(name);
+        Ok(if name.is_empty() {
+            Some(NoteError::EmptyName)
+        } else if Note::note_exists(name.as_str(), db)? {
+            Some(NoteError::NoteAlreadyExists { name })
+        } else {
+            None
+        })
+    }
+
+    /// Tags are ordered by name.
     pub fn list_tags(id: i64, db: &Connection) -> Result<Vec<Tag>> {
         db.prepare(
             Query::select()
@@ -167,6 +285,8 @@ impl Note {
                         .equals((TagsJoinTable, TagsJoinCharacters::TagId)),
                 )
                 .and_where(Expr::col(TagsJoinCharacters::NoteId).eq(id))
+                .and_where(Expr::col((TagsTable, TagsCharacters::DeletedAt)).is_null())
+                .order_by((TagsTable, TagsCharacters::Name), Order::Asc)
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
@@ -178,12 +298,14 @@ impl Note {
         .collect::<Result<Vec<Tag>>>()
     }
 
+    /// Links are ordered by insertion.
     pub fn list_links(id: i64, db: &Connection) -> Result<Vec<Link>> {
         db.prepare(
             Query::select()
-                .from(TagsJoinTable)
+                .from(LinksTable)
                 .columns([LinksCharacters::ToName])
                 .and_where(Expr::col(LinksCharacters::FromId).eq(id))
+                .order_by(LinksCharacters::Id, Order::Asc)
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
@@ -194,36 +316,307 @@ impl Note {
         })
         .collect()
     }
+
+    pub fn count_backlinks(name: &str, db: &Connection) -> Result<usize> {
+        db.query_row(
+            Query::select()
+                .from(LinksTable)
+                .expr(Expr::col(LinksCharacters::Id).count())
+                .and_where(Expr::col(LinksCharacters::ToName).eq(name))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+            |row| row.get(0),
+        )
+        .map_err(anyhow::Error::from)
+    }
+
+    /// Names are ordered alphabetically.
+    pub fn list_backlinks(name: &str, db: &Connection) -> Result<Vec<String>> {
+        db.prepare(
+            Query::select()
+                .distinct()
+                .from(LinksTable)
+                .column((NotesTable, NotesCharacters::Name))
+                .join(
+                    JoinType::InnerJoin,
+                    NotesTable,
+                    Expr::col((LinksTable, LinksCharacters::FromId))
+                        .equals((NotesTable, NotesCharacters::Id)),
+                )
+                .and_where(Expr::col((LinksTable, LinksCharacters::ToName)).eq(name))
+                .order_by((NotesTable, NotesCharacters::Name), Order::Asc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], |row| row.get(0))?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// Rewrite the cross-references to `old_name` in the notes linking to it,
+    /// keeping their heading fragments.
+    /// Returns the number of notes rewritten.
+    pub fn rewrite_references(old_name: &str, new_name: &str, db: &Connection) -> Result<usize> {
+        let old_name = Note::normalize_name(old_name);
+        let new_name = Note::normalize_name(new_name);
+
+        let referencing: Vec<Note> = db
+            .prepare(
+                Query::select()
+                    .distinct()
+                    .from(LinksTable)
+                    .columns([
+                        (NotesTable, NotesCharacters::Id),
+                        (NotesTable, NotesCharacters::Name),
+                        (NotesTable, NotesCharacters::Content),
+                    ])
+                    .join(
+                        JoinType::InnerJoin,
+                        NotesTable,
+                        Expr::col((LinksTable, LinksCharacters::FromId))
+                            .equals((NotesTable, NotesCharacters::Id)),
+                    )
+                    .and_where(
+                        Expr::col((LinksTable, LinksCharacters::ToName)).eq(old_name.as_str()),
+                    )
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    content: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut rewritten = 0;
+        for mut note in referencing {
+            let cross_refs =
+                rewrite_cross_refs(note.content.as_str(), old_name.as_str(), new_name.as_str());
+            let Some(content) = rewrite_note_links(
+                cross_refs.as_deref().unwrap_or(note.content.as_str()),
+                old_name.as_str(),
+                new_name.as_str(),
+            )
+            .or(cross_refs) else {
+                continue;
+            };
+            note.content = content;
+            note.update(db)?;
+
+            let links = Link::from_markdown(note.id, &parse(note.content.as_str()));
+            NoteData::try_from_database(note, db)?.update_links(&links, db)?;
+            rewritten += 1;
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Notes are ordered by link distance from the first one, then by link insertion.
+    /// Links to missing notes are returned separately.
+    pub fn linked_notes(self, depth: usize, db: &Connection) -> Result<(Vec<Note>, Vec<String>)> {
+        let mut visited = HashSet::from([self.name.clone()]);
+        let mut queue = VecDeque::from([(self, 0)]);
+        let mut notes = Vec::new();
+        let mut dangling = Vec::new();
+
+        while let Some((note, distance)) = queue.pop_front() {
+            if distance < depth {
+                for link in Note::list_links(note.id, db)? {
+                    if !visited.insert(link.to.clone()) {
+                        continue;
+                    }
+
+                    match Note::load_by_name(link.to.as_str(), db)? {
+                        Some(linked_note) => queue.push_back((linked_note, distance + 1)),
+                        None => dangling.push(link.to),
+                    }
+                }
+            }
+            notes.push(note);
+        }
+
+        Ok((notes, dangling))
+    }
 }
 
 impl NoteSummary {
-    pub fn search_by_name(pattern: &str, db: &Connection) -> Result<Vec<Self>> {
+    /// At most `limit` summaries are returned when one is given.
+    pub fn search_by_name(
+        pattern: &str,
+        order: NotesOrder,
+        limit: Option<usize>,
+        db: &Connection,
+    ) -> Result<Vec<Self>> {
+        let mut query = Query::select();
+        query
+            .from(NotesTable)
+            .columns([NotesCharacters::Id, NotesCharacters::Name])
+            .expr(LinksTable::outgoing_count())
+            .expr(LinksTable::incoming_count())
+            .expr(NotesTable::content_length())
+            .and_where(Expr::col(NotesCharacters::Name).like(format!("%{pattern}%")));
+        if order == NotesOrder::Size {
+            query.order_by_expr(NotesTable::content_length(), Order::Desc);
+        }
+        query.order_by(NotesCharacters::Name, Order::Asc);
+        if let Some(limit) = limit {
+            query.limit(limit as u64);
+        }
+
+        db.prepare(query.to_string(SqliteQueryBuilder).as_str())?
+            .query_map([], NoteSummary::from_row)?
+            .map(|row| row.map_err(anyhow::Error::from))
+            .collect()
+    }
+
+    /// Summaries are ordered by name.
+    pub fn search_by_content(pattern: &str, db: &Connection) -> Result<Vec<Self>> {
         db.prepare(
             Query::select()
                 .from(NotesTable)
                 .columns([NotesCharacters::Id, NotesCharacters::Name])
+                .expr(LinksTable::outgoing_count())
+                .expr(LinksTable::incoming_count())
+                .expr(NotesTable::content_length())
+                .and_where(Expr::col(NotesCharacters::Content).like(format!("%{pattern}%")))
                 .order_by(NotesCharacters::Name, Order::Asc)
-                .and_where(Expr::col(NotesCharacters::Name).like(format!("%{pattern}%")))
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
-        .map(|row| -> Result<(i64, String)> { row.map_err(anyhow::Error::from) })
-        .map(|row| {
-            row.and_then(|(id, name)| {
-                Ok(NoteSummary {
-                    id,
-                    name,
-                    tags: Note::list_tags(id, db)?,
+        .query_map([], NoteSummary::from_row)?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// Summaries are ordered by the number of words prefixed by a word of the name, then by name.
+    pub fn search_similar(name: &str, limit: usize, db: &Connection) -> Result<Vec<Self>> {
+        let words: Vec<String> = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut summaries = db
+            .prepare(
+                Query::select()
+                    .from(NotesTable)
+                    .columns([NotesCharacters::Id, NotesCharacters::Name])
+                    .expr(LinksTable::outgoing_count())
+                    .expr(LinksTable::incoming_count())
+                    .expr(NotesTable::content_length())
+                    .cond_where(words.iter().fold(Cond::any(), |cond, word| {
+                        cond.add(Expr::col(NotesCharacters::Name).like(format!("%{word}%")))
+                    }))
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], NoteSummary::from_row)?
+            .map(|row| {
+                row.map_err(anyhow::Error::from).map(|summary| {
+                    let name = summary.name.to_lowercase();
+                    let shared_words = words
+                        .iter()
+                        .filter(|word| {
+                            name.split(|c: char| !c.is_alphanumeric())
+                                .any(|note_word| note_word.starts_with(word.as_str()))
+                        })
+                        .count();
+                    (shared_words, summary)
                 })
             })
+            .collect::<Result<Vec<(usize, NoteSummary)>>>()?;
+
+        summaries.retain(|(shared_words, _)| *shared_words > 0);
+        summaries.sort_by(|(a_shared, a), (b_shared, b)| {
+            b_shared.cmp(a_shared).then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(summaries
+            .into_iter()
+            .take(limit)
+            .map(|(_, summary)| summary)
+            .collect())
+    }
+
+    /// The notes whose name is within a few typos of `name`, the closest first.
+    /// A name is also compared word for word, so a part of a longer name matches.
+    pub fn search_closest(name: &str, limit: usize, db: &Connection) -> Result<Vec<Self>> {
+        let name = Note::normalize_name(name).to_lowercase();
+        let word_count = name.split(' ').count();
+        let max_distance = (name.chars().count() / 3).max(2);
+
+        let mut summaries = db
+            .prepare(
+                Query::select()
+                    .from(NotesTable)
+                    .columns([NotesCharacters::Id, NotesCharacters::Name])
+                    .expr(LinksTable::outgoing_count())
+                    .expr(LinksTable::incoming_count())
+                    .expr(NotesTable::content_length())
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], NoteSummary::from_row)?
+            .map(|row| {
+                row.map_err(anyhow::Error::from).map(|summary| {
+                    let note_name = summary.name.to_lowercase();
+                    let note_words: Vec<&str> = note_name.split(' ').collect();
+                    let distance = note_words
+                        .windows(word_count.min(note_words.len()))
+                        .map(|window| edit_distance(name.as_str(), window.join(" ").as_str()))
+                        .chain([edit_distance(name.as_str(), note_name.as_str())])
+                        .min()
+                        .unwrap_or(usize::MAX);
+                    (distance, summary)
+                })
+            })
+            .collect::<Result<Vec<(usize, NoteSummary)>>>()?;
+
+        summaries.retain(|(distance, _)| *distance <= max_distance);
+        summaries.sort_by(|(a_distance, a), (b_distance, b)| {
+            a_distance.cmp(b_distance).then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(summaries
+            .into_iter()
+            .take(limit)
+            .map(|(_, summary)| summary)
+            .collect())
+    }
+
+    // Expects the id, name, outgoing and incoming links count, then content length columns in order.
+    pub fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(NoteSummary {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            outgoing_links: row.get(2)?,
+            incoming_links: row.get(3)?,
+            content_len: row.get(4)?,
         })
-        .collect()
     }
 }
 
 impl NoteData {
+    /// The tags implied by `tag` are added too, unless the note already has them.
     pub fn add_tag(&mut self, tag: Tag, db: &Connection) -> Result<()> {
+        let implied = tag.implied(db)?;
+        self.insert_tag(tag, db)?;
+        for tag in implied {
+            if self.tags.iter().all(|t| t.id != tag.id) {
+                self.insert_tag(tag, db)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_tag(&mut self, tag: Tag, db: &Connection) -> Result<()> {
         let tag_id = tag.id;
         self.tags.push(tag);
         db.execute_batch(
@@ -253,6 +646,46 @@ impl NoteData {
         .map_err(anyhow::Error::from)
     }
 
+    /// Swap the tags in a single transaction so the note never ends up with both or neither.
+    /// The tags implied by the new one are added along with it, as with `add_tag`.
+    pub fn replace_tag(&mut self, old: &Tag, new: Tag, db: &Connection) -> Result<()> {
+        let implied: Vec<Tag> = new
+            .implied(db)?
+            .into_iter()
+            .filter(|tag| tag.id == old.id || self.tags.iter().all(|t| t.id != tag.id))
+            .collect();
+
+        let transaction = db.unchecked_transaction()?;
+        transaction.execute_batch(
+            Query::delete()
+                .from_table(TagsJoinTable)
+                .and_where(
+                    Expr::col(TagsJoinCharacters::TagId)
+                        .eq(old.id)
+                        .and(Expr::col(TagsJoinCharacters::NoteId).eq(self.note.id)),
+                )
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+        for tag_id in iter::once(new.id).chain(implied.iter().map(|tag| tag.id)) {
+            transaction.execute_batch(
+                Query::insert()
+                    .into_table(TagsJoinTable)
+                    .columns([TagsJoinCharacters::NoteId, TagsJoinCharacters::TagId])
+                    .values([self.note.id.into(), tag_id.into()])?
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?;
+        }
+        transaction.commit()?;
+
+        if let Some(tag) = self.tags.iter_mut().find(|t| t.id == old.id) {
+            *tag = new;
+        }
+        self.tags.extend(implied);
+        Ok(())
+    }
+
     pub fn add_link(&mut self, to: &str, db: &Connection) -> Result<()> {
         self.links.push(Link {
             from: self.note.id,
@@ -284,6 +717,30 @@ impl NoteData {
         )
         .map_err(anyhow::Error::from)
     }
+
+    /// What `update_links` would change, without touching the database.
+    pub fn diff_links(&self, computed_links: &[Link]) -> LinksDiff {
+        LinksDiff::compute(&self.links, computed_links)
+    }
+
+    pub fn update_links(&mut self, computed_links: &[Link], db: &Connection) -> Result<LinksDiff> {
+        let diff = self.diff_links(computed_links);
+
+        for name in &diff.removed {
+            self.remove_link(name.as_str(), db)?;
+        }
+
+        let added: Vec<&Link> = computed_links
+            .iter()
+            .filter(|link| !self.links.contains(link))
+            .collect();
+
+        for link in &added {
+            self.add_link(link.to.as_str(), db)?;
+        }
+
+        Ok(diff)
+    }
 }
 
 impl TryFromDatabase<NoteSummary> for Note {
@@ -302,16 +759,6 @@ impl From<NoteData> for Note {
     }
 }
 
-impl TryFromDatabase<Note> for NoteSummary {
-    fn try_from_database(note: Note, db: &Connection) -> Result<Self> {
-        Ok(NoteSummary {
-            id: note.id,
-            tags: Note::list_tags(note.id, db)?,
-            name: note.name,
-        })
-    }
-}
-
 impl TryFromDatabase<Note> for NoteData {
     fn try_from_database(note: Note, db: &Connection) -> Result<Self> {
         Ok(NoteData {
@@ -346,4 +793,381 @@ impl NotesTable {
         )
         .discard_result()
     }
+
+    pub fn content_length() -> SimpleExpr {
+        Func::if_null(
+            Func::char_length(Expr::col((NotesTable, NotesCharacters::Content))),
+            0,
+        )
+        .into()
+    }
+
+    // Returns the names which couldn't be normalized without colliding with another note.
+    pub fn normalize_names(db: &Connection) -> Result<Vec<String>> {
+        let names: Vec<(i64, String)> = db
+            .prepare(
+                Query::select()
+                    .from(NotesTable)
+                    .columns([NotesCharacters::Id, NotesCharacters::Name])
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut collisions = Vec::new();
+        let mut renamed = false;
+        for (id, name) in names {
+            let normalized_name = Note::normalize_name(name.as_str());
+            if normalized_name == name {
+                continue;
+            }
+
+            if Note::note_exists(normalized_name.as_str(), db)? {
+                collisions.push(name);
+            } else {
+                db.execute_batch(
+                    Query::update()
+                        .table(NotesTable)
+                        .value(NotesCharacters::Name, normalized_name)
+                        .and_where(Expr::col(NotesCharacters::Id).eq(id))
+                        .to_string(SqliteQueryBuilder)
+                        .as_str(),
+                )?;
+                renamed = true;
+            }
+        }
+
+        // The links still point to the old names
+        if renamed {
+            NotesTable::reindex_links(db)?;
+        }
+
+        Ok(collisions)
+    }
+
+    // Returns the number of links which changed.
+    pub fn reindex_links(db: &Connection) -> Result<usize> {
+        let notes: Vec<Note> = db
+            .prepare(
+                Query::select()
+                    .from(NotesTable)
+                    .columns([
+                        NotesCharacters::Id,
+                        NotesCharacters::Name,
+                        NotesCharacters::Content,
+                    ])
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    content: row.get(2)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let transaction = db.unchecked_transaction()?;
+        let mut changed = 0;
+        for note in notes {
+            let computed_links = Link::from_markdown(note.id, &parse(note.content.as_str()));
+            changed += NoteData::try_from_database(note, &transaction)?
+                .update_links(&computed_links, &transaction)?
+                .changes();
+        }
+        transaction.commit()?;
+
+        Ok(changed)
+    }
+}
+
+fn rewrite_cross_refs(content: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let code_ranges = code_ranges(content);
+    let mut rewritten = String::with_capacity(content.len());
+    let mut changed = false;
+    let mut rest = content;
+
+    while let Some(start) = rest.find("[[") {
+        let Some(end) = rest[start + 2..].find("]]").map(|end| start + 2 + end) else {
+            break;
+        };
+        let offset = content.len() - rest.len();
+        if code_ranges
+            .iter()
+            .any(|range| range.contains(&(offset + start)))
+        {
+            rewritten.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let inner = &rest[start + 2..end];
+        let (dest, heading) = match inner.split_once('#') {
+            Some((dest, heading)) if !heading.trim().is_empty() => (dest, Some(heading)),
+            _ => (inner, None),
+        };
+
+        rewritten.push_str(&rest[..start]);
+        if Note::normalize_name(dest) == old_name {
+            rewritten.push_str("[[");
+            rewritten.push_str(new_name);
+            if let Some(heading) = heading {
+                rewritten.push('#');
+                rewritten.push_str(heading);
+            }
+            rewritten.push_str("]]");
+            changed = true;
+        } else {
+            rewritten.push_str(&rest[start..end + 2]);
+        }
+        rest = &rest[end + 2..];
+    }
+    rewritten.push_str(rest);
+
+    changed.then_some(rewritten)
+}
+
+fn rewrite_note_links(content: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let mut rewritten = content.to_owned();
+    let mut changed = false;
+
+    for (range, url) in note_link_ranges(content).into_iter().rev() {
+        if let Some((dest, heading)) = note_link_dest(url.as_str()) {
+            if Note::normalize_name(dest.as_str()) == old_name {
+                rewritten
+                    .replace_range(range, note_link_url(new_name, heading.as_deref()).as_str());
+                changed = true;
+            }
+        }
+    }
+
+    changed.then_some(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::notebook::test_database;
+
+    #[test]
+    fn creating_a_referenced_note_indexes_its_backlinks() {
+        let db = test_database();
+        Note::new("Source", String::from("See [[Big  target]]."), &db).unwrap();
+        Note::new("Mention", String::from("Big target, without a link."), &db).unwrap();
+        assert!(Note::list_backlinks("Big target", &db).unwrap().is_empty());
+
+        let target = Note::new("Big target", String::new(), &db).unwrap();
+        assert_eq!(
+            Note::list_backlinks("Big target", &db).unwrap(),
+            vec![String::from("Source")]
+        );
+        assert_eq!(target.index_backlinks(&db).unwrap(), 0);
+    }
+
+    #[test]
+    fn creating_a_note_indexes_its_note_url_backlinks() {
+        let db = test_database();
+        Note::new(
+            "Source",
+            String::from("See [the menu](note:Caf%C3%A9%20menu)."),
+            &db,
+        )
+        .unwrap();
+        assert!(Note::list_backlinks("Café menu", &db).unwrap().is_empty());
+
+        Note::new("Café menu", String::new(), &db).unwrap();
+        assert_eq!(
+            Note::list_backlinks("Café menu", &db).unwrap(),
+            vec![String::from("Source")]
+        );
+    }
+
+    #[test]
+    fn rejected_names_explain_why() {
+        let db = test_database();
+        Note::new("Existing note", String::new(), &db).unwrap();
+
+        let empty = Note::validate_name("  ", &db).unwrap().unwrap();
+        assert!(matches!(empty, NoteError::EmptyName));
+        assert_eq!(empty.to_string(), "The name is empty");
+
+        let taken = Note::validate_name("Existing  note", &db).unwrap().unwrap();
+        assert_eq!(
+            taken.to_string(),
+            "A note named \"Existing note\" already exists"
+        );
+
+        assert!(Note::validate_name("Other note", &db).unwrap().is_none());
+    }
+
+    #[test]
+    fn duplicate_names_are_typed_errors() {
+        let db = test_database();
+        Note::new("First", String::new(), &db).unwrap();
+        let mut second = Note::new("Second", String::new(), &db).unwrap();
+
+        let created = Note::new("First", String::new(), &db).unwrap_err();
+        assert!(matches!(
+            created.downcast_ref::<NoteError>(),
+            Some(NoteError::NoteAlreadyExists { name }) if name == "First"
+        ));
+
+        let renamed = second.rename("First", &db).unwrap_err();
+        assert!(matches!(
+            renamed.downcast_ref::<NoteError>(),
+            Some(NoteError::NoteAlreadyExists { name }) if name == "First"
+        ));
+        assert_eq!(second.name, "Second");
+    }
+
+    #[test]
+    fn closest_names_tolerate_typos() {
+        let db = test_database();
+        for name in [
+            "Meeting notes",
+            "Weekly meeting",
+            "Greeting cards",
+            "Shopping",
+        ] {
+            Note::new(name, String::new(), &db).unwrap();
+        }
+
+        let closest: Vec<String> = NoteSummary::search_closest("meetng", 5, &db)
+            .unwrap()
+            .into_iter()
+            .map(|summary| summary.name)
+            .collect();
+        assert_eq!(closest, ["Meeting notes", "Weekly meeting"]);
+        assert!(NoteSummary::search_closest("xyz", 5, &db)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn normalizing_names_updates_links() {
+        let db = test_database();
+        db.execute_batch(
+            "INSERT INTO notes_table (id, name, content) VALUES (1, 'Spaced   name', ''), \
+             (2, 'Source', 'See [[Spaced   name]]'); \
+             INSERT INTO links_table (from_id, to_name) VALUES (2, 'Spaced   name');",
+        )
+        .unwrap();
+
+        assert!(NotesTable::normalize_names(&db).unwrap().is_empty());
+
+        let to_name: String = db
+            .query_row(
+                "SELECT to_name FROM links_table WHERE from_id = 2",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(to_name, "Spaced name");
+        assert!(Note::note_exists("Spaced name", &db).unwrap());
+    }
+
+    #[test]
+    fn import_rejects_invalid_bytes() {
+        let file = env::temp_dir().join(format!("foucault-test-{}-invalid.md", std::process::id()));
+        fs::write(&file, b"valid start \xff\xfe end").unwrap();
+        let mut note = Note {
+            id: 1,
+            name: String::from("Note"),
+            content: String::from("Original content"),
+        };
+
+        let err = note.import_content(&file).unwrap_err();
+        fs::remove_file(&file).unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<NoteError>(),
+            Some(NoteError::InvalidEncoding { file: error_file }) if *error_file == file
+        ));
+        assert_eq!(note.content, "Original content");
+    }
+
+    #[test]
+    fn renaming_skips_references_in_code() {
+        assert_eq!(
+            rewrite_cross_refs(
+                "[[Old]] `[[Old]]` ``a ` [[Old]]`` [[Old#Part]]",
+                "Old",
+                "New"
+            )
+            .as_deref(),
+            Some("[[New]] `[[Old]]` ``a ` [[Old]]`` [[New#Part]]")
+        );
+        assert_eq!(
+            rewrite_cross_refs("[[Old]]\n\n```\n[[Old]]\n```\n\n[[Old]]", "Old", "New").as_deref(),
+            Some("[[New]]\n\n```\n[[Old]]\n```\n\n[[New]]")
+        );
+        assert_eq!(
+            rewrite_cross_refs("[[Old]]\n\n````\n```\n[[Old]]", "Old", "New").as_deref(),
+            Some("[[New]]\n\n````\n```\n[[Old]]")
+        );
+        assert_eq!(
+            rewrite_cross_refs("``` [[Old]]\n[[Old]]", "Old", "New"),
+            None
+        );
+    }
+
+    #[test]
+    fn replacing_a_tag_adds_its_implications() {
+        let db = test_database();
+        let [draft, project, work, kept] =
+            ["draft", "project", "work", "kept"].map(|name| Tag::new(name, &db).unwrap());
+        project.add_implication(&work, &db).unwrap();
+        project.add_implication(&kept, &db).unwrap();
+
+        let note = Note::new("Plan", String::new(), &db).unwrap();
+        let mut note_data = NoteData::try_from_database(note, &db).unwrap();
+        note_data.add_tag(draft.clone(), &db).unwrap();
+        note_data.add_tag(kept, &db).unwrap();
+        note_data.replace_tag(&draft, project, &db).unwrap();
+
+        let names = |tags: Vec<Tag>| {
+            let mut names: Vec<String> = tags.into_iter().map(|tag| tag.name).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(note_data.tags.clone()), ["kept", "project", "work"]);
+        assert_eq!(
+            names(Note::list_tags(note_data.note.id, &db).unwrap()),
+            ["kept", "project", "work"]
+        );
+    }
+
+    #[test]
+    fn renaming_rewrites_every_note_link_form() {
+        for (old, new) in [
+            ("Old", "New name"),
+            ("Folder/Old", "Folder/Été 日本"),
+            ("100% done", "Paren (x) <y>"),
+        ] {
+            let url = note_link_url(old, None);
+            let content = format!(
+                "[a]({url}) [b]({url} \"title\") [c](<{url}>) [d]({}) [e](note:Other)\n\n\
+                 `[f]({url})`\n\n[ref]: {url}",
+                note_link_url(old, Some("Part"))
+            );
+
+            let rewritten = rewrite_note_links(content.as_str(), old, new).unwrap();
+            let new_url = note_link_url(new, None);
+            assert_eq!(
+                rewritten,
+                format!(
+                    "[a]({new_url}) [b]({new_url} \"title\") [c](<{new_url}>) [d]({}) \
+                     [e](note:Other)\n\n`[f]({url})`\n\n[ref]: {new_url}",
+                    note_link_url(new, Some("Part"))
+                )
+            );
+            assert_eq!(
+                parse(rewritten.as_str()).list_links(),
+                [new, new, new, new, "Other"]
+            );
+        }
+    }
 }
@@ -1,20 +1,28 @@
+use std::cell::{Cell, Ref, RefCell};
 use std::path::{Path, PathBuf};
 use std::{env, fs};
 
 use anyhow::Result;
-use log::error;
+use chrono::{Duration, Utc};
+use log::{error, info, warn};
 use thiserror::Error;
 
-use rusqlite::Connection;
+use rusqlite::{Connection, ErrorCode};
+use sea_query::{Expr, Query, SqliteQueryBuilder};
 
+use crate::attachment::AttachmentsTable;
 use crate::links::LinksTable;
-use crate::note::NotesTable;
-use crate::tag::{TagsJoinTable, TagsTable};
+use crate::note::{NotesCharacters, NotesTable};
+use crate::settings::{NotebookSettings, SettingsTable};
+use crate::stats::{NotebookStats, StatsHistoryTable};
+use crate::tag::{ImplicationsTable, Tag, TagsJoinTable, TagsTable, TAG_TRASH_DAYS};
 
 pub struct Notebook {
     pub name: String,
     file: PathBuf,
     database: Connection,
+    settings: RefCell<NotebookSettings>,
+    note_count: Cell<usize>,
 }
 
 #[derive(Error, Debug)]
@@ -27,6 +35,22 @@ pub enum OpeningError {
 pub enum CreationError {
     #[error("Another notebook named {name:?} was found.")]
     NotebookAlreadyExists { name: String },
+    #[error("The notebook name can't be empty.")]
+    EmptyName,
+    #[error("The notebook name can't contain path separators.")]
+    InvalidName,
+}
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("The backup path {path:?} isn't valid unicode.")]
+    InvalidPath { path: PathBuf },
+}
+
+#[derive(Error, Debug)]
+pub enum RenamingError {
+    #[error("The notebook {name:?} is open elsewhere.")]
+    NotebookInUse { name: String },
 }
 
 #[derive(Error, Debug)]
@@ -44,6 +68,70 @@ impl Notebook {
         self.file.parent()
     }
 
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.file.with_extension("attachments")
+    }
+
+    pub fn settings(&self) -> Ref<'_, NotebookSettings> {
+        self.settings.borrow()
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.settings.borrow_mut().set(key, value, &self.database)
+    }
+
+    /// The count is only as fresh as the last call to `refresh_note_count`.
+    pub fn note_count(&self) -> usize {
+        self.note_count.get()
+    }
+
+    pub fn refresh_note_count(&self) -> Result<()> {
+        self.note_count.set(
+            self.database.query_row(
+                Query::select()
+                    .from(NotesTable)
+                    .expr(Expr::col(NotesCharacters::Id).count())
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+                [],
+                |row| row.get(0),
+            )?,
+        );
+        Ok(())
+    }
+
+    /// The size of the database file along with its write-ahead log.
+    pub fn file_size(&self) -> Result<u64> {
+        let wal_size =
+            fs::metadata(self.file.with_extension("book-wal")).map_or(0, |metadata| metadata.len());
+        Ok(fs::metadata(&self.file)?.len() + wal_size)
+    }
+
+    /// Rebuild the database to reclaim the space left by deleted content.
+    pub fn compact(&self) -> Result<()> {
+        let journal_mode: String = self
+            .database
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        if journal_mode.eq_ignore_ascii_case("wal") {
+            self.database
+                .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        }
+        self.database.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    /// Write a consistent copy of the database, even while another connection is writing to it.
+    pub fn backup(&self, path: &Path) -> Result<()> {
+        let Some(path) = path.to_str() else {
+            return Err(BackupError::InvalidPath {
+                path: path.to_path_buf(),
+            }
+            .into());
+        };
+        self.database.execute("VACUUM INTO ?1", [path])?;
+        Ok(())
+    }
+
     pub fn open_notebook(name: &str, dir: &Path) -> Result<Self> {
         let notebook_path = {
             let app_dir_notebook_path = dir.join(format!("{name}.book"));
@@ -62,16 +150,41 @@ impl Notebook {
             }
         };
 
-        let database = Connection::open(&notebook_path).unwrap_or_else(|_| {
-            error!("Unable to open the notebook \"{name}\".");
-            todo!();
-        });
+        let database = Connection::open(&notebook_path)
+            .inspect_err(|_| error!("Unable to open the notebook \"{name}\"."))?;
 
-        Ok(Notebook {
+        // Migrate notebooks created before the indexes existed
+        LinksTable::create_indexes(&database)?;
+        SettingsTable::create(&database)?;
+        AttachmentsTable::create(&database)?;
+        StatsHistoryTable::create(&database)?;
+        ImplicationsTable::create(&database)?;
+        TagsTable::migrate(&database)?;
+
+        let purged = Tag::purge_trash(Utc::now() - Duration::days(TAG_TRASH_DAYS), &database)?;
+        if purged > 0 {
+            info!("Purge {purged} tags deleted more than {TAG_TRASH_DAYS} days ago.");
+        }
+
+        for name in NotesTable::normalize_names(&database)? {
+            warn!(
+                "The note {name:?} collides with another note once its whitespaces are normalized."
+            );
+        }
+
+        let notebook = Notebook {
             name: name.to_owned(),
             file: notebook_path,
+            settings: RefCell::new(NotebookSettings::load(&database)?),
             database,
-        })
+            note_count: Cell::new(0),
+        };
+        notebook.refresh_note_count()?;
+        if NotebookStats::snapshot(notebook.db())? {
+            info!("Record the daily statistics of notebook {name}.");
+        }
+
+        Ok(notebook)
     }
 
     pub fn new_notebook(name: &str, dir: &Path) -> Result<Self> {
@@ -85,21 +198,17 @@ impl Notebook {
             .into());
         }
 
-        let database = Connection::open(&notebook_path).unwrap_or_else(|_| {
-            error!("Unable to open the notebook \"{name}\".");
-            todo!();
-        });
+        let database = Connection::open(&notebook_path)
+            .inspect_err(|_| error!("Unable to open the notebook \"{name}\"."))?;
 
-        // Initialize
-        NotesTable::create(&database)?;
-        TagsTable::create(&database)?;
-        TagsJoinTable::create(&database)?;
-        LinksTable::create(&database)?;
+        create_tables(&database)?;
 
         Ok(Notebook {
             name: name.to_owned(),
             file: notebook_path,
+            settings: RefCell::new(NotebookSettings::load(&database)?),
             database,
+            note_count: Cell::new(0),
         })
     }
 
@@ -114,7 +223,200 @@ impl Notebook {
             .into());
         }
 
+        let attachments_dir = notebook_path.with_extension("attachments");
+        if attachments_dir.exists() {
+            fs::remove_dir_all(attachments_dir)?;
+        }
         fs::remove_file(notebook_path)?;
         Ok(())
     }
+
+    pub fn validate_name(name: &str, dir: &Path) -> Option<CreationError> {
+        if name.trim().is_empty() {
+            Some(CreationError::EmptyName)
+        } else if name.contains(['/', '\\']) {
+            Some(CreationError::InvalidName)
+        } else if dir.join(format!("{name}.book")).exists() {
+            Some(CreationError::NotebookAlreadyExists {
+                name: name.to_owned(),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn rename_notebook(name: &str, new_name: &str, dir: &Path) -> Result<()> {
+        let notebook_path = dir.join(format!("{name}.book"));
+        let new_notebook_path = dir.join(format!("{new_name}.book"));
+
+        if !notebook_path.exists() {
+            error!("No notebook named {name} exists.");
+            return Err(OpeningError::NotebookNotFound {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+        if let Some(err) = Notebook::validate_name(new_name, dir) {
+            return Err(err.into());
+        }
+        if Notebook::is_in_use(&notebook_path)? {
+            error!("The notebook {name} is open elsewhere.");
+            return Err(RenamingError::NotebookInUse {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+
+        // The database goes first, its sidecars and attachments are useless without it
+        let mut moved = Vec::new();
+        for extension in [
+            "book",
+            "book-wal",
+            "book-shm",
+            "book-journal",
+            "attachments",
+        ] {
+            let from = notebook_path.with_extension(extension);
+            if !from.exists() {
+                continue;
+            }
+            let to = new_notebook_path.with_extension(extension);
+            if let Err(err) = fs::rename(&from, &to) {
+                for (from, to) in moved.into_iter().rev() {
+                    fs::rename(to, from)?;
+                }
+                return Err(err.into());
+            }
+            moved.push((from, to));
+        }
+        Ok(())
+    }
+
+    /// Whether another connection locks the notebook, or keeps it open in WAL mode.
+    /// Connections idle in the other journal modes hold no lock and go unnoticed.
+    fn is_in_use(notebook_path: &Path) -> Result<bool> {
+        {
+            let database = Connection::open(notebook_path)?;
+            database.busy_timeout(std::time::Duration::ZERO)?;
+            match database.execute_batch("BEGIN EXCLUSIVE; COMMIT;") {
+                Err(err) if err.sqlite_error_code() == Some(ErrorCode::DatabaseBusy) => {
+                    return Ok(true);
+                }
+                result => result?,
+            }
+        }
+        // The last connection to close checkpoints and removes the write-ahead log
+        Ok(notebook_path.with_extension("book-wal").exists())
+    }
+}
+
+fn create_tables(db: &Connection) -> Result<()> {
+    NotesTable::create(db)?;
+    TagsTable::create(db)?;
+    TagsJoinTable::create(db)?;
+    LinksTable::create(db)?;
+    LinksTable::create_indexes(db)?;
+    SettingsTable::create(db)?;
+    AttachmentsTable::create(db)?;
+    StatsHistoryTable::create(db)?;
+    ImplicationsTable::create(db)?;
+    Ok(())
+}
+
+/// An empty notebook database kept in memory, for tests.
+#[cfg(test)]
+pub fn test_database() -> Connection {
+    let db = Connection::open_in_memory().expect("Open an in memory database");
+    create_tables(&db).expect("Create the notebook tables");
+    db
+}
+
+/// An empty notebook kept in memory, for tests.
+#[cfg(test)]
+pub fn test_notebook() -> Notebook {
+    let database = test_database();
+    Notebook {
+        name: String::from("test"),
+        file: PathBuf::new(),
+        settings: RefCell::new(NotebookSettings::load(&database).expect("Load the settings")),
+        database,
+        note_count: Cell::new(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::note::Note;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("foucault-test-{}-{name}", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn renaming_moves_the_sidecars() {
+        let dir = test_dir("renaming");
+        let notebook = Notebook::new_notebook("old", &dir).unwrap();
+        // A persisted journal outlives the connection
+        notebook
+            .db()
+            .execute_batch("PRAGMA journal_mode = PERSIST")
+            .unwrap();
+        Note::new("Kept", String::from("Content"), notebook.db()).unwrap();
+        fs::create_dir_all(notebook.attachments_dir()).unwrap();
+        drop(notebook);
+        assert!(dir.join("old.book-journal").exists());
+
+        Notebook::rename_notebook("old", "new", &dir).unwrap();
+        for extension in ["book", "book-journal", "attachments"] {
+            assert!(!dir.join(format!("old.{extension}")).exists());
+            assert!(dir.join(format!("new.{extension}")).exists());
+        }
+        let renamed = Notebook::open_notebook("new", &dir).unwrap();
+        assert!(Note::load_by_name("Kept", renamed.db()).unwrap().is_some());
+
+        drop(renamed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn notebooks_open_elsewhere_are_not_renamed() {
+        let dir = test_dir("in-use");
+
+        let notebook = Notebook::new_notebook("wal", &dir).unwrap();
+        notebook
+            .db()
+            .execute_batch("PRAGMA journal_mode = WAL")
+            .unwrap();
+        Note::new("Logged", String::new(), notebook.db()).unwrap();
+        let err = Notebook::rename_notebook("wal", "moved", &dir).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RenamingError>(),
+            Some(RenamingError::NotebookInUse { name }) if name == "wal"
+        ));
+        drop(notebook);
+
+        let notebook = Notebook::new_notebook("locked", &dir).unwrap();
+        notebook
+            .db()
+            .execute_batch("PRAGMA locking_mode = EXCLUSIVE")
+            .unwrap();
+        Note::new("Locked", String::new(), notebook.db()).unwrap();
+        assert!(Notebook::rename_notebook("locked", "moved", &dir).is_err());
+        drop(notebook);
+
+        // Once closed, the write-ahead log is folded into the moved database
+        Notebook::rename_notebook("wal", "moved", &dir).unwrap();
+        assert!(!dir.join("wal.book-wal").exists());
+        let moved = Notebook::open_notebook("moved", &dir).unwrap();
+        assert!(Note::load_by_name("Logged", moved.db()).unwrap().is_some());
+
+        drop(moved);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
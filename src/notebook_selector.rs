@@ -1,6 +1,6 @@
 use std::ffi::OsString;
 use std::io::stdout;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use std::{env, fs};
 
@@ -14,15 +14,19 @@ use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use crossterm::ExecutableCommand;
-use ratatui::prelude::{Alignment, CrosstermBackend, Margin};
+use ratatui::prelude::{Alignment, CrosstermBackend, Margin, Rect};
 use ratatui::style::Style;
 use ratatui::style::{Color, Modifier};
 use ratatui::text::Text;
 use ratatui::widgets::{
-    Block, BorderType, Borders, List, ListDirection, ListState, Padding, Scrollbar,
+    Block, BorderType, Borders, List, ListDirection, ListState, Padding, Paragraph, Scrollbar,
     ScrollbarOrientation, ScrollbarState,
 };
-use ratatui::Terminal;
+use ratatui::{Frame, Terminal};
+
+use crate::config::ThemeConfig;
+use crate::helpers::{draw_text_prompt, draw_yes_no_prompt, EditableText};
+use crate::notebook::Notebook;
 
 #[derive(Clone, Debug, Error)]
 pub enum NotebookSelectorError {
@@ -30,14 +34,19 @@ pub enum NotebookSelectorError {
     InvalidNotebookName { name: OsString },
 }
 
-pub fn open_selector(dir: &Path) -> Result<Option<String>> {
-    info!("Open notebook selector.");
-
-    // Retreive notebooks
+enum Prompt {
+    Creating(EditableText),
+    Renaming(EditableText),
+    Deleting(bool),
+}
 
-    let notebooks = fs::read_dir(dir)?
-        .chain(fs::read_dir(env::current_dir()?)?)
-        .filter_map(|file| {
+/// The notebooks of `dir` and of the current directory, along with the directory they are in.
+fn list_notebooks(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let current_dir = env::current_dir()?;
+    fs::read_dir(dir)?
+        .map(|file| (file, dir))
+        .chain(fs::read_dir(&current_dir)?.map(|file| (file, current_dir.as_path())))
+        .filter_map(|(file, dir)| {
             file.map_err(anyhow::Error::from)
                 .map(|file| {
                     let file_path = file.path();
@@ -47,9 +56,10 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
                     }
                 })
                 .transpose()
+                .map(|file_path| file_path.map(|file_path| (file_path, dir)))
         })
         .map(|file_path| {
-            file_path.and_then(|file_path| {
+            file_path.and_then(|(file_path, dir)| {
                 file_path
                     .file_stem()
                     .ok_or(
@@ -63,9 +73,16 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
                             NotebookSelectorError::InvalidNotebookName { name: e.clone() }.into()
                         })
                     })
+                    .map(|name| (name, dir.to_path_buf()))
             })
         })
-        .collect::<Result<Vec<String>>>()?;
+        .collect()
+}
+
+pub fn open_selector(dir: &Path, theme: &ThemeConfig) -> Result<Option<String>> {
+    info!("Open notebook selector.");
+
+    let mut notebooks = list_notebooks(dir)?;
 
     // Display
     enable_raw_mode().expect("Prepare terminal");
@@ -81,24 +98,117 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
     let mut selected = 0;
+    let mut prompt: Option<Prompt> = None;
+    let mut status: Option<String> = None;
 
     loop {
         if event::poll(Duration::from_millis(50))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Esc | KeyCode::Char('q') => {
-                            info!("Quit notebook selector.");
-                            break Ok(None);
-                        }
-                        KeyCode::Up | KeyCode::Char('k') if selected > 0 => selected -= 1,
-                        KeyCode::Down | KeyCode::Char('j') if selected < notebooks.len() - 1 => {
-                            selected += 1;
+                    match prompt.take() {
+                        Some(Prompt::Creating(mut name)) => match key.code {
+                            KeyCode::Esc => {}
+                            KeyCode::Enter
+                                if Notebook::validate_name(name.as_str(), dir).is_none() =>
+                            {
+                                info!("Create notebook {}.", name.as_str());
+                                status = Some(match Notebook::new_notebook(name.as_str(), dir) {
+                                    Ok(_) => format!("Notebook {} was created", name.as_str()),
+                                    Err(err) => err.to_string(),
+                                });
+                                notebooks = list_notebooks(dir)?;
+                                selected = notebooks
+                                    .iter()
+                                    .position(|(notebook, _)| notebook == name.as_str())
+                                    .unwrap_or(selected);
+                            }
+                            code => {
+                                edit_name(&mut name, code);
+                                prompt = Some(Prompt::Creating(name));
+                            }
+                        },
+                        Some(Prompt::Renaming(mut new_name)) => match key.code {
+                            KeyCode::Esc => {}
+                            KeyCode::Enter => {
+                                let (name, notebook_dir) = &notebooks[selected];
+                                if Notebook::validate_name(new_name.as_str(), notebook_dir)
+                                    .is_none()
+                                {
+                                    info!("Rename notebook {name} to {}.", new_name.as_str());
+                                    status = Some(
+                                        match Notebook::rename_notebook(
+                                            name,
+                                            new_name.as_str(),
+                                            notebook_dir,
+                                        ) {
+                                            Ok(()) => format!(
+                                                "Notebook {name} was renamed to {}",
+                                                new_name.as_str()
+                                            ),
+                                            Err(err) => err.to_string(),
+                                        },
+                                    );
+                                    notebooks = list_notebooks(dir)?;
+                                    selected = notebooks
+                                        .iter()
+                                        .position(|(notebook, _)| notebook == new_name.as_str())
+                                        .unwrap_or(0);
+                                } else {
+                                    prompt = Some(Prompt::Renaming(new_name));
+                                }
+                            }
+                            code => {
+                                edit_name(&mut new_name, code);
+                                prompt = Some(Prompt::Renaming(new_name));
+                            }
+                        },
+                        Some(Prompt::Deleting(delete)) => match key.code {
+                            KeyCode::Tab => prompt = Some(Prompt::Deleting(!delete)),
+                            KeyCode::Enter if delete => {
+                                let (name, notebook_dir) = &notebooks[selected];
+                                info!("Delete notebook {name}.");
+                                status =
+                                    Some(match Notebook::delete_notebook(name, notebook_dir) {
+                                        Ok(()) => format!("Notebook {name} was deleted"),
+                                        Err(err) => err.to_string(),
+                                    });
+                                notebooks = list_notebooks(dir)?;
+                                selected = selected.min(notebooks.len().saturating_sub(1));
+                            }
+                            KeyCode::Enter | KeyCode::Esc => {}
+                            _ => prompt = Some(Prompt::Deleting(delete)),
+                        },
+                        None => {
+                            status = None;
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') => {
+                                    info!("Quit notebook selector.");
+                                    break Ok(None);
+                                }
+                                KeyCode::Up | KeyCode::Char('k') if selected > 0 => selected -= 1,
+                                KeyCode::Down | KeyCode::Char('j')
+                                    if selected < notebooks.len().saturating_sub(1) =>
+                                {
+                                    selected += 1;
+                                }
+                                KeyCode::Char('n') => {
+                                    prompt =
+                                        Some(Prompt::Creating(EditableText::new(String::new())));
+                                }
+                                KeyCode::Char('r') if !notebooks.is_empty() => {
+                                    prompt = Some(Prompt::Renaming(EditableText::new(
+                                        notebooks[selected].0.clone(),
+                                    )));
+                                }
+                                KeyCode::Char('d') if !notebooks.is_empty() => {
+                                    prompt = Some(Prompt::Deleting(false));
+                                }
+                                KeyCode::Enter if !notebooks.is_empty() => {
+                                    break Ok(Some(notebooks[selected].0.clone()));
+                                }
+                                _ => {}
+                            }
                         }
-                        KeyCode::Enter => {
-                            break Ok(Some(notebooks[selected].clone()));
-                        }
-                        _ => {}
                     }
                 }
             }
@@ -114,15 +224,16 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
                 .borders(Borders::all())
                 .border_style(Style::default().fg(Color::White))
                 .border_type(BorderType::Rounded);
+            let main_rect = main_block.inner(frame.size());
 
             let list = List::default()
                 .items(
                     notebooks
                         .iter()
-                        .map(|notebook| Text::styled(notebook, Style::default())),
+                        .map(|(notebook, _)| Text::styled(notebook, Style::default())),
                 )
-                .highlight_symbol(">>")
-                .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
                 .direction(ListDirection::TopToBottom);
 
             let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
@@ -131,7 +242,7 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
 
             frame.render_stateful_widget(
                 list,
-                main_block.inner(frame.size()),
+                main_rect,
                 &mut ListState::default().with_selected(Some(selected)),
             );
             frame.render_widget(main_block, frame.size());
@@ -140,6 +251,63 @@ pub fn open_selector(dir: &Path) -> Result<Option<String>> {
                 frame.size().inner(&Margin::new(0, 1)),
                 &mut ScrollbarState::new(notebooks.len()).position(selected),
             );
+
+            if let Some(status) = &status {
+                let status_rect = Rect {
+                    y: main_rect.bottom().saturating_sub(1),
+                    height: 1.min(main_rect.height),
+                    ..main_rect
+                };
+                frame.render_widget(
+                    Paragraph::new(status.as_str()).style(Style::default().fg(Color::Yellow)),
+                    status_rect,
+                );
+            }
+
+            match &prompt {
+                Some(Prompt::Creating(name)) => {
+                    draw_name_prompt(frame, "New notebook", name, dir, main_rect);
+                }
+                Some(Prompt::Renaming(new_name)) => {
+                    draw_name_prompt(
+                        frame,
+                        format!("Rename notebook {}", notebooks[selected].0).as_str(),
+                        new_name,
+                        &notebooks[selected].1,
+                        main_rect,
+                    );
+                }
+                Some(Prompt::Deleting(delete)) => draw_yes_no_prompt(
+                    frame,
+                    *delete,
+                    format!("Delete notebook {} ?", notebooks[selected].0).as_str(),
+                    main_rect,
+                ),
+                None => {}
+            }
         })?;
     }
 }
+
+fn edit_name(name: &mut EditableText, code: KeyCode) {
+    match code {
+        KeyCode::Backspace => name.remove_char(),
+        KeyCode::Delete => name.del_char(),
+        KeyCode::Left => name.move_left(),
+        KeyCode::Right => name.move_right(),
+        KeyCode::Char(c) => name.insert_char(c),
+        _ => {}
+    }
+}
+
+fn draw_name_prompt(frame: &mut Frame, title: &str, name: &EditableText, dir: &Path, rect: Rect) {
+    let error = Notebook::validate_name(name.as_str(), dir);
+    draw_text_prompt(
+        frame,
+        title,
+        name.as_str(),
+        error.is_none(),
+        error.as_ref().map(ToString::to_string).as_deref(),
+        rect,
+    );
+}
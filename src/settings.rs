@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use thiserror::Error;
+
+use rusqlite::Connection;
+use sea_query::{ColumnDef, Iden, OnConflict, Query, SqliteQueryBuilder, Table};
+
+use crate::helpers::DiscardResult;
+
+#[derive(Iden)]
+pub struct SettingsTable;
+
+#[derive(Iden, Clone, Copy, Debug)]
+pub enum SettingsCharacters {
+    Key,
+    Value,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SettingKind {
+    Text,
+    Number,
+    /// A number which may be 0.
+    Count,
+    List,
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Setting {
+    pub key: &'static str,
+    pub kind: SettingKind,
+    pub default: &'static str,
+}
+
+pub const EDITOR: Setting = Setting {
+    key: "editor",
+    kind: SettingKind::Text,
+    default: "",
+};
+pub const DEFAULT_VIEW: Setting = Setting {
+    key: "default_view",
+    kind: SettingKind::Choice(&["rendered", "source"]),
+    default: "rendered",
+};
+pub const RAW_PAGE_LENGTH: Setting = Setting {
+    key: "raw_page_length",
+    kind: SettingKind::Number,
+    default: "20",
+};
+
+pub const DEFAULT_TAGS: Setting = Setting {
+    key: "default_tags",
+    kind: SettingKind::List,
+    default: "",
+};
+
+pub const TYPED_DELETE_BACKLINKS: Setting = Setting {
+    key: "typed_delete_backlinks",
+    kind: SettingKind::Number,
+    default: "3",
+};
+
+pub const SEARCH_RESULT_LIMIT: Setting = Setting {
+    key: "search_result_limit",
+    kind: SettingKind::Number,
+    default: "200",
+};
+
+pub const ENTRY_TIMESTAMP_FORMAT: Setting = Setting {
+    key: "entry_timestamp_format",
+    kind: SettingKind::Text,
+    default: "%Y-%m-%d %H:%M",
+};
+
+pub const TAG_SUGGESTIONS: Setting = Setting {
+    key: "tag_suggestions",
+    kind: SettingKind::Count,
+    default: "5",
+};
+
+pub const KNOWN_SETTINGS: [Setting; 8] = [
+    EDITOR,
+    DEFAULT_VIEW,
+    RAW_PAGE_LENGTH,
+    DEFAULT_TAGS,
+    TYPED_DELETE_BACKLINKS,
+    SEARCH_RESULT_LIMIT,
+    ENTRY_TIMESTAMP_FORMAT,
+    TAG_SUGGESTIONS,
+];
+
+#[derive(Error, Debug)]
+pub enum SettingError {
+    #[error("No setting named {key:?} is known.")]
+    UnknownSetting { key: String },
+    #[error("{value:?} is not a valid value for the setting {key:?}.")]
+    InvalidValue { key: String, value: String },
+}
+
+impl Setting {
+    pub fn find(key: &str) -> Option<Setting> {
+        KNOWN_SETTINGS
+            .into_iter()
+            .find(|setting| setting.key == key)
+    }
+
+    pub fn validate(&self, value: &str) -> bool {
+        match self.kind {
+            SettingKind::Text | SettingKind::List => true,
+            SettingKind::Number => value.parse::<usize>().is_ok_and(|number| number > 0),
+            SettingKind::Count => value.parse::<usize>().is_ok(),
+            SettingKind::Choice(choices) => choices.contains(&value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NotebookSettings {
+    // Keep every stored pair, including keys written by newer versions
+    values: BTreeMap<String, String>,
+}
+
+impl NotebookSettings {
+    pub fn load(db: &Connection) -> Result<Self> {
+        let values = db
+            .prepare(
+                Query::select()
+                    .from(SettingsTable)
+                    .columns([SettingsCharacters::Key, SettingsCharacters::Value])
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<BTreeMap<String, String>, _>>()?;
+
+        Ok(NotebookSettings { values })
+    }
+
+    pub fn get(&self, setting: Setting) -> &str {
+        self.values
+            .get(setting.key)
+            .map(String::as_str)
+            .filter(|value| setting.validate(value))
+            .unwrap_or(setting.default)
+    }
+
+    /// Every stored pair, known or not.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn unknown(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .filter(|(key, _)| Setting::find(key).is_none())
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn set(&mut self, key: &str, value: &str, db: &Connection) -> Result<()> {
+        let Some(setting) = Setting::find(key) else {
+            return Err(SettingError::UnknownSetting {
+                key: key.to_owned(),
+            }
+            .into());
+        };
+
+        if !setting.validate(value) {
+            return Err(SettingError::InvalidValue {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            }
+            .into());
+        }
+
+        db.execute_batch(
+            Query::insert()
+                .into_table(SettingsTable)
+                .columns([SettingsCharacters::Key, SettingsCharacters::Value])
+                .values([key.into(), value.into()])?
+                .on_conflict(
+                    OnConflict::column(SettingsCharacters::Key)
+                        .update_column(SettingsCharacters::Value)
+                        .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+
+        self.values.insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    pub fn editor(&self) -> Option<&str> {
+        Some(self.get(EDITOR)).filter(|editor| !editor.trim().is_empty())
+    }
+
+    pub fn default_raw_view(&self) -> bool {
+        self.get(DEFAULT_VIEW) == "source"
+    }
+
+    pub fn default_tags(&self) -> Vec<&str> {
+        self.get(DEFAULT_TAGS).split_whitespace().collect()
+    }
+
+    pub fn raw_page_length(&self) -> usize {
+        self.get(RAW_PAGE_LENGTH)
+            .parse()
+            .expect("The setting should have been validated")
+    }
+
+    /// Notes with more backlinks than this must have their name typed to be deleted.
+    pub fn typed_delete_backlinks(&self) -> usize {
+        self.get(TYPED_DELETE_BACKLINKS)
+            .parse()
+            .expect("The setting should have been validated")
+    }
+
+    /// A strftime-like format, see the chrono documentation.
+    pub fn entry_timestamp_format(&self) -> &str {
+        Some(self.get(ENTRY_TIMESTAMP_FORMAT))
+            .filter(|format| !format.trim().is_empty())
+            .unwrap_or(ENTRY_TIMESTAMP_FORMAT.default)
+    }
+
+    /// How many terms of a note are suggested when adding a tag, 0 to disable suggestions.
+    pub fn tag_suggestions(&self) -> usize {
+        self.get(TAG_SUGGESTIONS)
+            .parse()
+            .expect("The setting should have been validated")
+    }
+
+    /// Searches with a short pattern load this many notes at a time.
+    pub fn search_result_limit(&self) -> usize {
+        self.get(SEARCH_RESULT_LIMIT)
+            .parse()
+            .expect("The setting should have been validated")
+    }
+}
+
+impl SettingsTable {
+    pub fn create(db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Table::create()
+                .if_not_exists()
+                .table(SettingsTable)
+                .col(
+                    ColumnDef::new(SettingsCharacters::Key)
+                        .string()
+                        .primary_key(),
+                )
+                .col(
+                    ColumnDef::new(SettingsCharacters::Value)
+                        .string()
+                        .not_null(),
+                )
+                .build(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .discard_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notebook::test_database;
+
+    #[test]
+    fn zero_disables_tag_suggestions() {
+        let db = test_database();
+        let mut settings = NotebookSettings::load(&db).unwrap();
+        assert_eq!(settings.tag_suggestions(), 5);
+
+        settings.set(TAG_SUGGESTIONS.key, "0", &db).unwrap();
+        assert_eq!(settings.tag_suggestions(), 0);
+        assert_eq!(NotebookSettings::load(&db).unwrap().tag_suggestions(), 0);
+        assert!(settings.set(RAW_PAGE_LENGTH.key, "0", &db).is_err());
+        assert!(settings.set(TAG_SUGGESTIONS.key, "-1", &db).is_err());
+    }
+}
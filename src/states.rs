@@ -1,35 +1,70 @@
+mod config_managing;
+mod help;
+mod input_discarding;
+mod note_attachments;
 mod note_creating;
 mod note_deleting;
+mod note_editing;
+mod note_forking;
+mod note_links;
 mod note_renaming;
 mod note_tag_adding;
 mod note_tag_deleting;
+mod note_tag_replacing;
 mod note_tags_managing;
 mod note_viewing;
 mod notes_managing;
 mod nothing;
+mod settings_managing;
+mod split_view;
+mod statistics;
 mod tag_creating;
 mod tag_deleting;
+mod tag_implications;
 mod tag_notes_listing;
+mod tag_template_setting;
 mod tags_managing;
+mod tags_trash;
 
 use std::io::Stdout;
 
 use anyhow::Result;
+use log::info;
 
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::CrosstermBackend;
 use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, BorderType, Borders, Padding};
+use ratatui::widgets::Block;
 use ratatui::Terminal as UITerminal;
 
+use crate::config::Config;
+use crate::helpers::{EditableText, TryFromDatabase};
+use crate::note::Note;
 use crate::notebook::Notebook;
 
+use crate::states::config_managing::{
+    draw_config_managing_state, run_config_managing_state, ConfigManagingStateData,
+};
+use crate::states::help::{draw_help_state, run_help_state, HelpStateData};
+use crate::states::input_discarding::{
+    draw_input_discarding_state, run_input_discarding_state, InputDiscardingStateData,
+};
+use crate::states::note_attachments::{
+    draw_note_attachments_state, run_note_attachments_state, NoteAttachmentsStateData,
+};
 use crate::states::note_creating::{
     draw_note_creating_state, run_note_creating_state, NoteCreatingStateData,
 };
 use crate::states::note_deleting::{
     draw_note_deleting_state, run_note_deleting_state, NoteDeletingStateData,
 };
+use crate::states::note_editing::{
+    draw_note_editing_state, run_note_editing_state, NoteEditingStateData,
+};
+use crate::states::note_forking::{
+    draw_note_forking_state, run_note_forking_state, NoteForkingStateData,
+};
+use crate::states::note_links::{draw_note_links_state, run_note_links_state, NoteLinksStateData};
 use crate::states::note_renaming::{
     draw_note_renaming_state, run_note_renaming_state, NoteRenamingStateData,
 };
@@ -41,15 +76,27 @@ use crate::states::notes_managing::{
     draw_note_managing_state, run_note_managing_state, NotesManagingStateData,
 };
 use crate::states::nothing::{draw_nothing_state, run_nothing_state};
+use crate::states::settings_managing::{
+    draw_settings_managing_state, run_settings_managing_state, SettingsManagingStateData,
+};
+use crate::states::split_view::{draw_split_view_state, run_split_view_state, SplitViewStateData};
+use crate::states::statistics::{draw_statistics_state, run_statistics_state, StatisticsStateData};
 use crate::states::tag_creating::{
     draw_tag_creating_state, run_tag_creating_state, TagsCreatingStateData,
 };
 use crate::states::tag_deleting::{
     draw_tag_deleting_state, run_tag_deleting_state, TagsDeletingStateData,
 };
+use crate::states::tag_implications::{
+    draw_tag_implications_state, run_tag_implications_state, TagImplicationsStateData,
+};
+use crate::states::tag_template_setting::{
+    draw_tag_template_setting_state, run_tag_template_setting_state, TagTemplateSettingStateData,
+};
 use crate::states::tags_managing::{
     draw_tags_managing_state, run_tags_managing_state, TagsManagingStateData,
 };
+use crate::states::tags_trash::{draw_tags_trash_state, run_tags_trash_state, TagsTrashStateData};
 
 use crate::states::note_tag_adding::{
     draw_note_tag_adding_state_data, run_note_tag_adding_state, NoteTagAddingStateData,
@@ -57,6 +104,9 @@ use crate::states::note_tag_adding::{
 use crate::states::note_tag_deleting::{
     draw_note_tag_deleting_state_data, run_note_tag_deleting_state, NoteTagDeletingStateData,
 };
+use crate::states::note_tag_replacing::{
+    draw_note_tag_replacing_state_data, run_note_tag_replacing_state, NoteTagReplacingStateData,
+};
 use crate::states::note_tags_managing::{
     draw_note_tags_managing_state, run_note_tags_managing_state,
 };
@@ -71,23 +121,66 @@ pub enum State {
     Exit,
     NotesManaging(NotesManagingStateData),
     NoteViewing(NoteViewingStateData),
+    NoteEditing(NoteEditingStateData),
     NoteCreating(NoteCreatingStateData),
     NoteDeleting(NoteDeletingStateData),
     NoteRenaming(NoteRenamingStateData),
+    NoteForking(NoteForkingStateData),
+    NoteAttachments(NoteAttachmentsStateData),
+    NoteLinks(NoteLinksStateData),
     NoteTagsManaging(NoteTagsManagingStateData),
     NoteTagDeleting(NoteTagDeletingStateData),
     NoteTagAdding(NoteTagAddingStateData),
+    NoteTagReplacing(NoteTagReplacingStateData),
     TagsManaging(TagsManagingStateData),
     TagCreating(TagsCreatingStateData),
     TagDeleting(TagsDeletingStateData),
+    TagImplications(TagImplicationsStateData),
+    TagTemplateSetting(TagTemplateSettingStateData),
+    TagsTrash(TagsTrashStateData),
     TagNotesListing(TagNotesListingStateData),
+    SettingsManaging(SettingsManagingStateData),
+    ConfigManaging(ConfigManagingStateData),
+    SplitView(SplitViewStateData),
+    Statistics(StatisticsStateData),
+    Help(HelpStateData),
+    InputDiscarding(InputDiscardingStateData),
 }
 
 impl State {
+    pub fn note_viewing(note: Note, notebook: &Notebook) -> Result<Self> {
+        Ok(State::NoteViewing(
+            NoteViewingStateData::try_from_database(note, notebook.db())?
+                .with_default_view(&notebook.settings()),
+        ))
+    }
+
     pub fn run(
         self,
         key_event: KeyEvent,
         notebook: &Notebook,
+        config: &mut Config,
+        force_redraw: &mut bool,
+    ) -> Result<Self> {
+        if key_event.code == KeyCode::Char('?') && !self.is_typing() {
+            info!("Open help.");
+            return Ok(State::Help(HelpStateData::new(self)));
+        }
+        if key_event.code == KeyCode::Esc
+            && self.typed_input().is_some_and(|input| !input.is_empty())
+        {
+            info!("Ask before discarding the typed input.");
+            return Ok(State::InputDiscarding(InputDiscardingStateData::new(self)));
+        }
+
+        self.dispatch(key_event, notebook, config, force_redraw)
+    }
+
+    fn dispatch(
+        self,
+        key_event: KeyEvent,
+        notebook: &Notebook,
+        config: &mut Config,
         force_redraw: &mut bool,
     ) -> Result<Self> {
         match self {
@@ -95,54 +188,351 @@ impl State {
             State::NotesManaging(data) => run_note_managing_state(data, key_event, notebook),
             State::NoteCreating(data) => run_note_creating_state(data, key_event, notebook),
             State::NoteViewing(data) => {
-                run_note_viewing_state(data, key_event, notebook, force_redraw)
+                run_note_viewing_state(data, key_event, notebook, &config.editing, force_redraw)
+            }
+            State::NoteEditing(data) => {
+                run_note_editing_state(data, key_event, notebook, &config.editing)
             }
             State::NoteDeleting(data) => run_note_deleting_state(data, key_event, notebook),
             State::NoteRenaming(data) => run_note_renaming_state(data, key_event, notebook),
+            State::NoteForking(data) => {
+                run_note_forking_state(data, key_event, notebook, &config.editing)
+            }
+            State::NoteAttachments(data) => run_note_attachments_state(data, key_event, notebook),
+            State::NoteLinks(data) => run_note_links_state(data, key_event, notebook),
             State::NoteTagsManaging(data) => {
                 run_note_tags_managing_state(data, key_event, notebook)
             }
             State::NoteTagAdding(data) => run_note_tag_adding_state(data, key_event, notebook),
+            State::NoteTagReplacing(data) => {
+                run_note_tag_replacing_state(data, key_event, notebook)
+            }
             State::NoteTagDeleting(data) => run_note_tag_deleting_state(data, key_event, notebook),
             State::TagsManaging(data) => run_tags_managing_state(data, key_event, notebook),
             State::TagCreating(data) => run_tag_creating_state(data, key_event, notebook),
             State::TagDeleting(data) => run_tag_deleting_state(data, key_event, notebook),
+            State::TagImplications(data) => run_tag_implications_state(data, key_event, notebook),
+            State::TagTemplateSetting(data) => {
+                run_tag_template_setting_state(data, key_event, notebook)
+            }
+            State::TagsTrash(data) => run_tags_trash_state(data, key_event, notebook),
             State::TagNotesListing(data) => run_tag_notes_listing_state(data, key_event, notebook),
+            State::SettingsManaging(data) => run_settings_managing_state(data, key_event, notebook),
+            State::ConfigManaging(data) => run_config_managing_state(data, key_event, config),
+            State::SplitView(data) => {
+                run_split_view_state(data, key_event, notebook, &config.editing, force_redraw)
+            }
+            State::Statistics(data) => Ok(run_statistics_state(data, key_event)),
+            State::Help(data) => Ok(run_help_state(data, key_event)),
+            State::InputDiscarding(data) => {
+                run_input_discarding_state(data, key_event, notebook, config, force_redraw)
+            }
             State::Exit => unreachable!(),
         }
     }
 
-    pub fn draw(&self, notebook: &Notebook, terminal: &mut Terminal) -> Result<()> {
-        let main_frame = Block::default()
-            .title(notebook.name.as_str())
-            .padding(Padding::uniform(1))
-            .borders(Borders::all())
-            .border_type(BorderType::Rounded)
+    /// Called while no key is pressed.
+    pub fn tick(&mut self, notebook: &Notebook) -> Result<()> {
+        match self {
+            State::NoteViewing(data) => data.update_preview(notebook.db()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether a character key would be typed into a text input.
+    fn is_typing(&self) -> bool {
+        match self {
+            State::NotesManaging(_)
+            | State::NoteCreating(_)
+            | State::NoteRenaming(_)
+            | State::NoteForking(_)
+            | State::NoteEditing(_)
+            | State::NoteTagAdding(_)
+            | State::NoteTagReplacing(_)
+            | State::TagCreating(_)
+            | State::TagTemplateSetting(_)
+            | State::Help(_)
+            | State::InputDiscarding(_) => true,
+            State::TagsManaging(data) => data.pattern_editing,
+            State::TagNotesListing(data) => data.digest.is_some() || data.creating.is_some(),
+            State::NoteAttachments(data) => data.adding.is_some(),
+            State::SettingsManaging(data) => data.editing.is_some(),
+            State::ConfigManaging(data) => data.editing.is_some(),
+            State::SplitView(data) => data.is_editing(),
+            State::NoteDeleting(data) => data.typed_name.is_some(),
+            State::TagImplications(data) => data.adding.is_some(),
+            _ => false,
+        }
+    }
+
+    /// The text typed in the prompt of the state, which Esc would throw away.
+    fn typed_input(&self) -> Option<&str> {
+        match self {
+            State::NoteCreating(data) => Some(data.name.as_str()),
+            State::NoteRenaming(data) => Some(data.new_name.as_str()),
+            State::NoteTagAdding(data) if data.create_tag.is_none() => Some(data.tag_name.as_str()),
+            State::NoteTagReplacing(data) => Some(data.tag_name.as_str()),
+            State::TagCreating(data) => Some(data.name.as_str()),
+            State::TagTemplateSetting(data) => Some(data.name.as_str()),
+            State::TagNotesListing(data) => {
+                data.creating.as_ref().map(|prompt| prompt.name.as_str())
+            }
+            State::TagImplications(data) => data.adding.as_ref().map(EditableText::as_str),
+            State::NoteAttachments(data) => data.adding.as_ref().map(EditableText::as_str),
+            _ => None,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        notebook: &Notebook,
+        config: &Config,
+        terminal: &mut Terminal,
+    ) -> Result<()> {
+        let main_frame = config
+            .layout
+            .block()
+            .title(format!(
+                "{} — {} notes",
+                notebook.name,
+                config.format.count(notebook.note_count() as u64)
+            ))
             .border_style(Style::default().fg(Color::White));
 
+        self.draw_in(notebook, config, terminal, main_frame)
+    }
+
+    fn draw_in(
+        &self,
+        notebook: &Notebook,
+        config: &Config,
+        terminal: &mut Terminal,
+        main_frame: Block,
+    ) -> Result<()> {
         match self {
             State::Nothing => draw_nothing_state(terminal, notebook, main_frame),
-            State::NotesManaging(data) => draw_note_managing_state(data, terminal, main_frame),
+            State::NotesManaging(data) => {
+                draw_note_managing_state(data, &config.format, &config.theme, terminal, main_frame)
+            }
             State::NoteCreating(data) => draw_note_creating_state(data, terminal, main_frame),
-            State::NoteViewing(data) => draw_note_viewing_state(data, terminal, main_frame),
-            State::NoteDeleting(data) => draw_note_deleting_state(data, terminal, main_frame),
-            State::NoteRenaming(data) => draw_note_renaming_state(data, terminal, main_frame),
+            State::NoteViewing(data) => {
+                draw_note_viewing_state(data, config.layout, terminal, main_frame)
+            }
+            State::NoteEditing(data) => {
+                draw_note_editing_state(data, config.layout, terminal, main_frame)
+            }
+            State::NoteDeleting(data) => {
+                draw_note_deleting_state(data, config.layout, terminal, main_frame)
+            }
+            State::NoteRenaming(data) => {
+                draw_note_renaming_state(data, config.layout, terminal, main_frame)
+            }
+            State::NoteForking(data) => {
+                draw_note_forking_state(data, config.layout, terminal, main_frame)
+            }
+            State::NoteAttachments(data) => draw_note_attachments_state(
+                data,
+                config.layout,
+                &config.format,
+                &config.theme,
+                terminal,
+                main_frame,
+            ),
+            State::NoteLinks(data) => {
+                draw_note_links_state(data, config.layout, &config.theme, terminal, main_frame)
+            }
             State::NoteTagsManaging(data) => {
-                draw_note_tags_managing_state(data, terminal, main_frame)
+                draw_note_tags_managing_state(data, &config.theme, terminal, main_frame)
             }
             State::NoteTagAdding(data) => {
-                draw_note_tag_adding_state_data(data, terminal, main_frame)
+                draw_note_tag_adding_state_data(data, &config.theme, terminal, main_frame)
             }
             State::NoteTagDeleting(data) => {
-                draw_note_tag_deleting_state_data(data, terminal, main_frame)
+                draw_note_tag_deleting_state_data(data, &config.theme, terminal, main_frame)
+            }
+            State::NoteTagReplacing(data) => {
+                draw_note_tag_replacing_state_data(data, &config.theme, terminal, main_frame)
+            }
+            State::TagsManaging(data) => {
+                draw_tags_managing_state(data, &config.theme, terminal, main_frame)
+            }
+            State::TagCreating(data) => {
+                draw_tag_creating_state(data, &config.theme, terminal, main_frame)
+            }
+            State::TagDeleting(data) => {
+                draw_tag_deleting_state(data, &config.theme, terminal, main_frame)
+            }
+            State::TagImplications(data) => {
+                draw_tag_implications_state(data, &config.theme, terminal, main_frame)
+            }
+            State::TagTemplateSetting(data) => {
+                draw_tag_template_setting_state(data, &config.theme, terminal, main_frame)
+            }
+            State::TagsTrash(data) => {
+                draw_tags_trash_state(data, &config.format, &config.theme, terminal, main_frame)
             }
-            State::TagsManaging(data) => draw_tags_managing_state(data, terminal, main_frame),
-            State::TagCreating(data) => draw_tag_creating_state(data, terminal, main_frame),
-            State::TagDeleting(data) => draw_tag_deleting_state(data, terminal, main_frame),
             State::TagNotesListing(data) => {
-                draw_tag_notes_listing_state(data, terminal, main_frame)
+                draw_tag_notes_listing_state(data, &config.theme, terminal, main_frame)
+            }
+            State::SettingsManaging(data) => {
+                draw_settings_managing_state(data, notebook, &config.theme, terminal, main_frame)
+            }
+            State::ConfigManaging(data) => {
+                draw_config_managing_state(data, config, terminal, main_frame)
+            }
+            State::SplitView(data) => {
+                draw_split_view_state(data, config.layout, terminal, main_frame)
+            }
+            State::Statistics(data) => {
+                draw_statistics_state(data, &config.format, terminal, main_frame)
+            }
+            State::Help(data) => draw_help_state(data, terminal, main_frame),
+            State::InputDiscarding(data) => {
+                draw_input_discarding_state(data, notebook, config, terminal, main_frame)
             }
             State::Exit => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::discriminant;
+
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::note::NoteData;
+    use crate::notebook::test_notebook;
+    use crate::tag::Tag;
+
+    fn press(state: State, code: KeyCode, notebook: &Notebook) -> State {
+        state
+            .run(
+                KeyEvent::new(code, KeyModifiers::NONE),
+                notebook,
+                &mut Config::default(),
+                &mut false,
+            )
+            .unwrap()
+    }
+
+    // Reach each prompt with a typed input, from the note viewer or the starting screen.
+    fn prompts(notebook: &Notebook) -> Vec<(&'static str, State)> {
+        let viewer = || {
+            State::note_viewing(
+                Note::load_by_name("Plan", notebook.db()).unwrap().unwrap(),
+                notebook,
+            )
+            .unwrap()
+        };
+        let sequences: [(&str, bool, &[KeyCode]); 9] = [
+            ("note creating", false, &[KeyCode::Char('c')]),
+            ("note renaming", true, &[KeyCode::Char('r')]),
+            (
+                "note tag adding",
+                true,
+                &[KeyCode::Char('t'), KeyCode::Char('a')],
+            ),
+            (
+                "note tag replacing",
+                true,
+                &[KeyCode::Char('t'), KeyCode::Char('s')],
+            ),
+            (
+                "note attachments",
+                true,
+                &[KeyCode::Char('F'), KeyCode::Char('a')],
+            ),
+            (
+                "tag creating",
+                false,
+                &[KeyCode::Char('t'), KeyCode::Char('c')],
+            ),
+            (
+                "tag template setting",
+                false,
+                &[KeyCode::Char('t'), KeyCode::Char('p')],
+            ),
+            (
+                "tag implications",
+                false,
+                &[KeyCode::Char('t'), KeyCode::Char('i'), KeyCode::Char('a')],
+            ),
+            (
+                "tag notes listing",
+                false,
+                &[KeyCode::Char('t'), KeyCode::Enter, KeyCode::Char('c')],
+            ),
+        ];
+
+        sequences
+            .into_iter()
+            .map(|(name, from_viewer, keys)| {
+                let start = if from_viewer {
+                    viewer()
+                } else {
+                    State::Nothing
+                };
+                let mut state = keys
+                    .iter()
+                    .fold(start, |state, code| press(state, *code, notebook));
+                // Start from an empty prompt, whatever it was filled with
+                while state.typed_input().is_some_and(|input| !input.is_empty()) {
+                    state = press(state, KeyCode::Backspace, notebook);
+                }
+                assert!(state.typed_input().is_some(), "{name} has no prompt");
+                (name, state)
+            })
+            .collect()
+    }
+
+    fn prompt_notebook() -> Notebook {
+        let notebook = test_notebook();
+        let note = Note::new("Plan", String::new(), notebook.db()).unwrap();
+        NoteData::try_from_database(note, notebook.db())
+            .unwrap()
+            .add_tag(Tag::new("work", notebook.db()).unwrap(), notebook.db())
+            .unwrap();
+        notebook
+    }
+
+    #[test]
+    fn esc_twice_discards_the_typed_input() {
+        let notebook = prompt_notebook();
+        for (name, prompt) in prompts(&notebook) {
+            let typed = press(prompt, KeyCode::Char('x'), &notebook);
+            let asking = press(typed, KeyCode::Esc, &notebook);
+            assert!(matches!(asking, State::InputDiscarding(_)), "{name}");
+
+            let left = press(asking, KeyCode::Esc, &notebook);
+            assert!(!matches!(left, State::InputDiscarding(_)), "{name}");
+            assert!(left.typed_input().is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn another_key_after_esc_keeps_typing() {
+        let notebook = prompt_notebook();
+        for (name, prompt) in prompts(&notebook) {
+            let variant = discriminant(&prompt);
+            let typed = press(prompt, KeyCode::Char('x'), &notebook);
+            let asking = press(typed, KeyCode::Esc, &notebook);
+            let resumed = press(asking, KeyCode::Char('y'), &notebook);
+
+            assert_eq!(discriminant(&resumed), variant, "{name}");
+            assert_eq!(resumed.typed_input(), Some("xy"), "{name}");
+        }
+    }
+
+    #[test]
+    fn esc_on_an_empty_prompt_leaves_at_once() {
+        let notebook = prompt_notebook();
+        for (name, prompt) in prompts(&notebook) {
+            let left = press(prompt, KeyCode::Esc, &notebook);
+
+            assert!(!matches!(left, State::InputDiscarding(_)), "{name}");
+            assert!(left.typed_input().is_none(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::Result;
+use chrono::Local;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, List, ListState, Padding};
+use ratatui::Frame;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::config::{Config, ConfigSource, FormatConfig};
+use crate::helpers::{DiscardResult, EditableText};
+use crate::states::{State, Terminal};
+
+const MAX_PADDING: u16 = 8;
+
+#[derive(Clone, Copy)]
+enum ConfigField {
+    Padding,
+    Borders,
+    ConfirmDiscard,
+    ConfirmQuit,
+    ForkTags,
+    DateFormat,
+    ThousandsSeparator,
+}
+
+const CONFIG_FIELDS: [ConfigField; 7] = [
+    ConfigField::Padding,
+    ConfigField::Borders,
+    ConfigField::ConfirmDiscard,
+    ConfigField::ConfirmQuit,
+    ConfigField::ForkTags,
+    ConfigField::DateFormat,
+    ConfigField::ThousandsSeparator,
+];
+
+impl ConfigField {
+    fn name(self) -> &'static str {
+        match self {
+            ConfigField::Padding => "Padding",
+            ConfigField::Borders => "Borders",
+            ConfigField::ConfirmDiscard => "Confirm discarding edits",
+            ConfigField::ConfirmQuit => "Confirm quitting with unsaved edits",
+            ConfigField::ForkTags => "Copy tags when forking",
+            ConfigField::DateFormat => "Date format",
+            ConfigField::ThousandsSeparator => "Thousands separator",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            ConfigField::Padding => "layout.padding",
+            ConfigField::Borders => "layout.borders",
+            ConfigField::ConfirmDiscard => "editing.confirm_discard",
+            ConfigField::ConfirmQuit => "editing.confirm_quit",
+            ConfigField::ForkTags => "editing.fork_tags",
+            ConfigField::DateFormat => "format.date_format",
+            ConfigField::ThousandsSeparator => "format.thousands_separator",
+        }
+    }
+
+    /// Values set by the environment or flags aren't saved, so they can't be edited here.
+    fn is_editable(self, config: &Config) -> bool {
+        !matches!(
+            config.source(self.key()),
+            ConfigSource::Environment | ConfigSource::Flag
+        )
+    }
+
+    fn is_valid(self, text: &str) -> bool {
+        match self {
+            ConfigField::Padding => parse_padding(text).is_some(),
+            ConfigField::DateFormat => !text.is_empty() && FormatConfig::is_valid_date_format(text),
+            ConfigField::ThousandsSeparator => text.graphemes(true).count() <= 1,
+            ConfigField::Borders
+            | ConfigField::ConfirmDiscard
+            | ConfigField::ConfirmQuit
+            | ConfigField::ForkTags => true,
+        }
+    }
+}
+
+pub struct ConfigManagingStateData {
+    pub selected: usize,
+    pub editing: Option<EditableText>,
+    pub status: Option<String>,
+}
+
+impl ConfigManagingStateData {
+    pub fn empty() -> Self {
+        ConfigManagingStateData {
+            selected: 0,
+            editing: None,
+            status: None,
+        }
+    }
+}
+
+fn parse_padding(text: &str) -> Option<u16> {
+    text.parse().ok().filter(|padding| *padding <= MAX_PADDING)
+}
+
+pub fn run_config_managing_state(
+    mut state_data: ConfigManagingStateData,
+    key_event: KeyEvent,
+    config: &mut Config,
+) -> Result<State> {
+    if let Some(mut text) = state_data.editing {
+        state_data.editing = match key_event.code {
+            KeyCode::Esc => None,
+            KeyCode::Enter => {
+                let field = CONFIG_FIELDS[state_data.selected];
+                if field.is_valid(text.as_str()) {
+                    match field {
+                        ConfigField::Padding => {
+                            config.layout.padding = parse_padding(text.as_str())
+                                .expect("The padding should have been validated");
+                            info!("Set layout padding to {}.", config.layout.padding);
+                        }
+                        ConfigField::DateFormat => {
+                            info!("Set date format to {:?}.", text.as_str());
+                            text.as_str().clone_into(&mut config.format.date_format);
+                        }
+                        ConfigField::ThousandsSeparator => {
+                            info!("Set thousands separator to {:?}.", text.as_str());
+                            text.as_str()
+                                .clone_into(&mut config.format.thousands_separator);
+                        }
+                        ConfigField::Borders
+                        | ConfigField::ConfirmDiscard
+                        | ConfigField::ConfirmQuit
+                        | ConfigField::ForkTags => {}
+                    }
+                    config.save()?;
+                    None
+                } else {
+                    Some(text)
+                }
+            }
+            KeyCode::Backspace => {
+                text.remove_char();
+                Some(text)
+            }
+            KeyCode::Delete => {
+                text.del_char();
+                Some(text)
+            }
+            KeyCode::Left => {
+                text.move_left();
+                Some(text)
+            }
+            KeyCode::Right => {
+                text.move_right();
+                Some(text)
+            }
+            KeyCode::Char(c) => {
+                text.insert_char(c);
+                Some(text)
+            }
+            _ => Some(text),
+        };
+        return Ok(State::ConfigManaging(state_data));
+    }
+
+    state_data.status = None;
+
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!("Close preferences.");
+            State::Nothing
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::ConfigManaging(state_data)
+        }
+        KeyCode::Down if state_data.selected < CONFIG_FIELDS.len() - 1 => {
+            state_data.selected += 1;
+            State::ConfigManaging(state_data)
+        }
+        KeyCode::Enter | KeyCode::Char(' ')
+            if !CONFIG_FIELDS[state_data.selected].is_editable(config) =>
+        {
+            let field = CONFIG_FIELDS[state_data.selected];
+            info!(
+                "Refuse to edit {}, set by the {}.",
+                field.key(),
+                config.source(field.key()).name()
+            );
+            state_data.status = Some(format!(
+                "{} is set by the {}, unset it to edit",
+                field.name(),
+                config.source(field.key()).name()
+            ));
+            State::ConfigManaging(state_data)
+        }
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            match CONFIG_FIELDS[state_data.selected] {
+                ConfigField::Padding => {
+                    state_data.editing = Some(EditableText::new(config.layout.padding.to_string()));
+                }
+                ConfigField::Borders => {
+                    config.layout.borders = config.layout.borders.next();
+                    info!("Set layout borders to {}.", config.layout.borders.name());
+                    config.save()?;
+                }
+                ConfigField::DateFormat => {
+                    state_data.editing = Some(EditableText::new(config.format.date_format.clone()));
+                }
+                ConfigField::ThousandsSeparator => {
+                    state_data.editing =
+                        Some(EditableText::new(config.format.thousands_separator.clone()));
+                }
+                ConfigField::ConfirmDiscard => {
+                    config.editing.confirm_discard = !config.editing.confirm_discard;
+                    info!(
+                        "Set discard confirmation to {}.",
+                        config.editing.confirm_discard
+                    );
+                    config.save()?;
+                }
+                ConfigField::ConfirmQuit => {
+                    config.editing.confirm_quit = !config.editing.confirm_quit;
+                    info!("Set quit confirmation to {}.", config.editing.confirm_quit);
+                    config.save()?;
+                }
+                ConfigField::ForkTags => {
+                    config.editing.fork_tags = !config.editing.fork_tags;
+                    info!("Set tags copy on fork to {}.", config.editing.fork_tags);
+                    config.save()?;
+                }
+            }
+            State::ConfigManaging(state_data)
+        }
+        _ => State::ConfigManaging(state_data),
+    })
+}
+
+pub fn draw_config_managing_state(
+    state_data: &ConfigManagingStateData,
+    config: &Config,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_config_managing(frame, state_data, config, main_rect);
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
+
+fn draw_config_managing(
+    frame: &mut Frame,
+    ConfigManagingStateData {
+        selected,
+        editing,
+        status,
+    }: &ConfigManagingStateData,
+    config: &Config,
+    main_rect: Rect,
+) {
+    let name_width = CONFIG_FIELDS
+        .iter()
+        .map(|field| field.name().len())
+        .max()
+        .unwrap_or_default();
+
+    let fields = CONFIG_FIELDS.iter().enumerate().map(|(i, field)| {
+        let mut line = Line::from(vec![Span::raw(format!("{:name_width$}  ", field.name()))
+            .style(Style::default().add_modifier(Modifier::BOLD))]);
+
+        match (field, editing) {
+            (field, Some(text)) if i == *selected => {
+                let style = Style::default().fg(if field.is_valid(text.as_str()) {
+                    Color::Green
+                } else {
+                    Color::Red
+                });
+                let graphemes: Vec<&str> = text.as_str().graphemes(true).collect();
+                let (before, after) = graphemes.split_at(text.cursor());
+
+                line.spans.push(Span::styled(before.concat(), style));
+                line.spans.push(Span::styled(
+                    after.first().copied().unwrap_or(" ").to_owned(),
+                    style.add_modifier(Modifier::REVERSED),
+                ));
+                line.spans.push(Span::styled(
+                    after.iter().skip(1).copied().collect::<String>(),
+                    style,
+                ));
+            }
+            (ConfigField::Padding, _) => {
+                line.spans
+                    .push(Span::raw(config.layout.padding.to_string()));
+            }
+            (ConfigField::Borders, _) => {
+                line.spans.push(Span::raw(config.layout.borders.name()));
+            }
+            (ConfigField::DateFormat, _) => {
+                line.spans.push(Span::raw(format!(
+                    "{}  ({})",
+                    config.format.date_format,
+                    config.format.date(&Local::now())
+                )));
+            }
+            (ConfigField::ThousandsSeparator, _) => {
+                line.spans
+                    .push(if config.format.thousands_separator.is_empty() {
+                        Span::styled("None", Style::default().add_modifier(Modifier::DIM))
+                    } else {
+                        Span::raw(format!(
+                            "{:?}  ({})",
+                            config.format.thousands_separator,
+                            config.format.count(1_234_567_u64)
+                        ))
+                    });
+            }
+            (ConfigField::ConfirmDiscard, _) => {
+                line.spans.push(yes_no_span(config.editing.confirm_discard));
+            }
+            (ConfigField::ConfirmQuit, _) => {
+                line.spans.push(yes_no_span(config.editing.confirm_quit));
+            }
+            (ConfigField::ForkTags, _) => {
+                line.spans.push(yes_no_span(config.editing.fork_tags));
+            }
+        }
+
+        line.spans.push(Span::styled(
+            if field.is_editable(config) {
+                format!("  [{}]", config.source(field.key()).name())
+            } else {
+                format!("  [{}, not editable]", config.source(field.key()).name())
+            },
+            Style::default().add_modifier(Modifier::DIM),
+        ));
+        line
+    });
+
+    let title = match status {
+        Some(status) => format!("Preferences, {status}"),
+        None => String::from("Preferences"),
+    };
+
+    let config_list = List::new(fields.collect::<Vec<_>>())
+        .highlight_symbol(config.theme.highlight_symbol.as_str())
+        .highlight_style(if editing.is_some() {
+            Style::default()
+        } else {
+            config.theme.selection_style()
+        })
+        .block(
+            Block::new()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow))
+                .padding(Padding::uniform(2)),
+        );
+
+    frame.render_stateful_widget(
+        config_list,
+        main_rect,
+        &mut ListState::with_selected(ListState::default(), Some(*selected)),
+    );
+}
+
+fn yes_no_span(value: bool) -> Span<'static> {
+    if value {
+        Span::styled("Yes", Style::default().fg(Color::Green))
+    } else {
+        Span::styled("No", Style::default().fg(Color::Red))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    #[test]
+    fn values_set_outside_the_file_are_not_edited() {
+        let mut config = Config::default();
+        config
+            .sources
+            .insert(String::from("layout.padding"), ConfigSource::Flag);
+
+        let state = run_config_managing_state(
+            ConfigManagingStateData::empty(),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            &mut config,
+        )
+        .unwrap();
+        let State::ConfigManaging(state_data) = state else {
+            panic!("Enter left the preferences");
+        };
+        assert!(state_data.editing.is_none());
+        assert_eq!(
+            state_data.status.as_deref(),
+            Some("Padding is set by the flag, unset it to edit")
+        );
+        assert!(CONFIG_FIELDS[1].is_editable(&config));
+    }
+}
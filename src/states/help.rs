@@ -0,0 +1,100 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Padding, Paragraph};
+
+use crate::helpers::DiscardResult;
+use crate::keymap::KEYMAP;
+use crate::states::{State, Terminal};
+
+pub struct HelpStateData {
+    pub previous: Box<State>,
+    pub scroll: u16,
+}
+
+impl HelpStateData {
+    pub fn new(previous: State) -> Self {
+        HelpStateData {
+            previous: Box::new(previous),
+            scroll: 0,
+        }
+    }
+}
+
+pub fn run_help_state(mut state_data: HelpStateData, key_event: KeyEvent) -> State {
+    match key_event.code {
+        KeyCode::Esc | KeyCode::Char('?' | 'q') => {
+            info!("Close help.");
+            *state_data.previous
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            state_data.scroll = state_data.scroll.saturating_sub(1);
+            State::Help(state_data)
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            state_data.scroll = (state_data.scroll + 1).min(help_line_count());
+            State::Help(state_data)
+        }
+        _ => State::Help(state_data),
+    }
+}
+
+fn help_line_count() -> u16 {
+    let count: usize = KEYMAP.iter().map(|state| state.bindings.len() + 2).sum();
+    u16::try_from(count).unwrap_or(u16::MAX)
+}
+
+pub fn draw_help_state(
+    HelpStateData { scroll, .. }: &HelpStateData,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            let keys_width = KEYMAP
+                .iter()
+                .flat_map(|state| state.bindings)
+                .map(|binding| binding.keys.len())
+                .max()
+                .unwrap_or_default();
+
+            let mut lines = Vec::new();
+            for state in KEYMAP {
+                lines.push(Line::from(vec![Span::raw(state.state).style(
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                )]));
+                lines.extend(state.bindings.iter().map(|binding| {
+                    Line::from(vec![
+                        Span::raw(format!("  {:keys_width$}  ", binding.keys))
+                            .style(Style::default().fg(Color::Yellow)),
+                        Span::raw(binding.action),
+                    ])
+                }));
+                lines.push(Line::default());
+            }
+
+            let max_scroll = help_line_count().saturating_sub(main_rect.height.saturating_sub(4));
+
+            let help = Paragraph::new(lines)
+                .scroll(((*scroll).min(max_scroll), 0))
+                .block(
+                    Block::new()
+                        .title("Help")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::Yellow))
+                        .padding(Padding::uniform(1)),
+                );
+
+            frame.render_widget(help, main_rect);
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::Alignment;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use ratatui::widgets::block::{Position, Title};
+use ratatui::widgets::Block;
+
+use crate::config::Config;
+use crate::notebook::Notebook;
+use crate::states::{State, Terminal};
+
+/// A prompt whose typed input Esc would drop, waiting for a second Esc.
+pub struct InputDiscardingStateData {
+    pub prompt: Box<State>,
+}
+
+impl InputDiscardingStateData {
+    pub fn new(prompt: State) -> Self {
+        InputDiscardingStateData {
+            prompt: Box::new(prompt),
+        }
+    }
+}
+
+pub fn run_input_discarding_state(
+    state_data: InputDiscardingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+    config: &mut Config,
+    force_redraw: &mut bool,
+) -> Result<State> {
+    if key_event.code == KeyCode::Esc {
+        info!("Discard the typed input.");
+        state_data
+            .prompt
+            .dispatch(key_event, notebook, config, force_redraw)
+    } else {
+        // Any other key goes on with the prompt as if nothing happened
+        state_data
+            .prompt
+            .run(key_event, notebook, config, force_redraw)
+    }
+}
+
+pub fn draw_input_discarding_state(
+    InputDiscardingStateData { prompt }: &InputDiscardingStateData,
+    notebook: &Notebook,
+    config: &Config,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    let warning = Title::from(Span::styled(
+        " Esc again to discard the input, any other key to keep it ",
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    ))
+    .position(Position::Bottom)
+    .alignment(Alignment::Center);
+
+    prompt.draw_in(notebook, config, terminal, main_frame.title(warning))
+}
@@ -0,0 +1,200 @@
+use std::io;
+use std::path::Path;
+
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListState};
+
+use crate::attachment::{format_size, Attachment, AttachmentError};
+use crate::config::{FormatConfig, LayoutConfig, ThemeConfig};
+use crate::helpers::{create_popup_proportion, draw_text_prompt, DiscardResult, EditableText};
+use crate::notebook::Notebook;
+use crate::states::note_viewing::{draw_viewed_note, NoteViewingStateData};
+use crate::states::{State, Terminal};
+
+pub struct NoteAttachmentsStateData {
+    pub note_viewing_data: NoteViewingStateData,
+    pub attachments: Vec<Attachment>,
+    pub selected: usize,
+    pub adding: Option<EditableText>,
+    pub error: Option<String>,
+}
+
+impl NoteAttachmentsStateData {
+    pub fn new(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        Ok(NoteAttachmentsStateData {
+            attachments: Attachment::list(note_viewing_data.note_data.note.id, notebook.db())?,
+            note_viewing_data,
+            selected: 0,
+            adding: None,
+            error: None,
+        })
+    }
+
+    fn refresh(&mut self, notebook: &Notebook) -> Result<()> {
+        self.attachments =
+            Attachment::list(self.note_viewing_data.note_data.note.id, notebook.db())?;
+        self.selected = self.selected.min(self.attachments.len().saturating_sub(1));
+        Ok(())
+    }
+}
+
+pub fn run_note_attachments_state(
+    mut state_data: NoteAttachmentsStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    if let Some(mut path) = state_data.adding.take() {
+        match key_event.code {
+            KeyCode::Esc => {
+                state_data.error = None;
+                return Ok(State::NoteAttachments(state_data));
+            }
+            KeyCode::Enter => {
+                let note_id = state_data.note_viewing_data.note_data.note.id;
+                match Attachment::attach(
+                    note_id,
+                    Path::new(path.as_str()),
+                    notebook.attachments_dir().as_path(),
+                    notebook.db(),
+                ) {
+                    Ok(attachment) => {
+                        info!("Attach {} to note {note_id}.", attachment.filename);
+                        state_data.error = None;
+                        state_data.refresh(notebook)?;
+                        return Ok(State::NoteAttachments(state_data));
+                    }
+                    Err(err) => {
+                        state_data.error = Some(match err.downcast::<AttachmentError>() {
+                            Ok(err) => err.to_string(),
+                            Err(err) => err.downcast::<io::Error>()?.to_string(),
+                        });
+                    }
+                }
+            }
+            KeyCode::Backspace => path.remove_char(),
+            KeyCode::Delete => path.del_char(),
+            KeyCode::Left => path.move_left(),
+            KeyCode::Right => path.move_right(),
+            KeyCode::Char(c) => path.insert_char(c),
+            _ => {}
+        }
+        if key_event.code != KeyCode::Enter {
+            state_data.error = None;
+        }
+        state_data.adding = Some(path);
+        return Ok(State::NoteAttachments(state_data));
+    }
+
+    Ok(match key_event.code {
+        KeyCode::Esc => State::NoteViewing(state_data.note_viewing_data),
+        KeyCode::Char('a') => {
+            state_data.adding = Some(EditableText::new(String::new()));
+            State::NoteAttachments(state_data)
+        }
+        KeyCode::Char('d') if !state_data.attachments.is_empty() => {
+            let attachment = state_data.attachments.remove(state_data.selected);
+            info!("Detach {}.", attachment.filename);
+            attachment.detach(notebook.attachments_dir().as_path(), notebook.db())?;
+            state_data.refresh(notebook)?;
+            State::NoteAttachments(state_data)
+        }
+        KeyCode::Enter if !state_data.attachments.is_empty() => {
+            let attachment = &state_data.attachments[state_data.selected];
+            info!("Open {}.", attachment.filename);
+            attachment.open(notebook.attachments_dir().as_path())?;
+            State::NoteAttachments(state_data)
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::NoteAttachments(state_data)
+        }
+        KeyCode::Down if state_data.selected < state_data.attachments.len().saturating_sub(1) => {
+            state_data.selected += 1;
+            State::NoteAttachments(state_data)
+        }
+        _ => State::NoteAttachments(state_data),
+    })
+}
+
+pub fn draw_note_attachments_state(
+    NoteAttachmentsStateData {
+        note_viewing_data,
+        attachments,
+        selected,
+        adding,
+        error,
+    }: &NoteAttachmentsStateData,
+    layout: LayoutConfig,
+    format: &FormatConfig,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_viewed_note(frame, note_viewing_data, layout, main_rect);
+
+            let popup_area = create_popup_proportion((60, 60), main_rect);
+            let attachments_block = Block::new()
+                .title("Attachments")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow));
+            let attachments_width =
+                attachments_block.inner(popup_area).width.saturating_sub(3) as usize;
+
+            let attachments_list = if attachments.is_empty() {
+                List::new([Line::from(
+                    Span::raw("No attachment, press a to add one").add_modifier(Modifier::DIM),
+                )])
+            } else {
+                List::new(attachments.iter().map(|attachment| {
+                    let mut line = Line::from(vec![Span::raw(attachment.filename.as_str())]);
+                    let badge = Span::raw(format!(
+                        "{}  {}",
+                        format_size(attachment.size),
+                        format.date(&attachment.created_at)
+                    ))
+                    .add_modifier(Modifier::DIM);
+                    let gap = attachments_width
+                        .saturating_sub(line.width() + badge.width())
+                        .max(1);
+                    line.spans.push(Span::raw(" ".repeat(gap)));
+                    line.spans.push(badge);
+                    line
+                }))
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
+            }
+            .block(attachments_block);
+
+            frame.render_widget(Clear, popup_area);
+            frame.render_stateful_widget(
+                attachments_list,
+                popup_area,
+                &mut ListState::default()
+                    .with_selected((!attachments.is_empty()).then_some(*selected)),
+            );
+
+            if let Some(path) = adding {
+                draw_text_prompt(
+                    frame,
+                    "Attach file",
+                    path.as_str(),
+                    error.is_none(),
+                    error.as_deref(),
+                    main_rect,
+                );
+            }
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
@@ -1,47 +1,97 @@
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
 
-use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::widgets::Block;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListState};
+use ratatui::Frame;
+
+use rusqlite::Connection;
 
 use crate::helpers::{draw_text_prompt, DiscardResult, TryFromDatabase};
-use crate::note::Note;
+use crate::note::{Note, NoteData, NoteError, NoteSummary};
 use crate::notebook::Notebook;
 use crate::states::note_viewing::NoteViewingStateData;
 use crate::states::{State, Terminal};
+use crate::tag::Tag;
+
+const SIMILAR_NOTES_SHOWN: usize = 3;
 
 pub struct NoteCreatingStateData {
     pub name: String,
-    pub valid: bool,
+    pub error: Option<NoteError>,
+    pub similar: Vec<NoteSummary>,
+    pub selected_similar: usize,
 }
 
 impl NoteCreatingStateData {
     pub fn empty() -> Self {
         NoteCreatingStateData {
             name: String::new(),
-            valid: false,
+            error: Some(NoteError::EmptyName),
+            similar: Vec::new(),
+            selected_similar: 0,
         }
     }
+
+    fn update_name(&mut self, db: &Connection) -> Result<()> {
+        self.error = Note::validate_name(self.name.as_str(), db)?;
+        self.similar = NoteSummary::search_similar(self.name.as_str(), SIMILAR_NOTES_SHOWN, db)?;
+        self.selected_similar = 0;
+        Ok(())
+    }
 }
 
 pub fn run_note_creating_state(
-    NoteCreatingStateData { mut name, valid }: NoteCreatingStateData,
+    mut state_data: NoteCreatingStateData,
     key_event: KeyEvent,
     notebook: &Notebook,
 ) -> Result<State> {
+    let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+
     Ok(match key_event.code {
-        KeyCode::Enter if !name.is_empty() => {
-            if Note::note_exists(name.as_str(), notebook.db())? {
-                State::NoteCreating(NoteCreatingStateData { name, valid: false })
+        KeyCode::Char('o') if control && !state_data.similar.is_empty() => {
+            let summary = &state_data.similar[state_data.selected_similar];
+            if let Some(note) = Note::load_by_id(summary.id, notebook.db())? {
+                info!("Open similar note {} instead of creating one.", note.name);
+                State::note_viewing(note, notebook)?
+            } else {
+                State::NoteCreating(state_data)
+            }
+        }
+        KeyCode::Up if state_data.selected_similar > 0 => {
+            state_data.selected_similar -= 1;
+            State::NoteCreating(state_data)
+        }
+        KeyCode::Down
+            if state_data.selected_similar < state_data.similar.len().saturating_sub(1) =>
+        {
+            state_data.selected_similar += 1;
+            State::NoteCreating(state_data)
+        }
+        KeyCode::Enter => {
+            let name = state_data.name.as_str();
+            if let Some(error) = Note::validate_name(name, notebook.db())? {
+                state_data.error = Some(error);
+                State::NoteCreating(state_data)
             } else {
-                info!("Create note : {}.", name.as_str());
+                info!("Create note : {}.", Note::normalize_name(name));
 
-                let new_note = Note::new(name.clone(), String::new(), notebook.db())?;
+                let new_note = match Note::new(name, String::new(), notebook.db()) {
+                    Ok(note) => note,
+                    Err(err) => {
+                        state_data.error = Some(err.downcast::<NoteError>()?);
+                        return Ok(State::NoteCreating(state_data));
+                    }
+                };
+                let mut note_data = NoteData::try_from_database(new_note, notebook.db())?;
+                add_default_tags(&mut note_data, notebook)?;
 
-                State::NoteViewing(NoteViewingStateData::try_from_database(
-                    new_note,
-                    notebook.db(),
-                )?)
+                State::NoteViewing(
+                    NoteViewingStateData::from(note_data).with_default_view(&notebook.settings()),
+                )
             }
         }
         KeyCode::Esc => {
@@ -49,25 +99,47 @@ pub fn run_note_creating_state(
             State::Nothing
         }
         KeyCode::Backspace => {
-            name.pop();
-            State::NoteCreating(NoteCreatingStateData {
-                valid: !Note::note_exists(name.as_str(), notebook.db())?,
-                name,
-            })
+            state_data.name.pop();
+            state_data.update_name(notebook.db())?;
+            State::NoteCreating(state_data)
         }
-        KeyCode::Char(c) => {
-            name.push(c);
-            State::NoteCreating(NoteCreatingStateData {
-                valid: !Note::note_exists(name.as_str(), notebook.db())?,
-                name,
-            })
+        KeyCode::Char(c) if !control => {
+            state_data.name.push(c);
+            state_data.update_name(notebook.db())?;
+            State::NoteCreating(state_data)
         }
-        _ => State::NoteCreating(NoteCreatingStateData { name, valid }),
+        _ => State::NoteCreating(state_data),
     })
 }
 
+pub fn add_default_tags(note_data: &mut NoteData, notebook: &Notebook) -> Result<()> {
+    for tag_name in notebook.settings().default_tags() {
+        let tag = match Tag::load_by_name(tag_name, notebook.db())? {
+            Some(tag) => tag,
+            None if Tag::load_trashed(tag_name, notebook.db())?.is_some() => {
+                warn!("The default tag {tag_name} is in the trash, leave it off.");
+                continue;
+            }
+            None => Tag::new(tag_name, notebook.db())?,
+        };
+        if note_data.tags.iter().all(|t| t.id != tag.id) {
+            info!(
+                "Add default tag {} to note {}.",
+                tag.name, note_data.note.name
+            );
+            note_data.add_tag(tag, notebook.db())?;
+        }
+    }
+    Ok(())
+}
+
 pub fn draw_note_creating_state(
-    NoteCreatingStateData { name, valid }: &NoteCreatingStateData,
+    NoteCreatingStateData {
+        name,
+        error,
+        similar,
+        selected_similar,
+    }: &NoteCreatingStateData,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -75,9 +147,67 @@ pub fn draw_note_creating_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_text_prompt(frame, "Note name", name, *valid, main_rect);
+            let prompt_area = draw_text_prompt(
+                frame,
+                prompt_title("Note name", name).as_str(),
+                name,
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
+
+            if !similar.is_empty() {
+                draw_similar_notes(frame, similar, *selected_similar, prompt_area, main_rect);
+            }
 
             frame.render_widget(main_frame, frame.size());
         })
         .discard_result()
 }
+
+fn draw_similar_notes(
+    frame: &mut Frame,
+    similar: &[NoteSummary],
+    selected: usize,
+    prompt_area: Rect,
+    main_rect: Rect,
+) {
+    let similar_area = Rect {
+        y: prompt_area.bottom(),
+        height: (u16::try_from(similar.len()).unwrap_or(u16::MAX) + 2)
+            .min(main_rect.bottom().saturating_sub(prompt_area.bottom())),
+        ..prompt_area
+    };
+
+    let similar_list = List::new(
+        similar
+            .iter()
+            .map(|summary| Line::from(vec![Span::raw(summary.name.as_str())]))
+            .collect::<Vec<_>>(),
+    )
+    .highlight_symbol("> ")
+    .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+    .block(
+        Block::new()
+            .title("Similar (Ctrl+o)")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    frame.render_widget(Clear, similar_area);
+    frame.render_stateful_widget(
+        similar_list,
+        similar_area,
+        &mut ListState::default().with_selected(Some(selected)),
+    );
+}
+
+pub fn prompt_title(title: &str, name: &str) -> String {
+    let normalized_name = Note::normalize_name(name);
+    if normalized_name == name {
+        title.to_string()
+    } else {
+        format!("{title} : {normalized_name:?}")
+    }
+}
@@ -4,7 +4,10 @@ use log::info;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::Block;
 
-use crate::helpers::{draw_yes_no_prompt, DiscardResult};
+use crate::attachment::Attachment;
+use crate::config::LayoutConfig;
+use crate::helpers::{draw_text_prompt, draw_yes_no_prompt, DiscardResult, EditableText};
+use crate::note::Note;
 use crate::notebook::Notebook;
 use crate::states::note_viewing::{draw_viewed_note, NoteViewingStateData};
 use crate::states::{State, Terminal};
@@ -12,62 +15,85 @@ use crate::states::{State, Terminal};
 pub struct NoteDeletingStateData {
     pub note_viewing_data: NoteViewingStateData,
     pub delete: bool,
+    pub backlinks: usize,
+    pub typed_name: Option<EditableText>,
 }
 
 impl NoteDeletingStateData {
-    pub fn empty(note_viewing_data: NoteViewingStateData) -> Self {
-        NoteDeletingStateData {
+    pub fn empty(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        let backlinks = Note::count_backlinks(
+            note_viewing_data.note_data.note.name.as_str(),
+            notebook.db(),
+        )?;
+        let typed_name = (backlinks > notebook.settings().typed_delete_backlinks())
+            .then(|| EditableText::new(String::new()));
+
+        Ok(NoteDeletingStateData {
             note_viewing_data,
             delete: false,
-        }
+            backlinks,
+            typed_name,
+        })
     }
 }
 
 pub fn run_note_deleting_state(
-    NoteDeletingStateData {
-        note_viewing_data,
-        delete,
-    }: NoteDeletingStateData,
+    mut state_data: NoteDeletingStateData,
     key_event: KeyEvent,
     notebook: &Notebook,
 ) -> Result<State> {
+    let note_name = state_data.note_viewing_data.note_data.note.name.as_str();
+
     Ok(match key_event.code {
         KeyCode::Esc => {
-            info!(
-                "Cancel deleting note {}.",
-                note_viewing_data.note_data.note.name
-            );
-            State::NoteViewing(note_viewing_data)
+            info!("Cancel deleting note {note_name}.");
+            State::NoteViewing(state_data.note_viewing_data)
         }
-        KeyCode::Tab => State::NoteDeleting(NoteDeletingStateData {
-            note_viewing_data,
-            delete: !delete,
-        }),
-        KeyCode::Enter => {
-            if delete {
-                info!("Delete note {}.", note_viewing_data.note_data.note.name);
-                note_viewing_data.note_data.note.delete(notebook.db())?;
-                State::Nothing
-            } else {
-                info!(
-                    "Cancel deleting note {}.",
-                    note_viewing_data.note_data.note.name
-                );
-                State::NoteViewing(note_viewing_data)
+        KeyCode::Enter => match &state_data.typed_name {
+            Some(typed_name) if typed_name.as_str() != note_name => State::NoteDeleting(state_data),
+            Some(_) => delete_note(state_data.note_viewing_data, notebook)?,
+            None if state_data.delete => delete_note(state_data.note_viewing_data, notebook)?,
+            None => {
+                info!("Cancel deleting note {note_name}.");
+                State::NoteViewing(state_data.note_viewing_data)
             }
+        },
+        KeyCode::Tab if state_data.typed_name.is_none() => {
+            state_data.delete = !state_data.delete;
+            State::NoteDeleting(state_data)
+        }
+        code => {
+            if let Some(typed_name) = &mut state_data.typed_name {
+                match code {
+                    KeyCode::Backspace => typed_name.remove_char(),
+                    KeyCode::Delete => typed_name.del_char(),
+                    KeyCode::Left => typed_name.move_left(),
+                    KeyCode::Right => typed_name.move_right(),
+                    KeyCode::Char(c) => typed_name.insert_char(c),
+                    _ => {}
+                }
+            }
+            State::NoteDeleting(state_data)
         }
-        _ => State::NoteDeleting(NoteDeletingStateData {
-            note_viewing_data,
-            delete,
-        }),
     })
 }
 
+fn delete_note(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<State> {
+    info!("Delete note {}.", note_viewing_data.note_data.note.name);
+    // The attachments go with the note, their blobs are removed once it is gone
+    note_viewing_data.note_data.note.delete(notebook.db())?;
+    Attachment::collect_garbage(notebook.attachments_dir().as_path(), notebook.db())?;
+    Ok(State::Nothing)
+}
+
 pub fn draw_note_deleting_state(
     NoteDeletingStateData {
         note_viewing_data,
         delete,
+        backlinks,
+        typed_name,
     }: &NoteDeletingStateData,
+    layout: LayoutConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -75,9 +101,23 @@ pub fn draw_note_deleting_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_viewed_note(frame, note_viewing_data, main_rect);
+            draw_viewed_note(frame, note_viewing_data, layout, main_rect);
 
-            draw_yes_no_prompt(frame, *delete, "Delete note ?", main_rect);
+            if let Some(typed_name) = typed_name {
+                draw_text_prompt(
+                    frame,
+                    "Delete note ?",
+                    typed_name.as_str(),
+                    typed_name.as_str() == note_viewing_data.note_data.note.name,
+                    Some(
+                        format!("{backlinks} notes link here, type the note name to delete it")
+                            .as_str(),
+                    ),
+                    main_rect,
+                );
+            } else {
+                draw_yes_no_prompt(frame, *delete, "Delete note ?", main_rect);
+            }
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -0,0 +1,238 @@
+use std::cell::Cell;
+
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::config::{EditingConfig, LayoutConfig};
+use crate::helpers::{draw_yes_no_prompt, DiscardResult, EditableText};
+use crate::notebook::Notebook;
+use crate::states::note_viewing::{draw_note_header, NoteViewingStateData};
+use crate::states::{State, Terminal};
+
+pub struct NoteEditingStateData {
+    pub note_viewing_data: NoteViewingStateData,
+    pub text: EditableText,
+    pub discarding: Option<bool>,
+    // The width the text was last wrapped at, for the cursor to move by rows
+    pub wrap_width: Cell<usize>,
+}
+
+impl From<NoteViewingStateData> for NoteEditingStateData {
+    fn from(note_viewing_data: NoteViewingStateData) -> Self {
+        let mut text = EditableText::new(note_viewing_data.note_data.note.content.clone());
+        text.move_to_start();
+        NoteEditingStateData {
+            note_viewing_data,
+            text,
+            discarding: None,
+            wrap_width: Cell::new(usize::MAX),
+        }
+    }
+}
+
+impl NoteEditingStateData {
+    pub fn modified(&self) -> bool {
+        self.text.as_str() != self.note_viewing_data.note_data.note.content
+    }
+}
+
+pub fn run_note_editing_state(
+    mut state_data: NoteEditingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+    editing: &EditingConfig,
+) -> Result<State> {
+    if let Some(discard) = state_data.discarding {
+        return Ok(match key_event.code {
+            KeyCode::Tab => {
+                state_data.discarding = Some(!discard);
+                State::NoteEditing(state_data)
+            }
+            KeyCode::Enter if discard => {
+                info!(
+                    "Discard edits of note {}.",
+                    state_data.note_viewing_data.note_data.note.name
+                );
+                State::NoteViewing(state_data.note_viewing_data)
+            }
+            KeyCode::Enter | KeyCode::Esc => {
+                state_data.discarding = None;
+                State::NoteEditing(state_data)
+            }
+            _ => State::NoteEditing(state_data),
+        });
+    }
+
+    let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            if state_data.modified() && editing.confirm_discard {
+                state_data.discarding = Some(false);
+                State::NoteEditing(state_data)
+            } else {
+                info!(
+                    "Stop editing note {}.",
+                    state_data.note_viewing_data.note_data.note.name
+                );
+                State::NoteViewing(state_data.note_viewing_data)
+            }
+        }
+        KeyCode::Char('s') if control => {
+            info!(
+                "Save edits of note {}.",
+                state_data.note_viewing_data.note_data.note.name
+            );
+            let mut note_viewing_data = state_data.note_viewing_data;
+            note_viewing_data.note_data.note.content = state_data.text.into();
+            note_viewing_data.note_data.note.update(notebook.db())?;
+            note_viewing_data.refresh_content(notebook.db())?;
+            State::NoteViewing(note_viewing_data)
+        }
+        KeyCode::Char('r') if control => {
+            info!(
+                "Revert edits of note {}.",
+                state_data.note_viewing_data.note_data.note.name
+            );
+            state_data.text =
+                EditableText::new(state_data.note_viewing_data.note_data.note.content.clone());
+            state_data.text.move_to_start();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Char(c) if !control => {
+            state_data.text.insert_char(c);
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Enter => {
+            state_data.text.insert_char('\n');
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Backspace => {
+            state_data.text.remove_char();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Delete => {
+            state_data.text.del_char();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Left => {
+            state_data.text.move_left();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Right => {
+            state_data.text.move_right();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Up => {
+            state_data.text.move_row_up(state_data.wrap_width.get());
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Down => {
+            state_data.text.move_row_down(state_data.wrap_width.get());
+            State::NoteEditing(state_data)
+        }
+        KeyCode::Home => {
+            state_data.text.move_to_line_start();
+            State::NoteEditing(state_data)
+        }
+        KeyCode::End => {
+            state_data.text.move_to_line_end();
+            State::NoteEditing(state_data)
+        }
+        _ => State::NoteEditing(state_data),
+    })
+}
+
+pub fn draw_note_editing_state(
+    state_data: &NoteEditingStateData,
+    layout: LayoutConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_edited_note(frame, state_data, layout, main_rect);
+
+            if let Some(discard) = state_data.discarding {
+                draw_yes_no_prompt(frame, discard, "Discard changes ?", main_rect);
+            }
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
+
+pub fn draw_edited_note(
+    frame: &mut Frame,
+    NoteEditingStateData {
+        note_viewing_data,
+        text,
+        discarding,
+        wrap_width,
+    }: &NoteEditingStateData,
+    layout: LayoutConfig,
+    main_rect: Rect,
+) {
+    let vertical_layout = Layout::new(
+        Direction::Vertical,
+        [
+            Constraint::Length(1 + layout.frame_size()),
+            Constraint::Min(0),
+        ],
+    )
+    .split(main_rect);
+
+    draw_note_header(
+        frame,
+        &note_viewing_data.note_data.note,
+        &note_viewing_data.note_data.tags,
+        layout,
+        vertical_layout[0],
+    );
+
+    let editor_block = layout
+        .block()
+        .title("Editing")
+        .border_style(Style::default().fg(Color::Blue));
+    let editor_area = editor_block.inner(vertical_layout[1]);
+    let width = (editor_area.width as usize).max(1);
+
+    wrap_width.set(width);
+
+    let graphemes: Vec<&str> = text.as_str().graphemes(true).collect();
+    let wrapped_lines: Vec<Line> = text
+        .wrapped_rows(width)
+        .iter()
+        .map(|row| Line::raw(graphemes[row.start..row.start + row.length].concat()))
+        .collect();
+    let (cursor_row, cursor_column) = text.wrapped_cursor(width);
+
+    let scroll = cursor_row.saturating_sub((editor_area.height as usize).saturating_sub(1));
+
+    let editor =
+        Paragraph::new(wrapped_lines).scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0));
+
+    frame.render_widget(editor, editor_area);
+    frame.render_widget(editor_block, vertical_layout[1]);
+
+    if discarding.is_none() {
+        frame.set_cursor(
+            editor_area
+                .x
+                .saturating_add(u16::try_from(cursor_column).unwrap_or(u16::MAX)),
+            editor_area
+                .y
+                .saturating_add(u16::try_from(cursor_row - scroll).unwrap_or(u16::MAX)),
+        );
+    }
+}
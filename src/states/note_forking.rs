@@ -0,0 +1,119 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::Block;
+
+use crate::config::{EditingConfig, LayoutConfig};
+use crate::helpers::{draw_text_prompt, DiscardResult, EditableText, TryFromDatabase};
+use crate::note::{Note, NoteData, NoteError};
+use crate::notebook::Notebook;
+use crate::states::note_creating::{add_default_tags, prompt_title};
+use crate::states::note_viewing::{draw_viewed_note, NoteViewingStateData};
+use crate::states::{State, Terminal};
+
+pub struct NoteForkingStateData {
+    pub note_viewing_data: NoteViewingStateData,
+    pub name: EditableText,
+    pub error: Option<NoteError>,
+}
+
+impl NoteForkingStateData {
+    pub fn new(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        let name = format!("{} (fork)", note_viewing_data.note_data.note.name);
+        Ok(NoteForkingStateData {
+            error: Note::validate_name(name.as_str(), notebook.db())?,
+            name: EditableText::new(name),
+            note_viewing_data,
+        })
+    }
+}
+
+pub fn run_note_forking_state(
+    mut state_data: NoteForkingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+    editing: &EditingConfig,
+) -> Result<State> {
+    match key_event.code {
+        KeyCode::Esc => {
+            info!(
+                "Cancel forking note {}.",
+                state_data.note_viewing_data.note_data.note.name
+            );
+            return Ok(State::NoteViewing(state_data.note_viewing_data));
+        }
+        KeyCode::Enter if state_data.error.is_none() => {
+            let source = &state_data.note_viewing_data.note_data;
+            info!(
+                "Fork note {} into {}.",
+                source.note.name,
+                state_data.name.as_str()
+            );
+
+            let note = match Note::new(
+                state_data.name.as_str(),
+                source.note.content.clone(),
+                notebook.db(),
+            ) {
+                Ok(note) => note,
+                Err(err) => {
+                    state_data.error = Some(err.downcast::<NoteError>()?);
+                    return Ok(State::NoteForking(state_data));
+                }
+            };
+
+            let mut note_data = NoteData::try_from_database(note, notebook.db())?;
+            if editing.fork_tags {
+                for tag in &source.tags {
+                    note_data.add_tag(tag.clone(), notebook.db())?;
+                }
+            }
+            add_default_tags(&mut note_data, notebook)?;
+
+            let mut note_viewing_data =
+                NoteViewingStateData::from(note_data).with_default_view(&notebook.settings());
+            note_viewing_data.refresh_content(notebook.db())?;
+            note_viewing_data.status = Some(format!("Forked from {}", source.note.name));
+            return Ok(State::NoteViewing(note_viewing_data));
+        }
+        KeyCode::Backspace => state_data.name.remove_char(),
+        KeyCode::Delete => state_data.name.del_char(),
+        KeyCode::Left => state_data.name.move_left(),
+        KeyCode::Right => state_data.name.move_right(),
+        KeyCode::Char(c) => state_data.name.insert_char(c),
+        _ => {}
+    }
+
+    state_data.error = Note::validate_name(state_data.name.as_str(), notebook.db())?;
+    Ok(State::NoteForking(state_data))
+}
+
+pub fn draw_note_forking_state(
+    NoteForkingStateData {
+        note_viewing_data,
+        name,
+        error,
+    }: &NoteForkingStateData,
+    layout: LayoutConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_viewed_note(frame, note_viewing_data, layout, main_rect);
+            draw_text_prompt(
+                frame,
+                prompt_title("Fork note as", name.as_str()).as_str(),
+                name.as_str(),
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
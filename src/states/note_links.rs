@@ -0,0 +1,170 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListState};
+
+use crate::config::{LayoutConfig, ThemeConfig};
+use crate::helpers::{create_popup_proportion, DiscardResult, TryFromDatabase};
+use crate::note::{Note, NoteData};
+use crate::notebook::Notebook;
+use crate::states::note_creating::add_default_tags;
+use crate::states::note_viewing::{draw_viewed_note, NoteViewingStateData};
+use crate::states::{State, Terminal};
+
+pub enum LinkEntry {
+    Outgoing { name: String, exists: bool },
+    Incoming { name: String },
+}
+
+impl LinkEntry {
+    fn name(&self) -> &str {
+        match self {
+            LinkEntry::Outgoing { name, .. } | LinkEntry::Incoming { name } => name,
+        }
+    }
+}
+
+pub struct NoteLinksStateData {
+    pub note_viewing_data: NoteViewingStateData,
+    pub entries: Vec<LinkEntry>,
+    pub selected: usize,
+}
+
+impl NoteLinksStateData {
+    pub fn new(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        let mut outgoing: Vec<String> = note_viewing_data
+            .parsed_content
+            .list_links()
+            .into_iter()
+            .map(Note::normalize_name)
+            .collect();
+        outgoing.sort();
+        outgoing.dedup();
+
+        let mut entries = Vec::new();
+        for name in outgoing {
+            entries.push(LinkEntry::Outgoing {
+                exists: Note::note_exists(name.as_str(), notebook.db())?,
+                name,
+            });
+        }
+        entries.extend(
+            Note::list_backlinks(
+                note_viewing_data.note_data.note.name.as_str(),
+                notebook.db(),
+            )?
+            .into_iter()
+            .map(|name| LinkEntry::Incoming { name }),
+        );
+
+        Ok(NoteLinksStateData {
+            note_viewing_data,
+            entries,
+            selected: 0,
+        })
+    }
+}
+
+pub fn run_note_links_state(
+    mut state_data: NoteLinksStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    Ok(match key_event.code {
+        KeyCode::Esc => State::NoteViewing(state_data.note_viewing_data),
+        KeyCode::Enter if !state_data.entries.is_empty() => {
+            let name = state_data.entries[state_data.selected].name();
+            if let Some(note) = Note::load_by_name(name, notebook.db())? {
+                info!("Open linked note {}.", note.name);
+                let mut new_viewing_data =
+                    NoteViewingStateData::try_from_database(note, notebook.db())?;
+                new_viewing_data.view = state_data.note_viewing_data.view;
+                State::NoteViewing(new_viewing_data)
+            } else {
+                info!("Create dangling note {name}.");
+                let mut note_data = NoteData::try_from_database(
+                    Note::new(name, String::new(), notebook.db())?,
+                    notebook.db(),
+                )?;
+                add_default_tags(&mut note_data, notebook)?;
+                State::NoteViewing(
+                    NoteViewingStateData::from(note_data).with_default_view(&notebook.settings()),
+                )
+            }
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::NoteLinks(state_data)
+        }
+        KeyCode::Down if state_data.selected < state_data.entries.len().saturating_sub(1) => {
+            state_data.selected += 1;
+            State::NoteLinks(state_data)
+        }
+        _ => State::NoteLinks(state_data),
+    })
+}
+
+pub fn draw_note_links_state(
+    NoteLinksStateData {
+        note_viewing_data,
+        entries,
+        selected,
+    }: &NoteLinksStateData,
+    layout: LayoutConfig,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_viewed_note(frame, note_viewing_data, layout, main_rect);
+
+            let popup_area = create_popup_proportion((60, 60), main_rect);
+            let links_block = Block::new()
+                .title("Links")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow));
+
+            let links_list = if entries.is_empty() {
+                List::new([Line::from(
+                    Span::raw("No link from or to this note").add_modifier(Modifier::DIM),
+                )])
+            } else {
+                List::new(entries.iter().map(|entry| match entry {
+                    LinkEntry::Outgoing { name, exists: true } => {
+                        Line::from(vec![Span::raw("→ "), Span::raw(name.as_str())])
+                    }
+                    LinkEntry::Outgoing {
+                        name,
+                        exists: false,
+                    } => Line::from(vec![
+                        Span::raw("→ "),
+                        Span::raw(name.as_str()).fg(Color::Red),
+                        Span::raw("  dangling").add_modifier(Modifier::DIM),
+                    ]),
+                    LinkEntry::Incoming { name } => {
+                        Line::from(vec![Span::raw("← "), Span::raw(name.as_str())])
+                    }
+                }))
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
+            }
+            .block(links_block);
+
+            frame.render_widget(Clear, popup_area);
+            frame.render_stateful_widget(
+                links_list,
+                popup_area,
+                &mut ListState::default().with_selected((!entries.is_empty()).then_some(*selected)),
+            );
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
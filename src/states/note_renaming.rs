@@ -4,25 +4,36 @@ use log::info;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::Block;
 
-use crate::helpers::{draw_text_prompt, DiscardResult};
-use crate::note::Note;
+use crate::config::LayoutConfig;
+use crate::helpers::{draw_text_prompt, DiscardResult, TryFromDatabase};
+use crate::note::{Note, NoteError};
 use crate::notebook::Notebook;
+use crate::states::note_creating::prompt_title;
 use crate::states::note_viewing::{draw_viewed_note, NoteViewingStateData};
 use crate::states::{State, Terminal};
 
 pub struct NoteRenamingStateData {
     pub note_viewing_data: NoteViewingStateData,
     pub new_name: String,
-    pub valid: bool,
+    pub error: Option<NoteError>,
+    pub backlinks: usize,
+    pub rewrite_references: bool,
 }
 
 impl NoteRenamingStateData {
-    pub fn empty(note_viewing_data: NoteViewingStateData) -> Self {
-        NoteRenamingStateData {
+    pub fn empty(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        let backlinks = Note::count_backlinks(
+            note_viewing_data.note_data.note.name.as_str(),
+            notebook.db(),
+        )?;
+
+        Ok(NoteRenamingStateData {
             note_viewing_data,
             new_name: String::new(),
-            valid: false,
-        }
+            error: Some(NoteError::EmptyName),
+            backlinks,
+            rewrite_references: true,
+        })
     }
 }
 
@@ -39,10 +50,10 @@ pub fn run_note_renaming_state(
             );
             State::NoteViewing(state_data.note_viewing_data)
         }
-        KeyCode::Enter if !state_data.new_name.is_empty() => {
-            if Note::note_exists(state_data.new_name.as_str(), notebook.db())? {
+        KeyCode::Enter => {
+            if let Some(error) = Note::validate_name(state_data.new_name.as_str(), notebook.db())? {
                 State::NoteRenaming(NoteRenamingStateData {
-                    valid: false,
+                    error: Some(error),
                     ..state_data
                 })
             } else {
@@ -50,24 +61,52 @@ pub fn run_note_renaming_state(
                     "Renaming note {} to {}.",
                     state_data.note_viewing_data.note_data.note.name, state_data.new_name
                 );
-                state_data.note_viewing_data.note_data.note.name = state_data.new_name;
-                state_data
+                let old_name = state_data.note_viewing_data.note_data.note.name.clone();
+                if let Err(err) = state_data
                     .note_viewing_data
                     .note_data
                     .note
-                    .update(notebook.db())?;
-                State::NoteViewing(state_data.note_viewing_data)
+                    .rename(state_data.new_name.as_str(), notebook.db())
+                {
+                    state_data.error = Some(err.downcast::<NoteError>()?);
+                    State::NoteRenaming(state_data)
+                } else if state_data.rewrite_references && state_data.backlinks > 0 {
+                    let note_viewing_data = state_data.note_viewing_data;
+                    let rewritten = Note::rewrite_references(
+                        old_name.as_str(),
+                        note_viewing_data.note_data.note.name.as_str(),
+                        notebook.db(),
+                    )?;
+                    info!("Rewrote the references of {rewritten} notes.");
+
+                    // The renamed note may reference itself, so reload it.
+                    let Some(note) =
+                        Note::load_by_id(note_viewing_data.note_data.note.id, notebook.db())?
+                    else {
+                        return Ok(State::Nothing);
+                    };
+                    let mut new_viewing_data =
+                        NoteViewingStateData::try_from_database(note, notebook.db())?;
+                    new_viewing_data.view = note_viewing_data.view;
+                    State::NoteViewing(new_viewing_data)
+                } else {
+                    State::NoteViewing(state_data.note_viewing_data)
+                }
             }
         }
 
+        KeyCode::Tab if state_data.backlinks > 0 => {
+            state_data.rewrite_references = !state_data.rewrite_references;
+            State::NoteRenaming(state_data)
+        }
         KeyCode::Backspace => {
             state_data.new_name.pop();
-            state_data.valid = !Note::note_exists(state_data.new_name.as_str(), notebook.db())?;
+            state_data.error = Note::validate_name(state_data.new_name.as_str(), notebook.db())?;
             State::NoteRenaming(state_data)
         }
         KeyCode::Char(c) => {
             state_data.new_name.push(c);
-            state_data.valid = !Note::note_exists(state_data.new_name.as_str(), notebook.db())?;
+            state_data.error = Note::validate_name(state_data.new_name.as_str(), notebook.db())?;
             State::NoteRenaming(state_data)
         }
         _ => State::NoteRenaming(state_data),
@@ -78,8 +117,11 @@ pub fn draw_note_renaming_state(
     NoteRenamingStateData {
         note_viewing_data,
         new_name,
-        valid,
+        error,
+        backlinks,
+        rewrite_references,
     }: &NoteRenamingStateData,
+    layout: LayoutConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -87,8 +129,26 @@ pub fn draw_note_renaming_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_viewed_note(frame, note_viewing_data, main_rect);
-            draw_text_prompt(frame, "Rename note", new_name, *valid, main_rect);
+            let message = match error {
+                Some(error) => Some(error.to_string()),
+                None if *backlinks > 0 && *rewrite_references => Some(format!(
+                    "{backlinks} notes link here; references will be rewritten (Tab)"
+                )),
+                None if *backlinks > 0 => Some(format!(
+                    "{backlinks} notes link here; references will break unless rewritten (Tab)"
+                )),
+                None => None,
+            };
+
+            draw_viewed_note(frame, note_viewing_data, layout, main_rect);
+            draw_text_prompt(
+                frame,
+                prompt_title("Rename note", new_name).as_str(),
+                new_name,
+                error.is_none(),
+                message.as_deref(),
+                main_rect,
+            );
 
             frame.render_widget(main_frame, frame.size());
         })
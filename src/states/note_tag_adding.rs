@@ -2,28 +2,56 @@ use anyhow::Result;
 use log::info;
 
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::widgets::Block;
+use ratatui::prelude::{Alignment, Rect};
+use ratatui::style::{Color, Modifier, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
 
-use crate::helpers::{draw_text_prompt, DiscardResult};
+use rusqlite::Connection;
+
+use crate::config::ThemeConfig;
+use crate::helpers::{draw_text_prompt, draw_yes_no_prompt, DiscardResult};
 use crate::notebook::Notebook;
 use crate::states::note_tags_managing::{draw_note_tags_managing, NoteTagsManagingStateData};
 use crate::states::{State, Terminal};
-use crate::tag::Tag;
+use crate::tag::{suggest_tags, Tag, TagError};
 
 pub struct NoteTagAddingStateData {
     pub note_tags_managing_data: NoteTagsManagingStateData,
     pub tag_name: String,
-    pub valid: bool,
+    pub error: Option<TagError>,
+    pub suggestions: Vec<String>,
+    pub suggestion: Option<usize>,
+    pub create_tag: Option<bool>,
 }
 
 impl NoteTagAddingStateData {
-    pub fn empty(note_tags_managing_data: NoteTagsManagingStateData) -> Self {
+    pub fn empty(note_tags_managing_data: NoteTagsManagingStateData, notebook: &Notebook) -> Self {
+        let note_data = &note_tags_managing_data.note_data;
+        let suggestions = suggest_tags(
+            note_data.note.content.as_str(),
+            &note_data.tags,
+            notebook.settings().tag_suggestions(),
+        );
         NoteTagAddingStateData {
             note_tags_managing_data,
             tag_name: String::new(),
-            valid: false,
+            error: Some(TagError::EmptyName),
+            suggestions,
+            suggestion: None,
+            create_tag: None,
         }
     }
+
+    fn add_tag(mut self, tag: Tag, db: &Connection) -> Result<State> {
+        info!(
+            "Add tag {} to note {}.",
+            tag.name, self.note_tags_managing_data.note_data.note.name
+        );
+        self.note_tags_managing_data.note_data.add_tag(tag, db)?;
+        self.note_tags_managing_data.refresh_tag_counts(db)?;
+        Ok(State::NoteTagsManaging(self.note_tags_managing_data))
+    }
 }
 
 pub fn run_note_tag_adding_state(
@@ -31,6 +59,10 @@ pub fn run_note_tag_adding_state(
     key_event: KeyEvent,
     notebook: &Notebook,
 ) -> Result<State> {
+    if let Some(create) = state_data.create_tag.take() {
+        return run_tag_creating_prompt(state_data, create, key_event, notebook);
+    }
+
     Ok(match key_event.code {
         KeyCode::Esc => {
             info!(
@@ -39,31 +71,47 @@ pub fn run_note_tag_adding_state(
             );
             State::NoteTagsManaging(state_data.note_tags_managing_data)
         }
+        KeyCode::Tab | KeyCode::BackTab if !state_data.suggestions.is_empty() => {
+            let count = state_data.suggestions.len();
+            let suggestion = match (state_data.suggestion, key_event.code) {
+                (None, KeyCode::Tab) => 0,
+                (None, _) => count - 1,
+                (Some(i), KeyCode::Tab) => (i + 1) % count,
+                (Some(i), _) => (i + count - 1) % count,
+            };
+            state_data.suggestion = Some(suggestion);
+            state_data
+                .suggestions
+                .get(suggestion)
+                .expect("The suggestion index is in bounds")
+                .clone_into(&mut state_data.tag_name);
+            state_data.error = tag_error(state_data.tag_name.as_str(), notebook.db())?;
+
+            State::NoteTagAdding(state_data)
+        }
         KeyCode::Char(c) if !c.is_whitespace() => {
+            state_data.suggestion = None;
             state_data.tag_name.push(c);
-            state_data.valid = Tag::tag_exists(state_data.tag_name.as_str(), notebook.db())?;
+            state_data.error = tag_error(state_data.tag_name.as_str(), notebook.db())?;
 
             State::NoteTagAdding(state_data)
         }
         KeyCode::Backspace => {
+            state_data.suggestion = None;
             state_data.tag_name.pop();
-            state_data.valid = Tag::tag_exists(state_data.tag_name.as_str(), notebook.db())?;
+            state_data.error = tag_error(state_data.tag_name.as_str(), notebook.db())?;
 
             State::NoteTagAdding(state_data)
         }
         KeyCode::Enter => {
             if let Some(tag) = Tag::load_by_name(state_data.tag_name.as_str(), notebook.db())? {
-                info!(
-                    "Add tag {} to note {}.",
-                    tag.name, state_data.note_tags_managing_data.note_data.note.name
-                );
-                state_data
-                    .note_tags_managing_data
-                    .note_data
-                    .add_tag(tag, notebook.db())?;
-                State::NoteTagsManaging(state_data.note_tags_managing_data)
+                state_data.add_tag(tag, notebook.db())?
             } else {
-                state_data.valid = false;
+                state_data.error = tag_error(state_data.tag_name.as_str(), notebook.db())?;
+                // A missing tag is only created once confirmed
+                if matches!(state_data.error, Some(TagError::TagDoesNotExist { .. })) {
+                    state_data.create_tag = Some(false);
+                }
 
                 State::NoteTagAdding(state_data)
             }
@@ -72,12 +120,56 @@ pub fn run_note_tag_adding_state(
     })
 }
 
+fn run_tag_creating_prompt(
+    mut state_data: NoteTagAddingStateData,
+    create: bool,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    Ok(match key_event.code {
+        KeyCode::Enter if create => {
+            info!("Create tag {}.", state_data.tag_name);
+            let tag = Tag::new(state_data.tag_name.as_str(), notebook.db())?;
+            state_data.add_tag(tag, notebook.db())?
+        }
+        KeyCode::Enter | KeyCode::Esc => State::NoteTagAdding(state_data),
+        KeyCode::Tab => {
+            state_data.create_tag = Some(!create);
+            State::NoteTagAdding(state_data)
+        }
+        _ => {
+            state_data.create_tag = Some(create);
+            State::NoteTagAdding(state_data)
+        }
+    })
+}
+
+fn tag_error(name: &str, db: &Connection) -> Result<Option<TagError>> {
+    Ok(if name.is_empty() {
+        Some(TagError::EmptyName)
+    } else if Tag::tag_exists(name, db)? {
+        None
+    } else if Tag::load_trashed(name, db)?.is_some() {
+        Some(TagError::TagInTrash {
+            name: name.to_owned(),
+        })
+    } else {
+        Some(TagError::TagDoesNotExist {
+            name: name.to_owned(),
+        })
+    })
+}
+
 pub fn draw_note_tag_adding_state_data(
     NoteTagAddingStateData {
         note_tags_managing_data,
         tag_name,
-        valid,
+        error,
+        suggestions,
+        suggestion,
+        create_tag,
     }: &NoteTagAddingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -85,8 +177,55 @@ pub fn draw_note_tag_adding_state_data(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_note_tags_managing(frame, note_tags_managing_data, main_rect);
-            draw_text_prompt(frame, "Tag name", tag_name.as_str(), *valid, main_rect);
+            draw_note_tags_managing(frame, note_tags_managing_data, theme, main_rect);
+            let prompt_area = draw_text_prompt(
+                frame,
+                "Tag name",
+                tag_name.as_str(),
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
+
+            if !suggestions.is_empty() {
+                let suggestions_area = Rect {
+                    y: prompt_area.bottom(),
+                    height: 1,
+                    ..main_rect
+                }
+                .intersection(main_rect);
+                let suggestions_line = Line::from(
+                    [Span::raw("Tab to pick : ").add_modifier(Modifier::DIM)]
+                        .into_iter()
+                        .chain(suggestions.iter().enumerate().flat_map(|(i, term)| {
+                            let span = Span::raw(term.as_str());
+                            [
+                                if Some(i) == *suggestion {
+                                    span.style(theme.selection_style())
+                                } else {
+                                    span.fg(Color::Cyan)
+                                },
+                                Span::raw("  "),
+                            ]
+                        }))
+                        .collect::<Vec<_>>(),
+                );
+
+                frame.render_widget(Clear, suggestions_area);
+                frame.render_widget(
+                    Paragraph::new(suggestions_line).alignment(Alignment::Center),
+                    suggestions_area,
+                );
+            }
+
+            if let Some(create) = create_tag {
+                draw_yes_no_prompt(
+                    frame,
+                    *create,
+                    format!("Create the tag {tag_name} ?").as_str(),
+                    main_rect,
+                );
+            }
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -4,6 +4,7 @@ use log::info;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::Block;
 
+use crate::config::ThemeConfig;
 use crate::helpers::draw_yes_no_prompt;
 use crate::notebook::Notebook;
 use crate::states::note_tags_managing::{draw_note_tags_managing, NoteTagsManagingStateData};
@@ -58,6 +59,7 @@ pub fn run_note_tag_deleting_state(
                 note_tags_managing_data
                     .note_data
                     .remove_tag(&tag, notebook.db())?;
+                note_tags_managing_data.refresh_tag_counts(notebook.db())?;
 
                 State::NoteTagsManaging(note_tags_managing_data)
             } else {
@@ -80,6 +82,7 @@ pub fn draw_note_tag_deleting_state_data(
         note_tags_managing_data,
         delete,
     }: &NoteTagDeletingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -87,8 +90,20 @@ pub fn draw_note_tag_deleting_state_data(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_note_tags_managing(frame, note_tags_managing_data, main_rect);
-            draw_yes_no_prompt(frame, *delete, "Remove tag ?", main_rect);
+            draw_note_tags_managing(frame, note_tags_managing_data, theme, main_rect);
+            let last_note = note_tags_managing_data
+                .get_selected()
+                .is_some_and(|tag| note_tags_managing_data.tag_count(tag) <= 1);
+            draw_yes_no_prompt(
+                frame,
+                *delete,
+                if last_note {
+                    "Remove tag ? (last note with this tag)"
+                } else {
+                    "Remove tag ?"
+                },
+                main_rect,
+            );
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::Block;
+
+use rusqlite::Connection;
+
+use crate::config::ThemeConfig;
+use crate::helpers::{draw_text_prompt, DiscardResult};
+use crate::notebook::Notebook;
+use crate::states::note_tags_managing::{draw_note_tags_managing, NoteTagsManagingStateData};
+use crate::states::{State, Terminal};
+use crate::tag::{Tag, TagError};
+
+pub struct NoteTagReplacingStateData {
+    pub note_tags_managing_data: NoteTagsManagingStateData,
+    pub tag_name: String,
+    pub error: Option<TagError>,
+}
+
+impl NoteTagReplacingStateData {
+    pub fn empty(note_tags_managing_data: NoteTagsManagingStateData) -> Self {
+        NoteTagReplacingStateData {
+            note_tags_managing_data,
+            tag_name: String::new(),
+            error: Some(TagError::EmptyName),
+        }
+    }
+
+    fn validate(&self, db: &Connection) -> Result<Option<TagError>> {
+        let name = self.tag_name.as_str();
+        Ok(if name.is_empty() {
+            Some(TagError::EmptyName)
+        } else if self
+            .note_tags_managing_data
+            .note_data
+            .tags
+            .iter()
+            .any(|tag| tag.name == name)
+        {
+            Some(TagError::AlreadyTagged {
+                name: name.to_owned(),
+            })
+        } else if Tag::tag_exists(name, db)? {
+            None
+        } else {
+            Some(TagError::TagDoesNotExist {
+                name: name.to_owned(),
+            })
+        })
+    }
+}
+
+pub fn run_note_tag_replacing_state(
+    mut state_data: NoteTagReplacingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!(
+                "Cancel tag replacing in note {}.",
+                state_data.note_tags_managing_data.note_data.note.name
+            );
+            State::NoteTagsManaging(state_data.note_tags_managing_data)
+        }
+        KeyCode::Tab => {
+            if let Some(tag) = Tag::search_by_name(state_data.tag_name.as_str(), notebook.db())?
+                .into_iter()
+                .find(|tag| tag.name.starts_with(state_data.tag_name.as_str()))
+            {
+                state_data.tag_name = tag.name;
+            }
+            state_data.error = state_data.validate(notebook.db())?;
+
+            State::NoteTagReplacing(state_data)
+        }
+        KeyCode::Char(c) if !c.is_whitespace() => {
+            state_data.tag_name.push(c);
+            state_data.error = state_data.validate(notebook.db())?;
+
+            State::NoteTagReplacing(state_data)
+        }
+        KeyCode::Backspace => {
+            state_data.tag_name.pop();
+            state_data.error = state_data.validate(notebook.db())?;
+
+            State::NoteTagReplacing(state_data)
+        }
+        KeyCode::Enter => {
+            state_data.error = state_data.validate(notebook.db())?;
+            if state_data.error.is_some() {
+                return Ok(State::NoteTagReplacing(state_data));
+            }
+
+            let new_tag = Tag::load_by_name(state_data.tag_name.as_str(), notebook.db())?
+                .expect("The tag should exist once validated");
+            let mut note_tags_managing_data = state_data.note_tags_managing_data;
+            let old_tag = note_tags_managing_data
+                .get_selected()
+                .cloned()
+                .expect("A tag should be selected.");
+
+            info!(
+                "Replace tag {} with {} in note {}.",
+                old_tag.name, new_tag.name, note_tags_managing_data.note_data.note.name
+            );
+            note_tags_managing_data
+                .note_data
+                .replace_tag(&old_tag, new_tag, notebook.db())?;
+            note_tags_managing_data.refresh_tag_counts(notebook.db())?;
+
+            State::NoteTagsManaging(note_tags_managing_data)
+        }
+        _ => State::NoteTagReplacing(state_data),
+    })
+}
+
+pub fn draw_note_tag_replacing_state_data(
+    NoteTagReplacingStateData {
+        note_tags_managing_data,
+        tag_name,
+        error,
+    }: &NoteTagReplacingStateData,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_note_tags_managing(frame, note_tags_managing_data, theme, main_rect);
+            draw_text_prompt(
+                frame,
+                format!(
+                    "Replace {}",
+                    note_tags_managing_data
+                        .get_selected()
+                        .map_or("", |tag| tag.name.as_str())
+                )
+                .as_str(),
+                tag_name.as_str(),
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
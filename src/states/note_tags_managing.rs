@@ -1,18 +1,24 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use log::info;
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::{Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, BorderType, Borders, List, ListState, Padding, Paragraph};
 use ratatui::Frame;
 
+use rusqlite::Connection;
+
+use crate::config::ThemeConfig;
 use crate::helpers::{DiscardResult, TryFromDatabase};
 use crate::note::NoteData;
 use crate::notebook::Notebook;
 use crate::states::note_tag_adding::NoteTagAddingStateData;
 use crate::states::note_tag_deleting::NoteTagDeletingStateData;
+use crate::states::note_tag_replacing::NoteTagReplacingStateData;
 use crate::states::note_viewing::NoteViewingStateData;
 use crate::states::{State, Terminal};
 use crate::tag::Tag;
@@ -22,14 +28,18 @@ use super::tag_notes_listing::TagNotesListingStateData;
 pub struct NoteTagsManagingStateData {
     pub note_data: NoteData,
     pub selected: usize,
+    pub tag_counts: HashMap<i64, usize>,
 }
 
-impl From<NoteData> for NoteTagsManagingStateData {
-    fn from(note_data: NoteData) -> Self {
-        NoteTagsManagingStateData {
+impl TryFromDatabase<NoteData> for NoteTagsManagingStateData {
+    fn try_from_database(note_data: NoteData, db: &Connection) -> Result<Self> {
+        let mut state_data = NoteTagsManagingStateData {
             note_data,
             selected: 0,
-        }
+            tag_counts: HashMap::new(),
+        };
+        state_data.refresh_tag_counts(db)?;
+        Ok(state_data)
     }
 }
 
@@ -37,6 +47,16 @@ impl NoteTagsManagingStateData {
     pub fn get_selected(&self) -> Option<&Tag> {
         self.note_data.tags.get(self.selected)
     }
+
+    pub fn tag_count(&self, tag: &Tag) -> usize {
+        self.tag_counts.get(&tag.id).copied().unwrap_or_default()
+    }
+
+    pub fn refresh_tag_counts(&mut self, db: &Connection) -> Result<()> {
+        let ids: Vec<i64> = self.note_data.tags.iter().map(|tag| tag.id).collect();
+        self.tag_counts = Tag::count_notes(&ids, db)?;
+        Ok(())
+    }
 }
 
 pub fn run_note_tags_managing_state(
@@ -50,7 +70,10 @@ pub fn run_note_tags_managing_state(
                 "Cancel note {} tags managing.",
                 state_data.note_data.note.name
             );
-            State::NoteViewing(NoteViewingStateData::from(state_data.note_data))
+            State::NoteViewing(
+                NoteViewingStateData::from(state_data.note_data)
+                    .with_default_view(&notebook.settings()),
+            )
         }
         KeyCode::Char('d') if !state_data.note_data.tags.is_empty() => {
             info!(
@@ -63,12 +86,23 @@ pub fn run_note_tags_managing_state(
             );
             State::NoteTagDeleting(NoteTagDeletingStateData::empty(state_data))
         }
+        KeyCode::Char('s') if !state_data.note_data.tags.is_empty() => {
+            info!(
+                "Open note {} tag {} replacing prompt.",
+                state_data.note_data.note.name,
+                state_data
+                    .get_selected()
+                    .expect("A tag should be selected.")
+                    .name
+            );
+            State::NoteTagReplacing(NoteTagReplacingStateData::empty(state_data))
+        }
         KeyCode::Char('a') => {
             info!(
                 "Open note {} tag adding prompt.",
                 state_data.note_data.note.name
             );
-            State::NoteTagAdding(NoteTagAddingStateData::empty(state_data))
+            State::NoteTagAdding(NoteTagAddingStateData::empty(state_data, notebook))
         }
         KeyCode::Enter if !state_data.note_data.tags.is_empty() => {
             info!(
@@ -103,6 +137,7 @@ pub fn run_note_tags_managing_state(
 
 pub fn draw_note_tags_managing_state(
     data: &NoteTagsManagingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -110,7 +145,7 @@ pub fn draw_note_tags_managing_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_note_tags_managing(frame, data, main_rect);
+            draw_note_tags_managing(frame, data, theme, main_rect);
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -119,12 +154,16 @@ pub fn draw_note_tags_managing_state(
 
 pub fn draw_note_tags_managing(
     frame: &mut Frame,
-    NoteTagsManagingStateData {
-        note_data,
-        selected,
-    }: &NoteTagsManagingStateData,
+    state_data: &NoteTagsManagingStateData,
+    theme: &ThemeConfig,
     main_rect: Rect,
 ) {
+    let NoteTagsManagingStateData {
+        note_data,
+        selected,
+        ..
+    } = state_data;
+
     let vertical_layout = Layout::new(
         Direction::Vertical,
         [Constraint::Length(5), Constraint::Min(0)],
@@ -143,21 +182,30 @@ pub fn draw_note_tags_managing(
             .padding(Padding::uniform(1)),
     );
 
-    let note_tags = List::new(
-        note_data
-            .tags
-            .iter()
-            .map(|tag| Span::raw(tag.name.as_str())),
-    )
-    .highlight_symbol(">> ")
-    .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
-    .block(
-        Block::new()
-            .title("Note Tags")
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded)
-            .border_style(Style::default().fg(Color::Yellow)),
-    );
+    let tags_block = Block::new()
+        .title("Note Tags")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Yellow));
+    let tags_width = tags_block.inner(vertical_layout[1]).width.saturating_sub(3) as usize;
+
+    let note_tags = List::new(note_data.tags.iter().map(|tag| {
+        let mut line = Line::from(vec![Span::raw(tag.name.as_str())]);
+
+        let count = state_data.tag_count(tag);
+        let badge = Span::raw(format!("{count} note{}", if count == 1 { "" } else { "s" }))
+            .add_modifier(Modifier::DIM);
+        let gap = tags_width
+            .saturating_sub(line.width() + badge.width())
+            .max(1);
+        line.spans.push(Span::raw(" ".repeat(gap)));
+        line.spans.push(badge);
+
+        line
+    }))
+    .highlight_symbol(theme.highlight_symbol.as_str())
+    .highlight_style(theme.selection_style())
+    .block(tags_block);
 
     frame.render_widget(note_name, vertical_layout[0]);
     frame.render_stateful_widget(
@@ -1,40 +1,110 @@
+use std::collections::HashMap;
+use std::fmt::Write;
 use std::io::stdout;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use std::{env, fs};
 
 use anyhow::Result;
-use log::info;
+use chrono::Local;
+use log::{info, warn};
 use rusqlite::Connection;
 use scopeguard::defer;
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::ExecutableCommand;
 use ratatui::prelude::{Alignment, Constraint, Direction, Layout, Margin, Rect};
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::Text;
+use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, BorderType, Borders, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation,
+    Block, BorderType, Borders, Clear, Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation,
     ScrollbarState, Table,
 };
 use ratatui::Frame;
 
-use crate::helpers::{DiscardResult, TryFromDatabase};
-use crate::links::Link;
-use crate::markdown::elements::{InlineElements, SelectableInlineElements};
+use crate::clipboard::copy_to_clipboard;
+use crate::config::{ConfigError, EditingConfig, LayoutConfig};
+use crate::helpers::{create_popup_proportion, draw_yes_no_prompt, DiscardResult, TryFromDatabase};
+use crate::links::{Link, LinksDiff};
+use crate::markdown::elements::{InlineElements, RenderedBlock, SelectableInlineElements};
 use crate::markdown::{combine, lines, parse, ParsedMarkdown};
-use crate::note::{Note, NoteData};
+use crate::note::{Note, NoteData, NoteError};
 use crate::notebook::Notebook;
+use crate::settings::{NotebookSettings, ENTRY_TIMESTAMP_FORMAT};
+use crate::states::note_attachments::NoteAttachmentsStateData;
 use crate::states::note_deleting::NoteDeletingStateData;
+use crate::states::note_editing::NoteEditingStateData;
+use crate::states::note_forking::NoteForkingStateData;
+use crate::states::note_links::NoteLinksStateData;
 use crate::states::note_renaming::NoteRenamingStateData;
 use crate::states::note_tags_managing::NoteTagsManagingStateData;
 use crate::states::notes_managing::NotesManagingStateData;
 use crate::states::{State, Terminal};
+use crate::tag::Tag;
+
+const HORIZONTAL_SCROLL_STEP: usize = 4;
+
+const PREVIEW_DELAY: Duration = Duration::from_millis(300);
+const PREVIEW_LINES: usize = 10;
+const PREVIEW_WIDTH: u16 = 60;
+const PREVIEW_CACHE_SIZE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Full,
+    Zen,
+    Focus,
+}
+
+#[derive(Clone, Copy)]
+pub struct ViewPreferences {
+    pub raw: bool,
+    pub mode: ViewMode,
+    pub wrap: bool,
+}
+
+impl Default for ViewPreferences {
+    fn default() -> Self {
+        ViewPreferences {
+            raw: false,
+            mode: ViewMode::Full,
+            wrap: true,
+        }
+    }
+}
+
+impl From<&NotebookSettings> for ViewPreferences {
+    fn from(settings: &NotebookSettings) -> Self {
+        ViewPreferences {
+            raw: settings.default_raw_view(),
+            ..ViewPreferences::default()
+        }
+    }
+}
 
 pub struct NoteViewingStateData {
     pub note_data: NoteData,
     pub parsed_content: ParsedMarkdown,
     pub selected: (usize, usize),
+    pub view: ViewPreferences,
+    pub raw_scroll: usize,
+    pub horizontal_scroll: usize,
+    pub status: Option<String>,
+    pub preview: Option<LinkPreview>,
+    /// The file keeping the edits of an editor session that couldn't be saved.
+    pub unsaved_edits: Option<PathBuf>,
+    pub quitting: Option<bool>,
+    hover_since: Instant,
+    // Missing notes are cached too, as they are looked up on every tick
+    preview_cache: HashMap<String, Option<Rc<ParsedMarkdown>>>,
+}
+
+pub struct LinkPreview {
+    pub name: String,
+    pub content: Rc<ParsedMarkdown>,
 }
 
 impl From<NoteData> for NoteViewingStateData {
@@ -45,6 +115,15 @@ impl From<NoteData> for NoteViewingStateData {
             note_data,
             parsed_content,
             selected: (0, 0),
+            view: ViewPreferences::default(),
+            raw_scroll: 0,
+            horizontal_scroll: 0,
+            status: None,
+            preview: None,
+            unsaved_edits: None,
+            quitting: None,
+            hover_since: Instant::now(),
+            preview_cache: HashMap::new(),
         }
     }
 }
@@ -58,50 +137,137 @@ impl TryFromDatabase<Note> for NoteViewingStateData {
 }
 
 impl NoteViewingStateData {
+    pub fn with_default_view(self, settings: &NotebookSettings) -> Self {
+        NoteViewingStateData {
+            view: ViewPreferences::from(settings),
+            ..self
+        }
+    }
+
     fn re_parse_content(&mut self) {
-        self.parsed_content = parse(self.note_data.note.content.as_str());
+        self.parsed_content
+            .update(self.note_data.note.content.as_str());
     }
     fn get_current(&self) -> Option<&SelectableInlineElements> {
         self.parsed_content.get_element(self.selected)
     }
+    fn status_line(&self) -> Option<Line<'_>> {
+        if let Some(status) = &self.status {
+            return Some(Line::from(vec![Span::styled(
+                status.as_str(),
+                Style::default().fg(Color::Yellow),
+            )]));
+        }
+        if self.view.raw {
+            return None;
+        }
+
+        match self.get_current().map(<&InlineElements>::from) {
+            Some(
+                InlineElements::HyperLink { dest, .. }
+                | InlineElements::CrossRef {
+                    dest,
+                    heading: None,
+                    ..
+                },
+            ) => Some(Line::from(vec![Span::styled(
+                format!("→ {dest}"),
+                Style::default().add_modifier(Modifier::DIM),
+            )])),
+            Some(InlineElements::CrossRef {
+                dest,
+                heading: Some(heading),
+                ..
+            }) => Some(Line::from(vec![Span::styled(
+                format!("→ {dest}#{heading}"),
+                Style::default().add_modifier(Modifier::DIM),
+            )])),
+            _ => None,
+        }
+    }
     fn select_current(&mut self, selected: bool) {
         self.parsed_content.select(self.selected, selected);
     }
-
-    fn compute_links(&self) -> Vec<Link> {
-        self.parsed_content
-            .list_links()
-            .into_iter()
-            .map(|to| Link {
-                from: self.note_data.note.id,
-                to: to.to_string(),
-            })
-            .collect()
+    fn select_block(&mut self, block: usize) {
+        self.select_current(false);
+        self.selected = (0, block);
+        self.select_current(true);
     }
-    fn update_links(&mut self, db: &Connection) -> Result<()> {
-        let computed_links = self.compute_links();
-
-        let removed: Vec<Link> = self
-            .note_data
-            .links
+    fn raw_line_count(&self) -> usize {
+        self.note_data.note.content.lines().count()
+    }
+    fn max_line_width(&self) -> usize {
+        let rendered_content = if self.view.raw {
+            render_raw_lines(self.note_data.note.content.as_str(), usize::MAX)
+        } else {
+            self.parsed_content.render_blocks(usize::MAX)
+        };
+        rendered_content
             .iter()
-            .filter(|link| !computed_links.contains(link))
-            .cloned()
-            .collect();
+            .flat_map(|block| block.iter())
+            .map(Line::width)
+            .max()
+            .unwrap_or_default()
+    }
 
-        for link in removed {
-            self.note_data.remove_link(link.to.as_str(), db)?;
+    /// Preview the note referenced by the selection once it rested on it long enough.
+    pub fn update_preview(&mut self, db: &Connection) -> Result<()> {
+        if self.preview.is_some() || self.view.raw || self.hover_since.elapsed() < PREVIEW_DELAY {
+            return Ok(());
         }
+        let Some(InlineElements::CrossRef { dest, .. }) =
+            self.get_current().map(<&InlineElements>::from)
+        else {
+            return Ok(());
+        };
 
-        let added: Vec<Link> = computed_links
-            .into_iter()
-            .filter(|link| !self.note_data.links.contains(link))
-            .collect();
+        let name = Note::normalize_name(dest);
+        if !self.preview_cache.contains_key(&name) {
+            if self.preview_cache.len() >= PREVIEW_CACHE_SIZE {
+                self.preview_cache.clear();
+            }
+            let content = Note::load_by_name(name.as_str(), db)?
+                .map(|note| Rc::new(parse(note.content.as_str())));
+            self.preview_cache.insert(name.clone(), content);
+        }
+
+        self.preview = self.preview_cache[&name]
+            .clone()
+            .map(|content| LinkPreview { name, content });
+        Ok(())
+    }
+
+    fn update_links(&mut self, db: &Connection) -> Result<LinksDiff> {
+        let computed_links = Link::from_markdown(self.note_data.note.id, &self.parsed_content);
+        self.note_data.update_links(&computed_links, db)
+    }
+
+    fn append_entry(&mut self, heading: &str, db: &Connection) -> Result<()> {
+        let content = &mut self.note_data.note.content;
+        content.truncate(content.trim_end().len());
+        if !content.is_empty() {
+            content.push_str("\n\n");
+        }
+        content.push_str(format!("## {heading}\n\n").as_str());
+        self.note_data.note.update(db)?;
 
-        for link in added {
-            self.note_data.add_link(link.to.as_str(), db)?;
+        self.refresh_content(db)?;
+        if let Some(last) = self.parsed_content.block_count().checked_sub(1) {
+            self.select_block(last);
         }
+        Ok(())
+    }
 
+    pub fn refresh_content(&mut self, db: &Connection) -> Result<()> {
+        self.select_current(false);
+        self.re_parse_content();
+        let diff = self.update_links(db)?;
+        if !diff.is_empty() {
+            self.status = Some(diff.to_string());
+        }
+        self.selected = (0, 0);
+        self.select_current(true);
+        self.raw_scroll = self.raw_scroll.min(self.raw_line_count().saturating_sub(1));
         Ok(())
     }
 }
@@ -110,53 +276,288 @@ pub fn run_note_viewing_state(
     mut state_data: NoteViewingStateData,
     key_event: KeyEvent,
     notebook: &Notebook,
+    editing: &EditingConfig,
     force_redraw: &mut bool,
 ) -> Result<State> {
+    if let Some(quit) = state_data.quitting {
+        return Ok(match key_event.code {
+            KeyCode::Tab => {
+                state_data.quitting = Some(!quit);
+                State::NoteViewing(state_data)
+            }
+            KeyCode::Enter if quit => {
+                info!("Quit foucault without saving the edits.");
+                State::Exit
+            }
+            KeyCode::Enter | KeyCode::Esc => {
+                state_data.quitting = None;
+                State::NoteViewing(state_data)
+            }
+            _ => State::NoteViewing(state_data),
+        });
+    }
+
+    state_data.status = None;
+    state_data.preview = None;
+    state_data.hover_since = Instant::now();
+
     Ok(match key_event.code {
         KeyCode::Esc => {
             info!("Stop viewing of note {}.", state_data.note_data.note.name);
             State::Nothing
         }
+        KeyCode::Char('q') if state_data.unsaved_edits.is_some() && editing.confirm_quit => {
+            state_data.quitting = Some(false);
+            State::NoteViewing(state_data)
+        }
         KeyCode::Char('q') => {
             info!("Quit foucault.");
             State::Exit
         }
         KeyCode::Char('e') => {
             info!("Edit note {}", state_data.note_data.note.name);
-            edit_note(&mut state_data.note_data.note, notebook)?;
-
-            state_data.re_parse_content();
-            state_data.update_links(notebook.db())?;
-            state_data.selected = (0, 0);
-            state_data.select_current(true);
+            match edit_note(
+                &mut state_data.note_data.note,
+                state_data.unsaved_edits.as_deref(),
+                notebook,
+                editing,
+            ) {
+                Ok(()) => {
+                    state_data.unsaved_edits = None;
+                    state_data.refresh_content(notebook.db())?;
+                }
+                Err(err) => match err.downcast_ref::<NoteError>() {
+                    Some(NoteError::InvalidEncoding { file }) => {
+                        warn!(
+                            "Edits of note {} aren't valid UTF-8.",
+                            state_data.note_data.note.name
+                        );
+                        state_data.status = Some(format!(
+                            "Edited file is not valid UTF-8, changes not saved (kept in {}).",
+                            file.display()
+                        ));
+                        state_data.unsaved_edits = Some(file.clone());
+                    }
+                    _ => return Err(err),
+                },
+            }
             *force_redraw = true;
 
             State::NoteViewing(state_data)
         }
+        KeyCode::Char('I') => {
+            info!("Edit note {} inline.", state_data.note_data.note.name);
+            State::NoteEditing(NoteEditingStateData::from(state_data))
+        }
+        KeyCode::Char('a') => {
+            let now = Local::now();
+            let mut heading = String::new();
+            if write!(
+                heading,
+                "{}",
+                now.format(notebook.settings().entry_timestamp_format())
+            )
+            .is_err()
+            {
+                warn!("The entry timestamp format is invalid, use the default one.");
+                heading = now.format(ENTRY_TIMESTAMP_FORMAT.default).to_string();
+            }
+
+            info!(
+                "Append entry {heading} to note {}.",
+                state_data.note_data.note.name
+            );
+            state_data.append_entry(heading.as_str(), notebook.db())?;
+
+            let mut editing_data = NoteEditingStateData::from(state_data);
+            editing_data.text.move_to_end();
+            State::NoteEditing(editing_data)
+        }
+        KeyCode::Char('z') => {
+            state_data.view.mode = if state_data.view.mode == ViewMode::Zen {
+                ViewMode::Full
+            } else {
+                ViewMode::Zen
+            };
+            info!(
+                "Toggle zen mode of note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('f') if !state_data.view.raw => {
+            state_data.view.mode = if state_data.view.mode == ViewMode::Focus {
+                ViewMode::Full
+            } else {
+                ViewMode::Focus
+            };
+            info!(
+                "Toggle focus mode of note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('y')
+            if key_event.modifiers.contains(KeyModifiers::CONTROL) && !state_data.view.raw =>
+        {
+            if let Some(text) = state_data.parsed_content.block_text(state_data.selected.1) {
+                state_data.status = Some(match copy_to_clipboard(text.as_str()) {
+                    Ok(()) => format!("Copied {} line(s)", text.lines().count()),
+                    Err(err) => format!("Unable to copy : {err}"),
+                });
+            }
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            info!(
+                "Pick a note to view beside note {}.",
+                state_data.note_data.note.name
+            );
+            State::NotesManaging(NotesManagingStateData::beside(state_data, notebook)?)
+        }
+        KeyCode::Char('v') => {
+            state_data.view.raw = !state_data.view.raw;
+            info!(
+                "Switch note {} to {} view.",
+                state_data.note_data.note.name,
+                if state_data.view.raw {
+                    "raw"
+                } else {
+                    "rendered"
+                }
+            );
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('w') => {
+            state_data.view.wrap = !state_data.view.wrap;
+            state_data.horizontal_scroll = 0;
+            info!(
+                "Toggle line wrapping of note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('H') if !state_data.view.wrap => {
+            state_data.horizontal_scroll = state_data
+                .horizontal_scroll
+                .saturating_sub(HORIZONTAL_SCROLL_STEP);
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('L') if !state_data.view.wrap => {
+            state_data.horizontal_scroll = (state_data.horizontal_scroll + HORIZONTAL_SCROLL_STEP)
+                .min(state_data.max_line_width().saturating_sub(1));
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Up | KeyCode::Char('k') if state_data.view.raw => {
+            state_data.raw_scroll = state_data.raw_scroll.saturating_sub(1);
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Down | KeyCode::Char('j') if state_data.view.raw => {
+            state_data.raw_scroll =
+                (state_data.raw_scroll + 1).min(state_data.raw_line_count().saturating_sub(1));
+            State::NoteViewing(state_data)
+        }
+        KeyCode::PageUp if state_data.view.raw => {
+            state_data.raw_scroll = state_data
+                .raw_scroll
+                .saturating_sub(notebook.settings().raw_page_length());
+            State::NoteViewing(state_data)
+        }
+        KeyCode::PageDown if state_data.view.raw => {
+            state_data.raw_scroll = (state_data.raw_scroll + notebook.settings().raw_page_length())
+                .min(state_data.raw_line_count().saturating_sub(1));
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('g') if state_data.view.raw => {
+            state_data.raw_scroll = 0;
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Char('E') if state_data.view.raw => {
+            state_data.raw_scroll = state_data.raw_line_count().saturating_sub(1);
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Enter | KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l')
+            if state_data.view.raw =>
+        {
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Enter
+        | KeyCode::Up
+        | KeyCode::Down
+        | KeyCode::Left
+        | KeyCode::Right
+        | KeyCode::Char('h' | 'j' | 'k' | 'l' | 'g' | 'E')
+            if state_data.parsed_content.is_empty() =>
+        {
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Up if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            let (start, _) = state_data
+                .parsed_content
+                .section_bounds(state_data.selected.1);
+            if let Some(previous) = start.checked_sub(1) {
+                let (previous_start, _) = state_data.parsed_content.section_bounds(previous);
+                state_data.select_block(previous_start);
+            }
+            State::NoteViewing(state_data)
+        }
+        KeyCode::Down if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            let (_, end) = state_data
+                .parsed_content
+                .section_bounds(state_data.selected.1);
+            if end < state_data.parsed_content.block_count() {
+                state_data.select_block(end);
+            }
+            State::NoteViewing(state_data)
+        }
         KeyCode::Char('s') => {
             info!("Enter notes listing.");
-            State::NotesManaging(NotesManagingStateData::empty(notebook.db())?)
+            State::NotesManaging(NotesManagingStateData::empty(notebook)?)
         }
         KeyCode::Char('d') => {
             info!(
                 "Open deleting prompt for note {}.",
                 state_data.note_data.note.name
             );
-            State::NoteDeleting(NoteDeletingStateData::empty(state_data))
+            State::NoteDeleting(NoteDeletingStateData::empty(state_data, notebook)?)
         }
         KeyCode::Char('r') => {
             info!(
                 "Open renaming prompt for note {}.",
                 state_data.note_data.note.name
             );
-            State::NoteRenaming(NoteRenamingStateData::empty(state_data))
+            State::NoteRenaming(NoteRenamingStateData::empty(state_data, notebook)?)
+        }
+        KeyCode::Char('c') => {
+            info!(
+                "Open forking prompt for note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteForking(NoteForkingStateData::new(state_data, notebook)?)
+        }
+        KeyCode::Char('b') => {
+            info!(
+                "Open links report of note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteLinks(NoteLinksStateData::new(state_data, notebook)?)
+        }
+        KeyCode::Char('F') => {
+            info!(
+                "Open attachments of note {}.",
+                state_data.note_data.note.name
+            );
+            State::NoteAttachments(NoteAttachmentsStateData::new(state_data, notebook)?)
         }
         KeyCode::Char('t') => {
             info!(
                 "Open tags manager for note {}",
                 state_data.note_data.note.name
             );
-            State::NoteTagsManaging(NoteTagsManagingStateData::from(state_data.note_data))
+            State::NoteTagsManaging(NoteTagsManagingStateData::try_from_database(
+                state_data.note_data,
+                notebook.db(),
+            )?)
         }
         KeyCode::Enter => {
             info!("Try to trigger element action.");
@@ -166,12 +567,18 @@ pub fn run_note_viewing_state(
                         opener::open(dest.as_str())?;
                         State::NoteViewing(state_data)
                     }
-                    InlineElements::CrossRef { dest, .. } => {
+                    InlineElements::CrossRef { dest, heading, .. } => {
                         if let Some(note) = Note::load_by_name(dest.as_str(), notebook.db())? {
-                            State::NoteViewing(NoteViewingStateData::try_from_database(
-                                note,
-                                notebook.db(),
-                            )?)
+                            let mut new_state_data =
+                                NoteViewingStateData::try_from_database(note, notebook.db())?;
+                            new_state_data.view = state_data.view;
+                            if let Some(index) = heading.as_deref().and_then(|heading| {
+                                new_state_data.parsed_content.header_index(heading)
+                            }) {
+                                info!("Jump to heading {index}.");
+                                new_state_data.select_block(index);
+                            }
+                            State::NoteViewing(new_state_data)
                         } else {
                             State::NoteViewing(state_data)
                         }
@@ -228,9 +635,7 @@ pub fn run_note_viewing_state(
             State::NoteViewing(state_data)
         }
         KeyCode::Char('g') => {
-            state_data.select_current(false);
-            state_data.selected = (0, 0);
-            state_data.select_current(true);
+            state_data.select_block(0);
             State::NoteViewing(state_data)
         }
         KeyCode::Char('E') => {
@@ -247,14 +652,37 @@ pub fn run_note_viewing_state(
     })
 }
 
-fn edit_note(note: &mut Note, notebook: &Notebook) -> Result<()> {
-    let tmp_file_path = notebook
-        .dir()
-        .unwrap()
-        .join(format!("{}.tmp.md", note.name));
-    note.export_content(tmp_file_path.as_path())?;
+/// Reopen `unsaved_edits` when it still exists, rather than exporting the note over it.
+fn edit_note(
+    note: &mut Note,
+    unsaved_edits: Option<&Path>,
+    notebook: &Notebook,
+    editing: &EditingConfig,
+) -> Result<()> {
+    let tmp_file_path = match unsaved_edits {
+        Some(file) if file.exists() => {
+            info!("Reopen the unsaved edits kept in {}.", file.display());
+            file.to_path_buf()
+        }
+        _ => {
+            let tmp_file_name = format!("{}.tmp.md", note.name);
+            let mut tmp_file_path = notebook.dir().unwrap().join(tmp_file_name.as_str());
+            if note.export_content(tmp_file_path.as_path()).is_err() {
+                warn!(
+                    "The notebook directory isn't writable, edit the note from the temp directory."
+                );
+                tmp_file_path = env::temp_dir().join(tmp_file_name);
+                note.export_content(tmp_file_path.as_path())?;
+            }
+            tmp_file_path
+        }
+    };
 
-    let editor = env::var("EDITOR")?;
+    let editor = match notebook.settings().editor() {
+        Some(editor) => editor.to_owned(),
+        None if !editing.editor.trim().is_empty() => editing.editor.clone(),
+        None => return Err(ConfigError::NoEditor.into()),
+    };
 
     stdout()
         .execute(LeaveAlternateScreen)
@@ -278,51 +706,302 @@ fn edit_note(note: &mut Note, notebook: &Notebook) -> Result<()> {
 
 pub fn draw_note_viewing_state(
     state_data: &NoteViewingStateData,
+    layout: LayoutConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
     terminal
         .draw(|frame| {
-            let main_rect = main_frame.inner(frame.size());
+            let main_rect = if state_data.view.mode == ViewMode::Focus && !state_data.view.raw {
+                draw_focused_note(frame, state_data, frame.size());
+                frame.size()
+            } else if state_data.view.mode == ViewMode::Zen {
+                draw_zen_note(frame, state_data, frame.size());
+                frame.size()
+            } else {
+                let main_rect = main_frame.inner(frame.size());
+
+                draw_viewed_note(frame, state_data, layout, main_rect);
 
-            draw_viewed_note(frame, state_data, main_rect);
+                frame.render_widget(main_frame, frame.size());
+                main_rect
+            };
+
+            if let Some(preview) = &state_data.preview {
+                draw_link_preview(frame, preview, main_rect);
+            }
 
-            frame.render_widget(main_frame, frame.size());
+            if let Some(quit) = state_data.quitting {
+                draw_yes_no_prompt(frame, quit, "Quit without saving the edits ?", main_rect);
+            }
         })
         .discard_result()
 }
 
 pub fn draw_viewed_note(
     frame: &mut Frame,
-    NoteViewingStateData {
+    state_data: &NoteViewingStateData,
+    layout: LayoutConfig,
+    main_rect: Rect,
+) {
+    let NoteViewingStateData {
         note_data: NoteData { note, tags, .. },
-        parsed_content,
-        selected,
-    }: &NoteViewingStateData,
+        view,
+        ..
+    } = state_data;
+    let status_line = state_data.status_line();
+
+    let vertical_layout = Layout::new(
+        Direction::Vertical,
+        [
+            Constraint::Length(1 + layout.frame_size()),
+            Constraint::Min(0),
+            Constraint::Length(u16::from(status_line.is_some())),
+        ],
+    )
+    .split(main_rect);
+
+    draw_note_header(frame, note, tags, layout, vertical_layout[0]);
+
+    let content_block = layout
+        .block()
+        .title(if view.raw { "Source" } else { "Content" })
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let content_area = content_block.inner(vertical_layout[1]);
+    let (note_content, position, length) = build_content(state_data, content_area.width);
+
+    let content_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(Some("↑"))
+        .end_symbol(Some("↓"));
+
+    frame.render_widget(note_content, content_area);
+    frame.render_widget(content_block, vertical_layout[1]);
+    frame.render_stateful_widget(
+        content_scrollbar,
+        vertical_layout[1].inner(&Margin::new(0, 1)),
+        &mut ScrollbarState::default()
+            .content_length(length.saturating_sub(1))
+            .viewport_content_length(1)
+            .position(position),
+    );
+
+    if let Some(status_line) = status_line {
+        frame.render_widget(Paragraph::new(status_line), vertical_layout[2]);
+    }
+}
+
+fn draw_link_preview(
+    frame: &mut Frame,
+    LinkPreview { name, content }: &LinkPreview,
     main_rect: Rect,
 ) {
+    let width = main_rect.width.saturating_sub(4).min(PREVIEW_WIDTH);
+    let preview_lines: Vec<Line> = if content.is_empty() {
+        vec![Line::from(vec![Span::styled(
+            "Empty note",
+            Style::default().add_modifier(Modifier::DIM),
+        )])]
+    } else {
+        combine(&content.render_blocks(width.saturating_sub(2) as usize))
+            .iter()
+            .take(PREVIEW_LINES)
+            .cloned()
+            .collect()
+    };
+
+    let height = (u16::try_from(preview_lines.len()).unwrap_or(u16::MAX) + 2)
+        .min(main_rect.height.saturating_sub(2));
+    let preview_area = Rect {
+        x: main_rect.x + (main_rect.width - width) / 2,
+        y: main_rect.bottom().saturating_sub(height + 2),
+        width,
+        height,
+    };
+
+    let preview = Paragraph::new(preview_lines).block(
+        Block::new()
+            .title(name.as_str())
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+
+    frame.render_widget(Clear, preview_area);
+    frame.render_widget(preview, preview_area);
+}
+
+fn draw_zen_note(frame: &mut Frame, state_data: &NoteViewingStateData, main_rect: Rect) {
+    let status_line = state_data.status_line();
     let vertical_layout = Layout::new(
         Direction::Vertical,
-        [Constraint::Length(5), Constraint::Min(0)],
+        [
+            Constraint::Min(0),
+            Constraint::Length(u16::from(status_line.is_some())),
+        ],
     )
     .split(main_rect);
+
+    let content_area = create_popup_proportion((60, 90), vertical_layout[0]);
+    let (note_content, _, _) = build_content(state_data, content_area.width);
+
+    frame.render_widget(note_content, content_area);
+
+    if let Some(status_line) = status_line {
+        frame.render_widget(
+            Paragraph::new(status_line).alignment(Alignment::Center),
+            vertical_layout[1],
+        );
+    }
+}
+
+fn draw_focused_note(frame: &mut Frame, state_data: &NoteViewingStateData, main_rect: Rect) {
+    let status_line = state_data.status_line();
+    let vertical_layout = Layout::new(
+        Direction::Vertical,
+        [
+            Constraint::Min(0),
+            Constraint::Length(u16::from(status_line.is_some())),
+        ],
+    )
+    .split(main_rect);
+
+    let section_block = Block::new().padding(Padding::new(4, 4, 2, 2));
+    let section_area = section_block.inner(create_popup_proportion((70, 90), vertical_layout[0]));
+
+    frame.render_widget(build_section(state_data, section_area), section_area);
+
+    if let Some(status_line) = status_line {
+        frame.render_widget(
+            Paragraph::new(status_line).alignment(Alignment::Center),
+            vertical_layout[1],
+        );
+    }
+}
+
+fn build_section(state_data: &NoteViewingStateData, area: Rect) -> Paragraph<'_> {
+    let NoteViewingStateData {
+        parsed_content,
+        selected,
+        view,
+        horizontal_scroll,
+        ..
+    } = state_data;
+
+    if parsed_content.is_empty() {
+        return build_content(state_data, area.width).0;
+    }
+
+    let max_len = if view.wrap {
+        area.width as usize
+    } else {
+        usize::MAX
+    };
+    let (start, end) = parsed_content.section_bounds(selected.1);
+    let rendered_section = &parsed_content.render_blocks(max_len)[start..end];
+
+    // Short sections are centered, longer ones scroll to the selected block.
+    let section_length = lines(rendered_section);
+    let height = area.height as usize;
+    let (padding, scroll) = if section_length < height {
+        ((height - section_length) / 2, 0)
+    } else {
+        (0, lines(&rendered_section[..selected.1 - start]))
+    };
+
+    let mut section = vec![Line::default(); padding];
+    section.extend(combine(rendered_section).iter().cloned());
+
+    RenderedBlock::from(section).build_paragraph().scroll((
+        scroll.try_into().unwrap(),
+        if view.wrap {
+            0
+        } else {
+            (*horizontal_scroll).try_into().unwrap_or(u16::MAX)
+        },
+    ))
+}
+
+fn build_content(
+    NoteViewingStateData {
+        note_data: NoteData { note, .. },
+        parsed_content,
+        selected,
+        view,
+        raw_scroll,
+        horizontal_scroll,
+        ..
+    }: &NoteViewingStateData,
+    width: u16,
+) -> (Paragraph<'_>, usize, usize) {
+    let max_len = if view.wrap {
+        width as usize
+    } else {
+        usize::MAX
+    };
+
+    if !view.raw && parsed_content.is_empty() {
+        return (
+            Paragraph::new(Line::from(vec![Span::styled(
+                "Empty note — press e to edit",
+                Style::default().add_modifier(Modifier::DIM),
+            )]))
+            .alignment(Alignment::Center),
+            0,
+            0,
+        );
+    }
+
+    let (rendered_content, position, length) = if view.raw {
+        let source_lines = note.content.lines().count();
+        (
+            render_raw_lines(note.content.as_str(), max_len),
+            (*raw_scroll).min(source_lines.saturating_sub(1)),
+            source_lines,
+        )
+    } else {
+        (
+            parsed_content.render_blocks(max_len),
+            selected.1,
+            parsed_content.block_count(),
+        )
+    };
+    let scroll = lines(&rendered_content[..position]);
+
+    let note_content = combine(&rendered_content).build_paragraph().scroll((
+        scroll.try_into().unwrap(),
+        if view.wrap {
+            0
+        } else {
+            (*horizontal_scroll).try_into().unwrap_or(u16::MAX)
+        },
+    ));
+
+    (note_content, position, length)
+}
+
+pub fn draw_note_header(
+    frame: &mut Frame,
+    note: &Note,
+    tags: &[Tag],
+    layout: LayoutConfig,
+    header_rect: Rect,
+) {
     let horizontal_layout = Layout::new(
         Direction::Horizontal,
         [Constraint::Percentage(30), Constraint::Min(0)],
     )
-    .split(vertical_layout[0]);
+    .split(header_rect);
 
     let note_title = Paragraph::new(note.name.as_str())
         .style(Style::default().add_modifier(Modifier::BOLD))
         .alignment(Alignment::Left)
         .block(
-            Block::default()
+            layout
+                .block()
                 .title("Title")
                 .title_style(Style::default())
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Green))
-                .padding(Padding::uniform(1)),
+                .border_style(Style::default().fg(Color::Green)),
         );
     let note_tags = Table::default()
         .rows([Row::new(tags.iter().map(|el| Text::raw(el.name.as_str())))])
@@ -338,43 +1017,140 @@ pub fn draw_viewed_note(
         )
         .column_spacing(1)
         .block(
-            Block::default()
+            layout
+                .block()
                 .title("Tags")
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Red))
-                .padding(Padding::uniform(1)),
+                .border_style(Style::default().fg(Color::Red)),
         );
 
-    let content_block = Block::default()
-        .title("Content")
-        .borders(Borders::ALL)
-        .border_type(BorderType::Rounded)
-        .border_style(Style::default().fg(Color::Yellow))
-        .padding(Padding::uniform(1));
+    frame.render_widget(note_title, horizontal_layout[0]);
+    frame.render_widget(note_tags, horizontal_layout[1]);
+}
 
-    let content_area = content_block.inner(vertical_layout[1]);
-    let rendered_content = parsed_content.render_blocks(content_area.width as usize);
-    let scroll = lines(&rendered_content[..selected.1]);
+fn render_raw_lines(content: &str, max_len: usize) -> Vec<RenderedBlock> {
+    let number_width = content.lines().count().to_string().len();
 
-    let note_content = combine(&rendered_content)
-        .build_paragraph()
-        .scroll((scroll.try_into().unwrap(), 0));
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            RenderedBlock::from(vec![Line::from(vec![
+                Span::raw(format!("{:>number_width$} │ ", i + 1))
+                    .style(Style::default().add_modifier(Modifier::DIM)),
+                Span::raw(line.to_string()),
+            ])])
+            .hard_wrap_lines(max_len)
+        })
+        .collect()
+}
 
-    let content_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-        .begin_symbol(Some("↑"))
-        .end_symbol(Some("↓"));
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
 
-    frame.render_widget(note_title, horizontal_layout[0]);
-    frame.render_widget(note_tags, horizontal_layout[1]);
-    frame.render_widget(note_content, content_block.inner(vertical_layout[1]));
-    frame.render_widget(content_block, vertical_layout[1]);
-    frame.render_stateful_widget(
-        content_scrollbar,
-        vertical_layout[1].inner(&Margin::new(0, 1)),
-        &mut ScrollbarState::default()
-            .content_length(parsed_content.block_count().saturating_sub(1))
-            .viewport_content_length(1)
-            .position(selected.1),
-    );
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::config::EditingConfig;
+    use crate::helpers::TryFromDatabase;
+    use crate::note::Note;
+    use crate::notebook::{test_notebook, Notebook};
+    use crate::states::State;
+
+    use super::{run_note_viewing_state, NoteViewingStateData};
+
+    const NAVIGATION: [KeyCode; 13] = [
+        KeyCode::Enter,
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::Char('h'),
+        KeyCode::Char('j'),
+        KeyCode::Char('k'),
+        KeyCode::Char('l'),
+        KeyCode::Char('g'),
+        KeyCode::Char('E'),
+        KeyCode::Down,
+        KeyCode::Right,
+    ];
+
+    fn navigate(content: &str) -> Vec<(usize, usize)> {
+        let notebook = test_notebook();
+        let note = Note::new("Navigated", content.to_owned(), notebook.db()).unwrap();
+        let mut state_data = NoteViewingStateData::try_from_database(note, notebook.db()).unwrap();
+        let mut selections = Vec::new();
+
+        for code in NAVIGATION {
+            let state = run_note_viewing_state(
+                state_data,
+                KeyEvent::new(code, KeyModifiers::NONE),
+                &notebook,
+                &EditingConfig::default(),
+                &mut false,
+            )
+            .unwrap();
+            let State::NoteViewing(next) = state else {
+                panic!("{code:?} left the note viewer");
+            };
+            state_data = next;
+            selections.push(state_data.selected);
+        }
+        selections
+    }
+
+    #[test]
+    fn navigation_in_an_empty_note_keeps_the_origin() {
+        assert!(navigate("").iter().all(|selected| *selected == (0, 0)));
+    }
+
+    #[test]
+    fn navigation_in_a_single_block_stays_in_it() {
+        assert!(navigate("Only paragraph")
+            .iter()
+            .all(|(_, block)| *block == 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn editing_again_reopens_the_unsaved_edits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("foucault-test-{}-unsaved", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        // An editor appending an invalid byte on every session
+        let editor = dir.join("editor.sh");
+        fs::write(&editor, "#!/bin/sh\nprintf '\\377' >> \"$1\"\n").unwrap();
+        fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+        let editing = EditingConfig {
+            editor: editor.display().to_string(),
+            ..EditingConfig::default()
+        };
+
+        let notebook = Notebook::new_notebook("unsaved", &dir).unwrap();
+        let note = Note::new("Draft", String::from("Text"), notebook.db()).unwrap();
+        let mut state_data = NoteViewingStateData::try_from_database(note, notebook.db()).unwrap();
+        for _ in 0..2 {
+            let state = run_note_viewing_state(
+                state_data,
+                KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE),
+                &notebook,
+                &editing,
+                &mut false,
+            )
+            .unwrap();
+            let State::NoteViewing(next) = state else {
+                panic!("Editing left the note viewer");
+            };
+            state_data = next;
+        }
+
+        let file = state_data.unsaved_edits.clone().unwrap();
+        assert_eq!(fs::read(file).unwrap(), b"Text\xff\xff");
+        assert_eq!(state_data.note_data.note.content, "Text");
+
+        drop(notebook);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
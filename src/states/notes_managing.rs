@@ -1,40 +1,177 @@
 use anyhow::Result;
 use log::info;
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::{Constraint, Direction, Layout, Margin};
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{
-    Block, BorderType, Borders, List, ListState, Padding, Paragraph, Scrollbar,
+    Block, BorderType, Borders, List, ListItem, ListState, Padding, Paragraph, Scrollbar,
     ScrollbarOrientation, ScrollbarState,
 };
 
 use rusqlite::Connection;
+use unicode_segmentation::UnicodeSegmentation;
 
+use crate::config::{FormatConfig, ThemeConfig};
 use crate::helpers::{DiscardResult, TryFromDatabase};
-use crate::note::{Note, NoteSummary};
+use crate::note::{Note, NoteSummary, NotesOrder};
 use crate::notebook::Notebook;
 use crate::states::note_viewing::NoteViewingStateData;
+use crate::states::split_view::SplitViewStateData;
 use crate::states::{State, Terminal};
 
+// Patterns shorter than this match too many notes to load them all.
+const SHORT_PATTERN_LENGTH: usize = 3;
+// The section of the names not starting with a letter.
+const SYMBOLS_SECTION: &str = "#";
+// The notes skipped by PageUp and PageDown.
+const RESULTS_PAGE_LENGTH: usize = 10;
+
+/// The notes starting with the same letter, listed under a header row.
+pub struct Section {
+    pub label: String,
+    pub start: usize,
+}
+
 pub struct NotesManagingStateData {
     pub pattern: String,
     pub selected: usize,
     pub notes: Vec<NoteSummary>,
+    pub order: NotesOrder,
+    pub page_length: usize,
+    pub limit: usize,
+    pub truncated: bool,
+    pub sections: Vec<Section>,
+    /// The typed start of a note name to jump to, instead of editing the pattern.
+    pub jump: Option<String>,
+    pub beside: Option<NoteViewingStateData>,
 }
 
 impl NotesManagingStateData {
-    pub fn from_pattern(pattern: String, db: &Connection) -> Result<Self> {
-        Ok(NotesManagingStateData {
-            notes: NoteSummary::search_by_name(pattern.as_str(), db)?,
+    pub fn from_pattern(pattern: String, notebook: &Notebook) -> Result<Self> {
+        let page_length = notebook.settings().search_result_limit();
+        let mut state_data = NotesManagingStateData {
+            notes: Vec::new(),
             selected: 0,
             pattern,
+            order: NotesOrder::Name,
+            page_length,
+            limit: page_length,
+            truncated: false,
+            sections: Vec::new(),
+            jump: None,
+            beside: None,
+        };
+        state_data.search(notebook.db())?;
+        Ok(state_data)
+    }
+
+    pub fn empty(notebook: &Notebook) -> Result<Self> {
+        Self::from_pattern(String::new(), notebook)
+    }
+
+    /// The picked note opens in a split view beside the viewed one.
+    pub fn beside(note_viewing_data: NoteViewingStateData, notebook: &Notebook) -> Result<Self> {
+        Ok(NotesManagingStateData {
+            beside: Some(note_viewing_data),
+            ..Self::empty(notebook)?
         })
     }
 
-    pub fn empty(db: &Connection) -> Result<Self> {
-        Self::from_pattern(String::new(), db)
+    fn search(&mut self, db: &Connection) -> Result<()> {
+        self.limit = self.page_length;
+        self.selected = 0;
+        self.load(db)
+    }
+
+    /// The selection stays on the same note, which grouping may have moved.
+    fn load_more(&mut self, db: &Connection) -> Result<()> {
+        info!("Load more notes matching {:?}.", self.pattern);
+        let selected_id = self.notes.get(self.selected).map(|note| note.id);
+        self.limit += self.page_length;
+        self.load(db)?;
+        self.selected = self
+            .notes
+            .iter()
+            .position(|note| Some(note.id) == selected_id)
+            .unwrap_or(0);
+        Ok(())
+    }
+
+    fn load(&mut self, db: &Connection) -> Result<()> {
+        let limit =
+            (self.pattern.graphemes(true).count() < SHORT_PATTERN_LENGTH).then_some(self.limit);
+        // Fetch one more note to know if some are left out.
+        self.notes = NoteSummary::search_by_name(
+            self.pattern.as_str(),
+            self.order,
+            limit.map(|limit| limit + 1),
+            db,
+        )?;
+        self.truncated = limit.is_some_and(|limit| self.notes.len() > limit);
+        if let Some(limit) = limit {
+            self.notes.truncate(limit);
+        }
+        self.group();
+        Ok(())
+    }
+
+    /// Split the notes by first letter while browsing them all by name.
+    fn group(&mut self) {
+        self.sections.clear();
+        if !self.pattern.is_empty() || self.order != NotesOrder::Name {
+            return;
+        }
+
+        // Names are ordered bytewise, which scatters the cases and the symbols
+        self.notes.sort_by_cached_key(|note| {
+            let label = section_label(note.name.as_str());
+            (label != SYMBOLS_SECTION, label)
+        });
+        for (i, note) in self.notes.iter().enumerate() {
+            let label = section_label(note.name.as_str());
+            if self
+                .sections
+                .last()
+                .is_none_or(|section| section.label != label)
+            {
+                self.sections.push(Section { label, start: i });
+            }
+        }
+    }
+
+    /// Select the first note from `from` on, wrapping around, whose name starts with the jump text.
+    fn jump_to_match(&mut self, from: usize) -> bool {
+        let Some(jump) = self.jump.as_ref().map(|jump| jump.to_lowercase()) else {
+            return false;
+        };
+        let found = (from..self.notes.len())
+            .chain(0..from)
+            .find(|&i| self.notes[i].name.to_lowercase().starts_with(jump.as_str()));
+        if let Some(i) = found {
+            self.selected = i;
+        }
+        found.is_some()
+    }
+
+    /// The row of the selected note, counting the section headers above it.
+    fn selected_row(&self) -> usize {
+        self.selected
+            + self
+                .sections
+                .iter()
+                .take_while(|section| section.start <= self.selected)
+                .count()
+    }
+}
+
+fn section_label(name: &str) -> String {
+    match name.graphemes(true).next() {
+        Some(first) if first.chars().next().is_some_and(char::is_alphabetic) => {
+            first.to_uppercase()
+        }
+        _ => String::from(SYMBOLS_SECTION),
     }
 }
 
@@ -43,36 +180,105 @@ pub fn run_note_managing_state(
     key_event: KeyEvent,
     notebook: &Notebook,
 ) -> Result<State> {
+    if let Some(mut jump) = state_data.jump.take() {
+        let plain = (key_event.modifiers - KeyModifiers::SHIFT).is_empty();
+        match key_event.code {
+            KeyCode::Esc => {
+                info!("Stop jumping through the results.");
+                return Ok(State::NotesManaging(state_data));
+            }
+            KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                info!("Stop jumping through the results.");
+                return Ok(State::NotesManaging(state_data));
+            }
+            KeyCode::Backspace => {
+                jump.pop();
+                state_data.jump = Some(jump);
+                state_data.jump_to_match(0);
+                return Ok(State::NotesManaging(state_data));
+            }
+            KeyCode::Tab => {
+                state_data.jump = Some(jump);
+                state_data.jump_to_match(state_data.selected + 1);
+                return Ok(State::NotesManaging(state_data));
+            }
+            KeyCode::Char(c) if plain => {
+                jump.push(c);
+                state_data.jump = Some(jump);
+                state_data.jump_to_match(state_data.selected);
+                return Ok(State::NotesManaging(state_data));
+            }
+            _ => state_data.jump = Some(jump),
+        }
+    }
+
     Ok(match key_event.code {
         KeyCode::Esc => {
             info!("Stop notes managing.");
-            State::Nothing
+            match state_data.beside {
+                Some(note_viewing_data) => State::NoteViewing(note_viewing_data),
+                None => State::Nothing,
+            }
         }
         KeyCode::Enter if !state_data.notes.is_empty() => {
             let note_summary = &state_data.notes[state_data.selected];
             if let Some(note) = Note::load_by_id(note_summary.id, notebook.db())? {
                 info!("Open note {}.", note_summary.name);
-                State::NoteViewing(NoteViewingStateData::try_from_database(
-                    note,
-                    notebook.db(),
-                )?)
+                let note_viewing_data =
+                    NoteViewingStateData::try_from_database(note, notebook.db())?
+                        .with_default_view(&notebook.settings());
+                match state_data.beside {
+                    Some(left) => {
+                        State::SplitView(SplitViewStateData::new(left, note_viewing_data))
+                    }
+                    None => State::NoteViewing(note_viewing_data),
+                }
             } else {
                 State::NotesManaging(state_data)
             }
         }
-        KeyCode::Backspace => {
+        KeyCode::Tab => {
+            state_data.order = match state_data.order {
+                NotesOrder::Name => NotesOrder::Size,
+                NotesOrder::Size => NotesOrder::Name,
+            };
+            state_data.search(notebook.db())?;
+
+            State::NotesManaging(state_data)
+        }
+        KeyCode::Char(c)
+            if key_event.modifiers.contains(KeyModifiers::ALT)
+                && !state_data.sections.is_empty() =>
+        {
+            let label = section_label(c.to_string().as_str());
+            if let Some(section) = state_data
+                .sections
+                .iter()
+                .find(|section| section.label == label)
+            {
+                info!("Jump to the notes starting with {label}.");
+                state_data.selected = section.start;
+            }
+
+            State::NotesManaging(state_data)
+        }
+        KeyCode::Char('f')
+            if key_event.modifiers.contains(KeyModifiers::CONTROL) && state_data.jump.is_none() =>
+        {
+            info!("Jump through the results.");
+            state_data.jump = Some(String::new());
+
+            State::NotesManaging(state_data)
+        }
+        KeyCode::Backspace if state_data.jump.is_none() => {
             state_data.pattern.pop();
-            state_data.notes =
-                NoteSummary::search_by_name(state_data.pattern.as_str(), notebook.db())?;
-            state_data.selected = 0;
+            state_data.search(notebook.db())?;
 
             State::NotesManaging(state_data)
         }
-        KeyCode::Char(c) => {
+        KeyCode::Char(c) if state_data.jump.is_none() => {
             state_data.pattern.push(c);
-            state_data.notes =
-                NoteSummary::search_by_name(state_data.pattern.as_str(), notebook.db())?;
-            state_data.selected = 0;
+            state_data.search(notebook.db())?;
 
             State::NotesManaging(state_data)
         }
@@ -86,19 +292,61 @@ pub fn run_note_managing_state(
                 ..state_data
             })
         }
+        KeyCode::Down if state_data.truncated => {
+            state_data.load_more(notebook.db())?;
+            state_data.selected = (state_data.selected + 1).min(state_data.notes.len() - 1);
+            State::NotesManaging(state_data)
+        }
+        KeyCode::PageUp => {
+            state_data.selected = state_data.selected.saturating_sub(RESULTS_PAGE_LENGTH);
+            State::NotesManaging(state_data)
+        }
+        KeyCode::PageDown if !state_data.notes.is_empty() => {
+            if state_data.selected + RESULTS_PAGE_LENGTH >= state_data.notes.len()
+                && state_data.truncated
+            {
+                state_data.load_more(notebook.db())?;
+            }
+            state_data.selected =
+                (state_data.selected + RESULTS_PAGE_LENGTH).min(state_data.notes.len() - 1);
+            State::NotesManaging(state_data)
+        }
+        KeyCode::Home => {
+            state_data.selected = 0;
+            State::NotesManaging(state_data)
+        }
+        KeyCode::End => {
+            state_data.selected = state_data.notes.len().saturating_sub(1);
+            State::NotesManaging(state_data)
+        }
         _ => State::NotesManaging(state_data),
     })
 }
 
 pub fn draw_note_managing_state(
-    NotesManagingStateData {
-        pattern,
-        selected,
-        notes,
-    }: &NotesManagingStateData,
+    state_data: &NotesManagingStateData,
+    format: &FormatConfig,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
+    let NotesManagingStateData {
+        pattern,
+        notes,
+        order,
+        truncated,
+        sections,
+        jump,
+        beside,
+        ..
+    } = state_data;
+    let selected_row = state_data.selected_row();
+    let jump_matched = jump.as_ref().is_none_or(|jump| {
+        notes
+            .iter()
+            .any(|note| note.name.to_lowercase().starts_with(&jump.to_lowercase()))
+    });
+
     terminal
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
@@ -109,46 +357,93 @@ pub fn draw_note_managing_state(
             )
             .split(main_rect);
 
-            let search_bar = Paragraph::new(Line::from(vec![
-                Span::raw(pattern).style(Style::default().add_modifier(Modifier::UNDERLINED))
-            ]))
+            let search_bar = Paragraph::new(Line::from(vec![Span::raw(
+                jump.as_deref().unwrap_or(pattern),
+            )
+            .style(Style::default().add_modifier(Modifier::UNDERLINED))]))
             .block(
                 Block::new()
-                    .title("Searching")
+                    .title(match (jump, beside) {
+                        (Some(_), _) => String::from("Jumping to"),
+                        (None, Some(data)) => {
+                            format!("Open beside {}", data.note_data.note.name)
+                        }
+                        (None, None) => String::from("Searching"),
+                    })
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded)
-                    .border_style(Style::default().fg(if notes.is_empty() {
+                    .border_style(Style::default().fg(if notes.is_empty() || !jump_matched {
                         Color::Red
+                    } else if jump.is_some() {
+                        Color::Blue
                     } else {
                         Color::Green
                     }))
                     .padding(Padding::uniform(1)),
             );
 
-            let list_results = List::new(notes.iter().map(|note| {
-                info!("Test {note:?}");
+            let results_block = Block::new()
+                .title(match (order, truncated) {
+                    (NotesOrder::Name, false) => String::from("Results"),
+                    (NotesOrder::Size, false) => String::from("Results by size"),
+                    (NotesOrder::Name, true) => format!("Results, first {}", notes.len()),
+                    (NotesOrder::Size, true) => {
+                        format!("Results by size, first {}", notes.len())
+                    }
+                })
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow))
+                .padding(Padding::uniform(2));
+            let results_width = results_block
+                .inner(vertical_layout[1])
+                .width
+                .saturating_sub(3) as usize;
+
+            let mut rows = Vec::with_capacity(notes.len() + sections.len());
+            let mut next_sections = sections.iter().peekable();
+            for (i, note) in notes.iter().enumerate() {
+                if let Some(section) = next_sections.next_if(|section| section.start == i) {
+                    rows.push(ListItem::new(
+                        Span::raw(section.label.as_str()).bold().fg(Color::Yellow),
+                    ));
+                }
                 let pattern_start = note
                     .name
                     .to_lowercase()
                     .find(&pattern.to_lowercase())
                     .expect("The search pattern should have matched");
                 let pattern_end = pattern_start + pattern.len();
-                Line::from(vec![
+                let mut line = Line::from(vec![
                     Span::raw(&note.name[..pattern_start]),
                     Span::raw(&note.name[pattern_start..pattern_end]).underlined(),
                     Span::raw(&note.name[pattern_end..]),
-                ])
-            }))
-            .highlight_symbol(">> ")
-            .highlight_style(Style::default().bg(Color::White).fg(Color::Black))
-            .block(
-                Block::new()
-                    .title("Results")
-                    .borders(Borders::ALL)
-                    .border_type(BorderType::Rounded)
-                    .border_style(Style::default().fg(Color::Yellow))
-                    .padding(Padding::uniform(2)),
-            );
+                ]);
+
+                let badge = if note.outgoing_links > 0 || note.incoming_links > 0 {
+                    format!(
+                        "→{} ←{}  {} chars",
+                        format.count(note.outgoing_links),
+                        format.count(note.incoming_links),
+                        format.count(note.content_len)
+                    )
+                } else {
+                    format!("{} chars", format.count(note.content_len))
+                };
+                let badge = Span::raw(badge).add_modifier(Modifier::DIM);
+                let gap = results_width
+                    .saturating_sub(line.width() + badge.width())
+                    .max(1);
+                line.spans.push(Span::raw(" ".repeat(gap)));
+                line.spans.push(badge);
+
+                rows.push(ListItem::new(line));
+            }
+
+            let list_results = List::new(rows)
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
+                .block(results_block);
 
             let notes_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
                 .begin_symbol(Some("↑"))
@@ -158,12 +453,12 @@ pub fn draw_note_managing_state(
             frame.render_stateful_widget(
                 list_results,
                 vertical_layout[1],
-                &mut ListState::with_selected(ListState::default(), Some(*selected)),
+                &mut ListState::with_selected(ListState::default(), Some(selected_row)),
             );
             frame.render_stateful_widget(
                 notes_scrollbar,
                 vertical_layout[1].inner(&Margin::new(0, 1)),
-                &mut ScrollbarState::new(notes.len()).position(*selected),
+                &mut ScrollbarState::new(notes.len() + sections.len()).position(selected_row),
             );
 
             frame.render_widget(main_frame, frame.size());
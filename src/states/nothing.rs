@@ -9,8 +9,11 @@ use ratatui::widgets::{Block, Paragraph};
 
 use crate::helpers::{create_popup_proportion, Capitalize, DiscardResult};
 use crate::notebook::Notebook;
+use crate::states::config_managing::ConfigManagingStateData;
 use crate::states::note_creating::NoteCreatingStateData;
 use crate::states::notes_managing::NotesManagingStateData;
+use crate::states::settings_managing::SettingsManagingStateData;
+use crate::states::statistics::StatisticsStateData;
 use crate::states::tags_managing::TagsManagingStateData;
 use crate::states::{State, Terminal};
 
@@ -26,12 +29,24 @@ pub fn run_nothing_state(key_event: KeyEvent, notebook: &Notebook) -> Result<Sta
         }
         KeyCode::Char('s') => {
             info!("Open notes listing.");
-            State::NotesManaging(NotesManagingStateData::empty(notebook.db())?)
+            State::NotesManaging(NotesManagingStateData::empty(notebook)?)
         }
         KeyCode::Char('t') => {
             info!("Open tags manager.");
             State::TagsManaging(TagsManagingStateData::empty(notebook.db())?)
         }
+        KeyCode::Char('i') => {
+            info!("Open notebook statistics.");
+            State::Statistics(StatisticsStateData::new(notebook)?)
+        }
+        KeyCode::Char('p') => {
+            info!("Open preferences.");
+            State::ConfigManaging(ConfigManagingStateData::empty())
+        }
+        KeyCode::Char(',') => {
+            info!("Open settings manager.");
+            State::SettingsManaging(SettingsManagingStateData::empty())
+        }
         _ => State::Nothing,
     })
 }
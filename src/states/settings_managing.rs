@@ -0,0 +1,180 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, List, ListState, Padding};
+use ratatui::Frame;
+
+use crate::config::ThemeConfig;
+use crate::helpers::DiscardResult;
+use crate::notebook::Notebook;
+use crate::settings::{NotebookSettings, SettingKind, KNOWN_SETTINGS};
+use crate::states::{State, Terminal};
+
+pub struct SettingsManagingStateData {
+    pub selected: usize,
+    pub editing: Option<String>,
+}
+
+impl SettingsManagingStateData {
+    pub fn empty() -> Self {
+        SettingsManagingStateData {
+            selected: 0,
+            editing: None,
+        }
+    }
+}
+
+pub fn run_settings_managing_state(
+    mut state_data: SettingsManagingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    let setting = KNOWN_SETTINGS[state_data.selected];
+
+    if let Some(mut value) = state_data.editing {
+        state_data.editing = match key_event.code {
+            KeyCode::Esc => None,
+            KeyCode::Enter if setting.validate(value.as_str()) => {
+                info!("Set notebook setting {} to {value:?}.", setting.key);
+                notebook.set_setting(setting.key, value.as_str())?;
+                None
+            }
+            KeyCode::Tab => {
+                if let SettingKind::Choice(choices) = setting.kind {
+                    let next = choices
+                        .iter()
+                        .position(|choice| *choice == value)
+                        .map_or(0, |current| (current + 1) % choices.len());
+                    choices[next].clone_into(&mut value);
+                }
+                Some(value)
+            }
+            KeyCode::Backspace => {
+                value.pop();
+                Some(value)
+            }
+            KeyCode::Char(c) => {
+                value.push(c);
+                Some(value)
+            }
+            _ => Some(value),
+        };
+        return Ok(State::SettingsManaging(state_data));
+    }
+
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!("Stop settings managing.");
+            State::Nothing
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::SettingsManaging(state_data)
+        }
+        KeyCode::Down if state_data.selected < KNOWN_SETTINGS.len() - 1 => {
+            state_data.selected += 1;
+            State::SettingsManaging(state_data)
+        }
+        KeyCode::Enter => {
+            info!("Edit notebook setting {}.", setting.key);
+            state_data.editing = Some(notebook.settings().get(setting).to_owned());
+            State::SettingsManaging(state_data)
+        }
+        _ => State::SettingsManaging(state_data),
+    })
+}
+
+pub fn draw_settings_managing_state(
+    state_data: &SettingsManagingStateData,
+    notebook: &Notebook,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_settings_managing(frame, state_data, &notebook.settings(), theme, main_rect);
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
+
+fn draw_settings_managing(
+    frame: &mut Frame,
+    SettingsManagingStateData { selected, editing }: &SettingsManagingStateData,
+    settings: &NotebookSettings,
+    theme: &ThemeConfig,
+    main_rect: Rect,
+) {
+    let key_width = KNOWN_SETTINGS
+        .iter()
+        .map(|setting| setting.key.len())
+        .max()
+        .unwrap_or_default();
+
+    let known_settings = KNOWN_SETTINGS.iter().enumerate().map(|(i, setting)| {
+        let hint = match setting.kind {
+            SettingKind::Text => String::from("text"),
+            SettingKind::Number => String::from("number"),
+            SettingKind::Count => String::from("number, 0 for none"),
+            SettingKind::List => String::from("space separated"),
+            SettingKind::Choice(choices) => choices.join(" | "),
+        };
+
+        let value = match editing {
+            Some(value) if i == *selected => Span::raw(value.as_str()).style(
+                Style::default()
+                    .fg(if setting.validate(value) {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    })
+                    .add_modifier(Modifier::UNDERLINED),
+            ),
+            _ => Span::raw(settings.get(*setting)),
+        };
+
+        Line::from(vec![
+            Span::raw(format!("{:key_width$}  ", setting.key))
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+            value,
+            Span::raw(format!("  ({hint})")).style(Style::default().add_modifier(Modifier::DIM)),
+        ])
+    });
+
+    let unknown_settings = settings.unknown().map(|(key, value)| {
+        Line::from(vec![Span::raw(format!(
+            "{key:key_width$}  {value}  (unknown)"
+        ))
+        .style(Style::default().add_modifier(Modifier::DIM))])
+    });
+
+    let settings_list = List::new(known_settings.chain(unknown_settings).collect::<Vec<_>>())
+        .highlight_symbol(theme.highlight_symbol.as_str())
+        .highlight_style(if editing.is_some() {
+            Style::default()
+        } else {
+            theme.selection_style()
+        })
+        .block(
+            Block::new()
+                .title("Settings")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow))
+                .padding(Padding::uniform(2)),
+        );
+
+    frame.render_stateful_widget(
+        settings_list,
+        main_rect,
+        &mut ListState::with_selected(ListState::default(), Some(*selected)),
+    );
+}
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::Block;
+use ratatui::Frame;
+
+use crate::config::{EditingConfig, LayoutConfig};
+use crate::helpers::{draw_yes_no_prompt, DiscardResult};
+use crate::notebook::Notebook;
+use crate::states::note_editing::{draw_edited_note, run_note_editing_state, NoteEditingStateData};
+use crate::states::note_viewing::{draw_viewed_note, run_note_viewing_state, NoteViewingStateData};
+use crate::states::{State, Terminal};
+
+pub enum Pane {
+    Viewing(NoteViewingStateData),
+    Editing(NoteEditingStateData),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Only the focused pane takes keys, so it is the only one that may be edited.
+pub struct SplitViewStateData {
+    pub focused: Pane,
+    pub other: Box<NoteViewingStateData>,
+    pub side: Side,
+}
+
+impl SplitViewStateData {
+    pub fn new(left: NoteViewingStateData, right: NoteViewingStateData) -> Self {
+        SplitViewStateData {
+            focused: Pane::Viewing(right),
+            other: Box::new(left),
+            side: Side::Right,
+        }
+    }
+
+    pub fn is_editing(&self) -> bool {
+        matches!(self.focused, Pane::Editing(_))
+    }
+}
+
+pub fn run_split_view_state(
+    state_data: SplitViewStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+    editing: &EditingConfig,
+    force_redraw: &mut bool,
+) -> Result<State> {
+    let SplitViewStateData {
+        focused,
+        other,
+        side,
+    } = state_data;
+
+    let state = match focused {
+        Pane::Viewing(focused) => {
+            let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+            match key_event.code {
+                // The quit prompt of the pane takes every key until it is answered
+                _ if focused.quitting.is_some() => {
+                    run_note_viewing_state(focused, key_event, notebook, editing, force_redraw)?
+                }
+                KeyCode::Tab => {
+                    return Ok(State::SplitView(SplitViewStateData {
+                        focused: Pane::Viewing(*other),
+                        other: Box::new(focused),
+                        side: match side {
+                            Side::Left => Side::Right,
+                            Side::Right => Side::Left,
+                        },
+                    }));
+                }
+                KeyCode::Char('v') if control => {
+                    info!("Leave split view.");
+                    return Ok(State::NoteViewing(focused));
+                }
+                KeyCode::Esc => {
+                    info!("Close split pane of note {}.", focused.note_data.note.name);
+                    return Ok(State::NoteViewing(*other));
+                }
+                _ => run_note_viewing_state(focused, key_event, notebook, editing, force_redraw)?,
+            }
+        }
+        Pane::Editing(focused) => run_note_editing_state(focused, key_event, notebook, editing)?,
+    };
+
+    // Screens other than the note itself take the whole terminal
+    Ok(match state {
+        State::NoteViewing(focused) => State::SplitView(SplitViewStateData {
+            focused: Pane::Viewing(focused),
+            other,
+            side,
+        }),
+        State::NoteEditing(focused) => State::SplitView(SplitViewStateData {
+            focused: Pane::Editing(focused),
+            other,
+            side,
+        }),
+        state => state,
+    })
+}
+
+pub fn draw_split_view_state(
+    SplitViewStateData {
+        focused,
+        other,
+        side,
+    }: &SplitViewStateData,
+    layout: LayoutConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            let panes = Layout::new(
+                Direction::Horizontal,
+                [Constraint::Percentage(50), Constraint::Percentage(50)],
+            )
+            .split(main_rect);
+            let (focused_rect, other_rect) = match side {
+                Side::Left => (panes[0], panes[1]),
+                Side::Right => (panes[1], panes[0]),
+            };
+
+            draw_pane(frame, focused, layout, focused_rect);
+            draw_viewed_note(frame, other, layout, other_rect);
+            frame
+                .buffer_mut()
+                .set_style(other_rect, Style::default().add_modifier(Modifier::DIM));
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
+
+fn draw_pane(frame: &mut Frame, pane: &Pane, layout: LayoutConfig, rect: Rect) {
+    match pane {
+        Pane::Viewing(data) => {
+            draw_viewed_note(frame, data, layout, rect);
+            if let Some(quit) = data.quitting {
+                draw_yes_no_prompt(frame, quit, "Quit without saving the edits ?", rect);
+            }
+        }
+        Pane::Editing(data) => {
+            draw_edited_note(frame, data, layout, rect);
+            if let Some(discard) = data.discarding {
+                draw_yes_no_prompt(frame, discard, "Discard changes ?", rect);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use crate::config::EditingConfig;
+    use crate::helpers::TryFromDatabase;
+    use crate::note::Note;
+    use crate::notebook::test_notebook;
+    use crate::states::note_viewing::NoteViewingStateData;
+    use crate::states::State;
+
+    use super::{run_split_view_state, Pane, Side, SplitViewStateData};
+
+    #[test]
+    fn the_quit_prompt_takes_the_keys_of_the_pane() {
+        let notebook = test_notebook();
+        let db = notebook.db();
+        let mut left = NoteViewingStateData::try_from_database(
+            Note::new("Left", String::new(), db).unwrap(),
+            db,
+        )
+        .unwrap();
+        let right = NoteViewingStateData::try_from_database(
+            Note::new("Right", String::new(), db).unwrap(),
+            db,
+        )
+        .unwrap();
+        left.unsaved_edits = Some(PathBuf::from("Left.tmp.md"));
+        let mut state = State::SplitView(SplitViewStateData {
+            focused: Pane::Viewing(left),
+            other: Box::new(right),
+            side: Side::Left,
+        });
+
+        for (code, quitting) in [
+            (KeyCode::Char('q'), Some(false)),
+            (KeyCode::Tab, Some(true)),
+            (KeyCode::Tab, Some(false)),
+            (KeyCode::Esc, None),
+        ] {
+            let State::SplitView(state_data) = state else {
+                panic!("The split view was left before {code:?}");
+            };
+            state = run_split_view_state(
+                state_data,
+                KeyEvent::new(code, KeyModifiers::NONE),
+                &notebook,
+                &EditingConfig::default(),
+                &mut false,
+            )
+            .unwrap();
+            let State::SplitView(SplitViewStateData {
+                focused: Pane::Viewing(focused),
+                side: Side::Left,
+                ..
+            }) = &state
+            else {
+                panic!("{code:?} left the focused pane");
+            };
+            assert_eq!(focused.quitting, quitting, "after {code:?}");
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Paragraph, Row, Sparkline, Table};
+
+use crate::attachment::format_size;
+use crate::config::FormatConfig;
+use crate::helpers::DiscardResult;
+use crate::notebook::Notebook;
+use crate::states::{State, Terminal};
+use crate::stats::NotebookStats;
+
+pub struct StatisticsStateData {
+    pub current: NotebookStats,
+    pub months: Vec<NotebookStats>,
+}
+
+impl StatisticsStateData {
+    pub fn new(notebook: &Notebook) -> Result<Self> {
+        Ok(StatisticsStateData {
+            current: NotebookStats::current(notebook.db())?,
+            months: NotebookStats::monthly(&NotebookStats::history(notebook.db())?),
+        })
+    }
+}
+
+pub fn run_statistics_state(state_data: StatisticsStateData, key_event: KeyEvent) -> State {
+    match key_event.code {
+        KeyCode::Esc => {
+            info!("Close notebook statistics.");
+            State::Nothing
+        }
+        _ => State::Statistics(state_data),
+    }
+}
+
+pub fn draw_statistics_state(
+    StatisticsStateData { current, months }: &StatisticsStateData,
+    format: &FormatConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            let vertical_layout = Layout::new(
+                Direction::Vertical,
+                [
+                    Constraint::Length(3),
+                    Constraint::Length(6),
+                    Constraint::Min(0),
+                ],
+            )
+            .split(main_rect);
+
+            let summary = Paragraph::new(Line::from(vec![
+                Span::raw(format.count(current.note_count)).bold(),
+                Span::raw(" notes, "),
+                Span::raw(format.count(current.link_count)).bold(),
+                Span::raw(" links, "),
+                Span::raw(format_size(current.total_bytes)).bold(),
+            ]))
+            .block(
+                Block::new()
+                    .title("Statistics")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+
+            let note_counts: Vec<u64> = months.iter().map(|stats| stats.note_count).collect();
+            let growth = Sparkline::default()
+                .data(note_counts.as_slice())
+                .style(Style::default().fg(Color::Green))
+                .block(
+                    Block::new()
+                        .title("Notes by month")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::Green)),
+                );
+
+            let history = Table::new(
+                months.iter().rev().map(|stats| {
+                    Row::new([
+                        stats.date.format("%Y-%m").to_string(),
+                        format.count(stats.note_count),
+                        format.count(stats.link_count),
+                        format_size(stats.total_bytes),
+                    ])
+                }),
+                [
+                    Constraint::Length(10),
+                    Constraint::Length(12),
+                    Constraint::Length(12),
+                    Constraint::Min(0),
+                ],
+            )
+            .header(
+                Row::new(["Month", "Notes", "Links", "Size"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(
+                Block::new()
+                    .title("History")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Blue)),
+            );
+
+            frame.render_widget(summary, vertical_layout[0]);
+            frame.render_widget(growth, vertical_layout[1]);
+            frame.render_widget(history, vertical_layout[2]);
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
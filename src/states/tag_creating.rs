@@ -4,16 +4,17 @@ use log::info;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::Block;
 
+use crate::config::ThemeConfig;
 use crate::helpers::{draw_text_prompt, DiscardResult};
 use crate::notebook::Notebook;
 use crate::states::tags_managing::{draw_tags_managing, TagsManagingStateData};
 use crate::states::{State, Terminal};
-use crate::tag::Tag;
+use crate::tag::{Tag, TagError};
 
 pub struct TagsCreatingStateData {
     pub tags_managing_data: TagsManagingStateData,
     pub name: String,
-    pub valid: bool,
+    pub error: Option<TagError>,
 }
 
 impl TagsCreatingStateData {
@@ -21,7 +22,7 @@ impl TagsCreatingStateData {
         TagsCreatingStateData {
             tags_managing_data,
             name: String::new(),
-            valid: false,
+            error: Some(TagError::EmptyName),
         }
     }
 }
@@ -36,30 +37,43 @@ pub fn run_tag_creating_state(
             info!("Cancel tag creation.");
             State::TagsManaging(state_data.tags_managing_data)
         }
-        KeyCode::Enter if !state_data.name.is_empty() => {
-            if Tag::tag_exists(state_data.name.as_str(), notebook.db())? {
+        KeyCode::Enter if matches!(state_data.error, Some(TagError::TagInTrash { .. })) => {
+            if let Some(tag) = Tag::load_trashed(state_data.name.as_str(), notebook.db())? {
+                info!("Restore tag {} from the trash.", tag.name);
+                tag.restore(notebook.db())?;
+            }
+            State::TagsManaging(TagsManagingStateData::from_pattern(
+                state_data.tags_managing_data.pattern,
+                notebook.db(),
+            )?)
+        }
+        KeyCode::Enter => {
+            if let Some(error) = Tag::validate_name(state_data.name.as_str(), notebook.db())? {
                 State::TagCreating(TagsCreatingStateData {
-                    valid: false,
+                    error: Some(error),
                     ..state_data
                 })
             } else {
                 info!("Create tag {}.", state_data.name);
-                Tag::new(state_data.name.as_str(), notebook.db())?;
-                State::TagsManaging(TagsManagingStateData::from_pattern(
-                    state_data.tags_managing_data.pattern,
-                    notebook.db(),
-                )?)
+                if let Err(err) = Tag::new(state_data.name.as_str(), notebook.db()) {
+                    state_data.error = Some(err.downcast::<TagError>()?);
+                    State::TagCreating(state_data)
+                } else {
+                    State::TagsManaging(TagsManagingStateData::from_pattern(
+                        state_data.tags_managing_data.pattern,
+                        notebook.db(),
+                    )?)
+                }
             }
         }
         KeyCode::Backspace => {
             state_data.name.pop();
-            state_data.valid = Tag::tag_exists(state_data.name.as_str(), notebook.db())?
-                && !state_data.name.is_empty();
+            state_data.error = Tag::validate_name(state_data.name.as_str(), notebook.db())?;
             State::TagCreating(state_data)
         }
         KeyCode::Char(c) if !c.is_whitespace() => {
             state_data.name.push(c);
-            state_data.valid = Tag::tag_exists(state_data.name.as_str(), notebook.db())?;
+            state_data.error = Tag::validate_name(state_data.name.as_str(), notebook.db())?;
             State::TagCreating(state_data)
         }
         _ => State::TagCreating(state_data),
@@ -70,8 +84,9 @@ pub fn draw_tag_creating_state(
     TagsCreatingStateData {
         tags_managing_data,
         name,
-        valid: taken,
+        error,
     }: &TagsCreatingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -79,8 +94,19 @@ pub fn draw_tag_creating_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_tags_managing(frame, tags_managing_data, main_rect);
-            draw_text_prompt(frame, "Tag name", name, !taken, main_rect);
+            draw_tags_managing(frame, tags_managing_data, theme, main_rect);
+            draw_text_prompt(
+                frame,
+                if matches!(error, Some(TagError::TagInTrash { .. })) {
+                    "Tag name, enter restores the trashed tag"
+                } else {
+                    "Tag name"
+                },
+                name,
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -4,6 +4,7 @@ use log::info;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::widgets::Block;
 
+use crate::config::ThemeConfig;
 use crate::helpers::{draw_yes_no_prompt, DiscardResult};
 use crate::notebook::Notebook;
 use crate::states::tags_managing::{draw_tags_managing, TagsManagingStateData};
@@ -56,7 +57,7 @@ pub fn run_tag_deleting_state(
                 tags_managing_data
                     .tags
                     .swap_remove(tags_managing_data.selected)
-                    .delete(notebook.db())?;
+                    .trash(notebook.db())?;
             } else {
                 info!(
                     "Cancel deleting of tag {}.",
@@ -87,6 +88,7 @@ pub fn draw_tag_deleting_state(
         tags_managing_data,
         delete,
     }: &TagsDeletingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -96,7 +98,7 @@ pub fn draw_tag_deleting_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_tags_managing(frame, tags_managing_data, main_rect);
+            draw_tags_managing(frame, tags_managing_data, theme, main_rect);
 
             draw_yes_no_prompt(
                 frame,
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListState};
+
+use rusqlite::Connection;
+
+use crate::config::ThemeConfig;
+use crate::helpers::{create_popup_proportion, draw_text_prompt, DiscardResult, EditableText};
+use crate::notebook::Notebook;
+use crate::states::tags_managing::{draw_tags_managing, TagsManagingStateData};
+use crate::states::{State, Terminal};
+use crate::tag::{Tag, TagError};
+
+pub struct TagImplicationsStateData {
+    pub tags_managing_data: TagsManagingStateData,
+    pub tag: Tag,
+    pub consequents: Vec<Tag>,
+    pub selected: usize,
+    pub adding: Option<EditableText>,
+    pub error: Option<TagError>,
+}
+
+impl TagImplicationsStateData {
+    pub fn new(tags_managing_data: TagsManagingStateData, db: &Connection) -> Result<Self> {
+        let tag = tags_managing_data
+            .get_selected()
+            .expect("A tag should be selected")
+            .clone();
+        Ok(TagImplicationsStateData {
+            consequents: tag.consequents(db)?,
+            tag,
+            tags_managing_data,
+            selected: 0,
+            adding: None,
+            error: None,
+        })
+    }
+
+    fn refresh(&mut self, db: &Connection) -> Result<()> {
+        self.consequents = self.tag.consequents(db)?;
+        self.selected = self.selected.min(self.consequents.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn validate(&self, name: &str, db: &Connection) -> Result<Option<TagError>> {
+        Ok(match Tag::load_by_name(name, db)? {
+            Some(consequent) => self.tag.validate_implication(&consequent, db)?,
+            None if name.is_empty() => Some(TagError::EmptyName),
+            None => Some(TagError::TagDoesNotExist {
+                name: name.to_owned(),
+            }),
+        })
+    }
+}
+
+pub fn run_tag_implications_state(
+    mut state_data: TagImplicationsStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    if let Some(mut name) = state_data.adding.take() {
+        match key_event.code {
+            KeyCode::Esc => {
+                state_data.error = None;
+                return Ok(State::TagImplications(state_data));
+            }
+            KeyCode::Enter if state_data.error.is_none() => {
+                let consequent = Tag::load_by_name(name.as_str(), notebook.db())?
+                    .expect("The implied tag should have been validated");
+                info!(
+                    "Make tag {} imply {}.",
+                    state_data.tag.name, consequent.name
+                );
+                state_data.tag.add_implication(&consequent, notebook.db())?;
+                state_data.refresh(notebook.db())?;
+                return Ok(State::TagImplications(state_data));
+            }
+            KeyCode::Backspace => name.remove_char(),
+            KeyCode::Delete => name.del_char(),
+            KeyCode::Left => name.move_left(),
+            KeyCode::Right => name.move_right(),
+            KeyCode::Char(c) if !c.is_whitespace() => name.insert_char(c),
+            _ => {}
+        }
+        state_data.error = state_data.validate(name.as_str(), notebook.db())?;
+        state_data.adding = Some(name);
+        return Ok(State::TagImplications(state_data));
+    }
+
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!("Close the implications of tag {}.", state_data.tag.name);
+            State::TagsManaging(state_data.tags_managing_data)
+        }
+        KeyCode::Char('a') => {
+            state_data.adding = Some(EditableText::new(String::new()));
+            state_data.error = Some(TagError::EmptyName);
+            State::TagImplications(state_data)
+        }
+        KeyCode::Char('d') if !state_data.consequents.is_empty() => {
+            let consequent = &state_data.consequents[state_data.selected];
+            info!(
+                "Stop tag {} from implying {}.",
+                state_data.tag.name, consequent.name
+            );
+            state_data
+                .tag
+                .remove_implication(consequent, notebook.db())?;
+            state_data.refresh(notebook.db())?;
+            State::TagImplications(state_data)
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::TagImplications(state_data)
+        }
+        KeyCode::Down if state_data.selected < state_data.consequents.len().saturating_sub(1) => {
+            state_data.selected += 1;
+            State::TagImplications(state_data)
+        }
+        _ => State::TagImplications(state_data),
+    })
+}
+
+pub fn draw_tag_implications_state(
+    TagImplicationsStateData {
+        tags_managing_data,
+        tag,
+        consequents,
+        selected,
+        adding,
+        error,
+    }: &TagImplicationsStateData,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_tags_managing(frame, tags_managing_data, theme, main_rect);
+
+            let popup_area = create_popup_proportion((50, 50), main_rect);
+            let consequents_list = if consequents.is_empty() {
+                List::new([Line::from(
+                    Span::raw("No implied tag, press a to add one").add_modifier(Modifier::DIM),
+                )])
+            } else {
+                List::new(
+                    consequents
+                        .iter()
+                        .map(|consequent| Line::from(consequent.name.as_str())),
+                )
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
+            }
+            .block(
+                Block::new()
+                    .title(format!("Tagging {} also tags", tag.name))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+
+            frame.render_widget(Clear, popup_area);
+            frame.render_stateful_widget(
+                consequents_list,
+                popup_area,
+                &mut ListState::default()
+                    .with_selected((!consequents.is_empty()).then_some(*selected)),
+            );
+
+            if let Some(name) = adding {
+                draw_text_prompt(
+                    frame,
+                    "Implied tag",
+                    name.as_str(),
+                    error.is_none(),
+                    error.as_ref().map(ToString::to_string).as_deref(),
+                    main_rect,
+                );
+            }
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
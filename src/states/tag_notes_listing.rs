@@ -1,9 +1,11 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
 use log::info;
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::{Constraint, Direction, Layout, Margin};
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{
     Block, BorderType, Borders, List, ListState, Padding, Paragraph, Scrollbar,
@@ -12,17 +14,43 @@ use ratatui::widgets::{
 
 use rusqlite::Connection;
 
-use crate::helpers::{DiscardResult, TryFromDatabase};
-use crate::note::{Note, NoteSummary};
+use crate::config::ThemeConfig;
+use crate::helpers::{
+    draw_text_prompt, draw_yes_no_prompt, DiscardResult, EditableText, TryFromDatabase,
+};
+use crate::note::{Note, NoteData, NoteError, NoteSummary};
 use crate::notebook::Notebook;
+use crate::states::note_creating::add_default_tags;
 use crate::states::note_viewing::NoteViewingStateData;
+use crate::states::tags_managing::TagsManagingStateData;
 use crate::states::{State, Terminal};
 use crate::tag::Tag;
 
+const TERMS_SHOWN: usize = 9;
+
 pub struct TagNotesListingStateData {
     pub tag: Tag,
     pub notes: Vec<NoteSummary>,
     pub selected: usize,
+    pub terms: Option<Vec<(String, usize)>>,
+    pub filter: Option<String>,
+    pub digest: Option<DigestPrompt>,
+    pub creating: Option<NotePrompt>,
+    pub marked: HashSet<i64>,
+    pub status: Option<String>,
+    pub delete_tag: Option<bool>,
+}
+
+pub struct DigestPrompt {
+    pub name: EditableText,
+    pub content: String,
+    pub truncated: bool,
+    pub error: Option<NoteError>,
+}
+
+pub struct NotePrompt {
+    pub name: EditableText,
+    pub error: Option<NoteError>,
 }
 
 impl TryFromDatabase<Tag> for TagNotesListingStateData {
@@ -30,16 +58,46 @@ impl TryFromDatabase<Tag> for TagNotesListingStateData {
         Ok(TagNotesListingStateData {
             notes: tag.get_notes(db)?,
             selected: 0,
+            terms: None,
+            filter: None,
+            digest: None,
+            creating: None,
+            marked: HashSet::new(),
+            status: None,
+            delete_tag: None,
             tag,
         })
     }
 }
 
+impl TagNotesListingStateData {
+    fn filter_notes(&mut self, filter: Option<String>, db: &Connection) -> Result<()> {
+        self.notes = match &filter {
+            Some(term) => self.tag.search_notes(term.as_str(), db)?,
+            None => self.tag.get_notes(db)?,
+        };
+        self.filter = filter;
+        self.selected = 0;
+        Ok(())
+    }
+}
+
 pub fn run_tag_notes_listing_state(
-    state_data: TagNotesListingStateData,
+    mut state_data: TagNotesListingStateData,
     key_event: KeyEvent,
     notebook: &Notebook,
 ) -> Result<State> {
+    state_data.status = None;
+    if let Some(digest) = state_data.digest.take() {
+        return run_digest_prompt(state_data, digest, key_event, notebook);
+    }
+    if let Some(creating) = state_data.creating.take() {
+        return run_note_creating_prompt(state_data, creating, key_event, notebook);
+    }
+    if let Some(delete) = state_data.delete_tag.take() {
+        return run_tag_deleting_prompt(state_data, delete, key_event, notebook);
+    }
+
     Ok(match key_event.code {
         KeyCode::Esc => {
             info!("Cancel tag {} note listing.", state_data.tag.name);
@@ -49,14 +107,82 @@ pub fn run_tag_notes_listing_state(
             let summary = &state_data.notes[state_data.selected];
             if let Some(note) = Note::load_by_id(summary.id, notebook.db())? {
                 info!("Open note {} viewing.", note.name);
-                State::NoteViewing(NoteViewingStateData::try_from_database(
-                    note,
-                    notebook.db(),
-                )?)
+                State::NoteViewing(
+                    NoteViewingStateData::try_from_database(note, notebook.db())?
+                        .with_default_view(&notebook.settings()),
+                )
             } else {
                 State::TagNotesListing(state_data)
             }
         }
+        KeyCode::Char('w') => {
+            state_data.terms = if state_data.terms.is_some() {
+                None
+            } else {
+                info!("Show the frequent terms of tag {}.", state_data.tag.name);
+                Some(state_data.tag.frequent_terms(TERMS_SHOWN, notebook.db())?)
+            };
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Char(' ') if !state_data.notes.is_empty() => {
+            let id = state_data.notes[state_data.selected].id;
+            if !state_data.marked.remove(&id) {
+                state_data.marked.insert(id);
+            }
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Char('u') if !state_data.marked.is_empty() => {
+            let note_ids: Vec<i64> = state_data.marked.drain().collect();
+            let untagged = state_data.tag.remove_from_notes(&note_ids, notebook.db())?;
+            info!("Untag {untagged} notes from tag {}.", state_data.tag.name);
+            state_data.status = Some(format!("{untagged} notes untagged"));
+
+            let filter = state_data.filter.take();
+            state_data.filter_notes(filter, notebook.db())?;
+            if Tag::count_notes(&[state_data.tag.id], notebook.db())?.is_empty() {
+                state_data.delete_tag = Some(false);
+            }
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Char('c') => {
+            info!("Open note creating prompt in tag {}.", state_data.tag.name);
+            state_data.creating = Some(NotePrompt {
+                name: EditableText::new(String::new()),
+                error: Some(NoteError::EmptyName),
+            });
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Char('D') => {
+            info!("Build the digest of tag {}.", state_data.tag.name);
+            let (content, truncated) = state_data.tag.digest(notebook.db())?;
+            let name = format!("{} digest", state_data.tag.name);
+            state_data.digest = Some(DigestPrompt {
+                error: Note::validate_name(name.as_str(), notebook.db())?,
+                name: EditableText::new(name),
+                content,
+                truncated,
+            });
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Char(c @ '1'..='9') => {
+            let term = state_data.terms.as_ref().and_then(|terms| {
+                c.to_digit(10)
+                    .and_then(|digit| terms.get(digit as usize - 1))
+                    .map(|(term, _)| term.clone())
+            });
+            if let Some(term) = term {
+                info!(
+                    "Filter notes of tag {} containing {term}.",
+                    state_data.tag.name
+                );
+                state_data.filter_notes(Some(term), notebook.db())?;
+            }
+            State::TagNotesListing(state_data)
+        }
+        KeyCode::Backspace if state_data.filter.is_some() => {
+            state_data.filter_notes(None, notebook.db())?;
+            State::TagNotesListing(state_data)
+        }
         KeyCode::Up if state_data.selected > 0 => {
             State::TagNotesListing(TagNotesListingStateData {
                 selected: state_data.selected - 1,
@@ -73,12 +199,117 @@ pub fn run_tag_notes_listing_state(
     })
 }
 
+fn run_digest_prompt(
+    mut state_data: TagNotesListingStateData,
+    mut digest: DigestPrompt,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    match key_event.code {
+        KeyCode::Esc => {
+            info!("Cancel the digest of tag {}.", state_data.tag.name);
+            return Ok(State::TagNotesListing(state_data));
+        }
+        KeyCode::Enter if digest.error.is_none() => {
+            info!("Create the digest note {}.", digest.name.as_str());
+            match Note::new(digest.name.as_str(), digest.content.clone(), notebook.db()) {
+                Ok(note) => return State::note_viewing(note, notebook),
+                Err(err) => digest.error = Some(err.downcast::<NoteError>()?),
+            }
+        }
+        KeyCode::Backspace => digest.name.remove_char(),
+        KeyCode::Delete => digest.name.del_char(),
+        KeyCode::Left => digest.name.move_left(),
+        KeyCode::Right => digest.name.move_right(),
+        KeyCode::Char(c) => digest.name.insert_char(c),
+        _ => {}
+    }
+
+    if !matches!(key_event.code, KeyCode::Enter) {
+        digest.error = Note::validate_name(digest.name.as_str(), notebook.db())?;
+    }
+    state_data.digest = Some(digest);
+    Ok(State::TagNotesListing(state_data))
+}
+
+fn run_note_creating_prompt(
+    mut state_data: TagNotesListingStateData,
+    mut creating: NotePrompt,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    match key_event.code {
+        KeyCode::Esc => {
+            info!("Cancel note creation in tag {}.", state_data.tag.name);
+            return Ok(State::TagNotesListing(state_data));
+        }
+        KeyCode::Enter if creating.error.is_none() => {
+            let name = creating.name.as_str();
+            info!("Create note {name} with tag {}.", state_data.tag.name);
+            let content = state_data.tag.template_content(name, notebook.db())?;
+            match Note::new(name, content, notebook.db()) {
+                Ok(note) => {
+                    let mut note_data = NoteData::try_from_database(note, notebook.db())?;
+                    note_data.add_tag(state_data.tag, notebook.db())?;
+                    add_default_tags(&mut note_data, notebook)?;
+                    return State::note_viewing(note_data.note, notebook);
+                }
+                Err(err) => creating.error = Some(err.downcast::<NoteError>()?),
+            }
+        }
+        KeyCode::Backspace => creating.name.remove_char(),
+        KeyCode::Delete => creating.name.del_char(),
+        KeyCode::Left => creating.name.move_left(),
+        KeyCode::Right => creating.name.move_right(),
+        KeyCode::Char(c) => creating.name.insert_char(c),
+        _ => {}
+    }
+
+    if !matches!(key_event.code, KeyCode::Enter) {
+        creating.error = Note::validate_name(creating.name.as_str(), notebook.db())?;
+    }
+    state_data.creating = Some(creating);
+    Ok(State::TagNotesListing(state_data))
+}
+
+fn run_tag_deleting_prompt(
+    mut state_data: TagNotesListingStateData,
+    delete: bool,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    Ok(match key_event.code {
+        KeyCode::Enter if delete => {
+            info!("Delete the unused tag {}.", state_data.tag.name);
+            state_data.tag.trash(notebook.db())?;
+            State::TagsManaging(TagsManagingStateData::empty(notebook.db())?)
+        }
+        KeyCode::Enter | KeyCode::Esc => State::TagNotesListing(state_data),
+        KeyCode::Tab => {
+            state_data.delete_tag = Some(!delete);
+            State::TagNotesListing(state_data)
+        }
+        _ => {
+            state_data.delete_tag = Some(delete);
+            State::TagNotesListing(state_data)
+        }
+    })
+}
+
 pub fn draw_tag_notes_listing_state(
     TagNotesListingStateData {
         tag,
         notes,
         selected,
+        terms,
+        filter,
+        digest,
+        creating,
+        marked,
+        status,
+        delete_tag,
     }: &TagNotesListingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -88,7 +319,11 @@ pub fn draw_tag_notes_listing_state(
 
             let vertical_layout = Layout::new(
                 Direction::Vertical,
-                [Constraint::Length(5), Constraint::Min(0)],
+                [
+                    Constraint::Length(5),
+                    Constraint::Min(0),
+                    Constraint::Length(if terms.is_some() { 3 } else { 0 }),
+                ],
             )
             .split(main_rect);
 
@@ -104,16 +339,35 @@ pub fn draw_tag_notes_listing_state(
                     .padding(Padding::uniform(1)),
             );
 
-            let tag_notes = List::new(notes.iter().map(|tag| Span::raw(tag.name.as_str())))
-                .highlight_symbol(">> ")
-                .highlight_style(Style::default().fg(Color::Black).bg(Color::White))
-                .block(
-                    Block::new()
-                        .title("Tag notes")
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded)
-                        .border_style(Style::default().fg(Color::Yellow)),
-                );
+            let mut title = match filter {
+                Some(term) => format!("Tag notes containing {term:?}"),
+                None => String::from("Tag notes"),
+            };
+            if let Some(status) = status {
+                title.push_str(format!(", {status}").as_str());
+            } else if !marked.is_empty() {
+                title.push_str(format!(", {} marked", marked.len()).as_str());
+            }
+
+            let tag_notes = List::new(notes.iter().map(|note| {
+                if marked.contains(&note.id) {
+                    Line::from(vec![
+                        Span::raw("✓ ").style(Style::default().fg(Color::Green)),
+                        Span::raw(note.name.as_str()),
+                    ])
+                } else {
+                    Line::from(vec![Span::raw("  "), Span::raw(note.name.as_str())])
+                }
+            }))
+            .highlight_symbol(theme.highlight_symbol.as_str())
+            .highlight_style(theme.selection_style())
+            .block(
+                Block::new()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
 
             let notes_scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
                 .begin_symbol(Some("↑"))
@@ -130,7 +384,119 @@ pub fn draw_tag_notes_listing_state(
                 vertical_layout[1].inner(&Margin::new(0, 1)),
                 &mut ScrollbarState::new(notes.len()).position(*selected),
             );
+            if let Some(terms) = terms {
+                let terms_strip = Paragraph::new(Line::from(
+                    terms
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(i, (term, count))| {
+                            [
+                                Span::raw(format!(" {} ", i + 1))
+                                    .style(Style::default().fg(Color::Yellow)),
+                                Span::raw(format!("{term} ({count})  "))
+                                    .style(Style::default().add_modifier(Modifier::DIM)),
+                            ]
+                        })
+                        .collect::<Vec<_>>(),
+                ))
+                .block(
+                    Block::new()
+                        .title("Frequent terms")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(Color::Blue)),
+                );
+                frame.render_widget(terms_strip, vertical_layout[2]);
+            }
+            if let Some(delete) = delete_tag {
+                draw_yes_no_prompt(
+                    frame,
+                    *delete,
+                    format!("Tag {} has no notes left, delete it ?", tag.name).as_str(),
+                    main_rect,
+                );
+            }
+            if let Some(DigestPrompt {
+                name,
+                truncated,
+                error,
+                ..
+            }) = digest
+            {
+                let message = match error {
+                    Some(error) => Some(error.to_string()),
+                    None if *truncated => Some(String::from(
+                        "The digest is too large, some notes are left out",
+                    )),
+                    None => None,
+                };
+                draw_text_prompt(
+                    frame,
+                    "Digest note name",
+                    name.as_str(),
+                    error.is_none(),
+                    message.as_deref(),
+                    main_rect,
+                );
+            }
+            if let Some(NotePrompt { name, error }) = creating {
+                draw_text_prompt(
+                    frame,
+                    "Note name",
+                    name.as_str(),
+                    error.is_none(),
+                    error.as_ref().map(ToString::to_string).as_deref(),
+                    main_rect,
+                );
+            }
             frame.render_widget(main_frame, frame.size());
         })
         .discard_result()
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+    use crate::notebook::test_notebook;
+
+    #[test]
+    fn notes_created_in_a_tag_get_the_template_and_default_tags() {
+        let notebook = test_notebook();
+        notebook.set_setting("default_tags", "inbox").unwrap();
+        let tag = Tag::new("meeting", notebook.db()).unwrap();
+        let template = Note::new("Template", String::from("# {{title}}"), notebook.db()).unwrap();
+        tag.set_template(Some(template.id), notebook.db()).unwrap();
+
+        let mut state = State::TagNotesListing(
+            TagNotesListingStateData::try_from_database(tag, notebook.db()).unwrap(),
+        );
+        for code in [
+            KeyCode::Char('c'),
+            KeyCode::Char('M'),
+            KeyCode::Char('1'),
+            KeyCode::Enter,
+        ] {
+            let State::TagNotesListing(state_data) = state else {
+                panic!("{code:?} left the tag notes listing");
+            };
+            state = run_tag_notes_listing_state(
+                state_data,
+                KeyEvent::new(code, KeyModifiers::NONE),
+                &notebook,
+            )
+            .unwrap();
+        }
+
+        let note = Note::load_by_name("M1", notebook.db()).unwrap().unwrap();
+        assert_eq!(note.content, "# M1");
+        let tags: Vec<String> = Note::list_tags(note.id, notebook.db())
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        assert_eq!(tags, ["inbox", "meeting"]);
+        assert!(matches!(state, State::NoteViewing(_)));
+    }
+}
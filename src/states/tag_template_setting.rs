@@ -0,0 +1,114 @@
+use anyhow::Result;
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::widgets::Block;
+
+use rusqlite::Connection;
+
+use crate::config::ThemeConfig;
+use crate::helpers::{draw_text_prompt, DiscardResult};
+use crate::note::{Note, NoteError};
+use crate::notebook::Notebook;
+use crate::states::tags_managing::{draw_tags_managing, TagsManagingStateData};
+use crate::states::{State, Terminal};
+
+pub struct TagTemplateSettingStateData {
+    pub tags_managing_data: TagsManagingStateData,
+    pub name: String,
+    pub error: Option<NoteError>,
+}
+
+impl TagTemplateSettingStateData {
+    pub fn new(tags_managing_data: TagsManagingStateData, db: &Connection) -> Result<Self> {
+        let name = match tags_managing_data.get_selected() {
+            Some(tag) => tag
+                .template(db)?
+                .map(|template| template.name)
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        Ok(TagTemplateSettingStateData {
+            tags_managing_data,
+            name,
+            error: None,
+        })
+    }
+}
+
+fn validate_template(name: &str, db: &Connection) -> Result<Option<NoteError>> {
+    Ok(if name.is_empty() || Note::note_exists(name, db)? {
+        None
+    } else {
+        Some(NoteError::NoteDoesNotExist)
+    })
+}
+
+pub fn run_tag_template_setting_state(
+    mut state_data: TagTemplateSettingStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!("Cancel tag template setting.");
+            State::TagsManaging(state_data.tags_managing_data)
+        }
+        KeyCode::Enter if state_data.error.is_none() => {
+            let template = if state_data.name.is_empty() {
+                None
+            } else {
+                Note::load_by_name(state_data.name.as_str(), notebook.db())?
+            };
+            if let Some(tag) = state_data.tags_managing_data.get_selected() {
+                if let Some(note) = &template {
+                    info!("Set note {} as template of tag {}.", note.name, tag.name);
+                } else {
+                    info!("Remove the template of tag {}.", tag.name);
+                }
+                tag.set_template(template.map(|note| note.id), notebook.db())?;
+            }
+            State::TagsManaging(state_data.tags_managing_data)
+        }
+        KeyCode::Backspace => {
+            state_data.name.pop();
+            state_data.error = validate_template(state_data.name.as_str(), notebook.db())?;
+            State::TagTemplateSetting(state_data)
+        }
+        KeyCode::Char(c) => {
+            state_data.name.push(c);
+            state_data.error = validate_template(state_data.name.as_str(), notebook.db())?;
+            State::TagTemplateSetting(state_data)
+        }
+        _ => State::TagTemplateSetting(state_data),
+    })
+}
+
+pub fn draw_tag_template_setting_state(
+    TagTemplateSettingStateData {
+        tags_managing_data,
+        name,
+        error,
+    }: &TagTemplateSettingStateData,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_tags_managing(frame, tags_managing_data, theme, main_rect);
+            draw_text_prompt(
+                frame,
+                "Template note name, empty for none",
+                name,
+                error.is_none(),
+                error.as_ref().map(ToString::to_string).as_deref(),
+                main_rect,
+            );
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
@@ -13,11 +13,15 @@ use ratatui::Frame;
 
 use rusqlite::Connection;
 
+use crate::config::ThemeConfig;
 use crate::helpers::{DiscardResult, TryFromDatabase};
 use crate::notebook::Notebook;
 use crate::states::tag_creating::TagsCreatingStateData;
 use crate::states::tag_deleting::TagsDeletingStateData;
+use crate::states::tag_implications::TagImplicationsStateData;
 use crate::states::tag_notes_listing::TagNotesListingStateData;
+use crate::states::tag_template_setting::TagTemplateSettingStateData;
+use crate::states::tags_trash::TagsTrashStateData;
 use crate::states::{State, Terminal};
 use crate::tag::Tag;
 
@@ -75,6 +79,18 @@ pub fn run_tags_managing_state(
             info!("Open tag deleting prompt.");
             State::TagDeleting(TagsDeletingStateData::empty(state_data))
         }
+        KeyCode::Char('i') if !state_data.pattern_editing && !state_data.tags.is_empty() => {
+            info!("Open tag implications.");
+            State::TagImplications(TagImplicationsStateData::new(state_data, notebook.db())?)
+        }
+        KeyCode::Char('p') if !state_data.pattern_editing && !state_data.tags.is_empty() => {
+            info!("Open tag template setting prompt.");
+            State::TagTemplateSetting(TagTemplateSettingStateData::new(state_data, notebook.db())?)
+        }
+        KeyCode::Char('T') if !state_data.pattern_editing => {
+            info!("Open the deleted tags.");
+            State::TagsTrash(TagsTrashStateData::new(state_data, notebook.db())?)
+        }
         KeyCode::Enter if !state_data.tags.is_empty() => {
             info!("Open tag notes listing.");
             let tag = state_data.tags.swap_remove(state_data.selected);
@@ -106,6 +122,7 @@ pub fn run_tags_managing_state(
 
 pub fn draw_tags_managing_state(
     tags_managing: &TagsManagingStateData,
+    theme: &ThemeConfig,
     terminal: &mut Terminal,
     main_frame: Block,
 ) -> Result<()> {
@@ -113,7 +130,7 @@ pub fn draw_tags_managing_state(
         .draw(|frame| {
             let main_rect = main_frame.inner(frame.size());
 
-            draw_tags_managing(frame, tags_managing, main_rect);
+            draw_tags_managing(frame, tags_managing, theme, main_rect);
 
             frame.render_widget(main_frame, frame.size());
         })
@@ -128,6 +145,7 @@ pub fn draw_tags_managing(
         selected,
         tags,
     }: &TagsManagingStateData,
+    theme: &ThemeConfig,
     main_rect: Rect,
 ) {
     let vertical_layout = Layout::new(
@@ -194,8 +212,8 @@ pub fn draw_tags_managing(
             Span::raw(&tag.name[pattern_end..]),
         ])
     }))
-    .highlight_symbol(">> ")
-    .highlight_style(Style::default().bg(Color::White).fg(Color::Black))
+    .highlight_symbol(theme.highlight_symbol.as_str())
+    .highlight_style(theme.selection_style())
     .block(
         Block::new()
             .title("Tags")
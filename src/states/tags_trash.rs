@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::info;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListState};
+
+use rusqlite::Connection;
+
+use crate::config::{FormatConfig, ThemeConfig};
+use crate::helpers::{create_popup_proportion, draw_yes_no_prompt, DiscardResult};
+use crate::notebook::Notebook;
+use crate::states::tags_managing::{draw_tags_managing, TagsManagingStateData};
+use crate::states::{State, Terminal};
+use crate::tag::{Tag, TAG_TRASH_DAYS};
+
+pub struct TagsTrashStateData {
+    pub tags_managing_data: TagsManagingStateData,
+    pub trash: Vec<(Tag, DateTime<Utc>)>,
+    pub note_counts: HashMap<i64, usize>,
+    pub selected: usize,
+    pub purging: Option<bool>,
+}
+
+impl TagsTrashStateData {
+    pub fn new(tags_managing_data: TagsManagingStateData, db: &Connection) -> Result<Self> {
+        let mut state_data = TagsTrashStateData {
+            tags_managing_data,
+            trash: Vec::new(),
+            note_counts: HashMap::new(),
+            selected: 0,
+            purging: None,
+        };
+        state_data.refresh(db)?;
+        Ok(state_data)
+    }
+
+    fn refresh(&mut self, db: &Connection) -> Result<()> {
+        self.trash = Tag::list_trash(db)?;
+        let ids: Vec<i64> = self.trash.iter().map(|(tag, _)| tag.id).collect();
+        self.note_counts = Tag::count_notes(&ids, db)?;
+        self.selected = self.selected.min(self.trash.len().saturating_sub(1));
+        Ok(())
+    }
+}
+
+pub fn run_tags_trash_state(
+    mut state_data: TagsTrashStateData,
+    key_event: KeyEvent,
+    notebook: &Notebook,
+) -> Result<State> {
+    if let Some(purge) = state_data.purging.take() {
+        match key_event.code {
+            KeyCode::Enter if purge => {
+                let (tag, _) = &state_data.trash[state_data.selected];
+                info!("Purge tag {}.", tag.name);
+                Tag::purge(tag.id, notebook.db())?;
+                state_data.refresh(notebook.db())?;
+            }
+            KeyCode::Tab => state_data.purging = Some(!purge),
+            KeyCode::Enter | KeyCode::Esc => {}
+            _ => state_data.purging = Some(purge),
+        }
+        return Ok(State::TagsTrash(state_data));
+    }
+
+    Ok(match key_event.code {
+        KeyCode::Esc => {
+            info!("Close the deleted tags.");
+            State::TagsManaging(TagsManagingStateData::from_pattern(
+                state_data.tags_managing_data.pattern,
+                notebook.db(),
+            )?)
+        }
+        KeyCode::Enter | KeyCode::Char('r') if !state_data.trash.is_empty() => {
+            let (tag, _) = &state_data.trash[state_data.selected];
+            info!("Restore tag {}.", tag.name);
+            tag.restore(notebook.db())?;
+            state_data.refresh(notebook.db())?;
+            State::TagsTrash(state_data)
+        }
+        KeyCode::Char('d') if !state_data.trash.is_empty() => {
+            state_data.purging = Some(false);
+            State::TagsTrash(state_data)
+        }
+        KeyCode::Up if state_data.selected > 0 => {
+            state_data.selected -= 1;
+            State::TagsTrash(state_data)
+        }
+        KeyCode::Down if state_data.selected < state_data.trash.len().saturating_sub(1) => {
+            state_data.selected += 1;
+            State::TagsTrash(state_data)
+        }
+        _ => State::TagsTrash(state_data),
+    })
+}
+
+pub fn draw_tags_trash_state(
+    TagsTrashStateData {
+        tags_managing_data,
+        trash,
+        note_counts,
+        selected,
+        purging,
+    }: &TagsTrashStateData,
+    format: &FormatConfig,
+    theme: &ThemeConfig,
+    terminal: &mut Terminal,
+    main_frame: Block,
+) -> Result<()> {
+    terminal
+        .draw(|frame| {
+            let main_rect = main_frame.inner(frame.size());
+
+            draw_tags_managing(frame, tags_managing_data, theme, main_rect);
+
+            let popup_area = create_popup_proportion((60, 50), main_rect);
+            let trash_list = if trash.is_empty() {
+                List::new([Line::from(
+                    Span::raw("No deleted tag").add_modifier(Modifier::DIM),
+                )])
+            } else {
+                List::new(trash.iter().map(|(tag, deleted_at)| {
+                    let notes = note_counts.get(&tag.id).copied().unwrap_or(0);
+                    Line::from(vec![
+                        Span::raw(tag.name.as_str()),
+                        Span::raw(format!(
+                            "  {} notes, deleted {}",
+                            format.count(notes as u64),
+                            format.date(deleted_at)
+                        ))
+                        .add_modifier(Modifier::DIM),
+                    ])
+                }))
+                .highlight_symbol(theme.highlight_symbol.as_str())
+                .highlight_style(theme.selection_style())
+            }
+            .block(
+                Block::new()
+                    .title(format!("Deleted tags, kept {TAG_TRASH_DAYS} days"))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+
+            frame.render_widget(Clear, popup_area);
+            frame.render_stateful_widget(
+                trash_list,
+                popup_area,
+                &mut ListState::default().with_selected((!trash.is_empty()).then_some(*selected)),
+            );
+
+            if let Some(purge) = purging {
+                let (tag, _) = &trash[*selected];
+                draw_yes_no_prompt(
+                    frame,
+                    *purge,
+                    format!("Delete tag {} for good ?", tag.name).as_str(),
+                    main_rect,
+                );
+            }
+
+            frame.render_widget(main_frame, frame.size());
+        })
+        .discard_result()
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+
+use rusqlite::{Connection, Row};
+use sea_query::{
+    Alias, ColumnDef, Expr, Func, Iden, OnConflict, Order, Query, SqliteQueryBuilder, Table,
+};
+
+use crate::helpers::DiscardResult;
+use crate::links::{LinksCharacters, LinksTable};
+use crate::note::{NotesCharacters, NotesTable};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Iden)]
+pub struct StatsHistoryTable;
+
+#[derive(Iden, Clone, Copy, Debug)]
+pub enum StatsHistoryCharacters {
+    Date,
+    NoteCount,
+    LinkCount,
+    TotalBytes,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotebookStats {
+    pub date: NaiveDate,
+    pub note_count: u64,
+    pub link_count: u64,
+    pub total_bytes: u64,
+}
+
+impl NotebookStats {
+    pub fn current(db: &Connection) -> Result<Self> {
+        let (note_count, total_bytes) = db.query_row(
+            Query::select()
+                .from(NotesTable)
+                .expr(Func::count(Expr::col(NotesCharacters::Id)))
+                .expr(Func::coalesce([
+                    Func::sum(Func::cust(Alias::new("LENGTH")).arg(Func::cast_as(
+                        Expr::col(NotesCharacters::Content),
+                        Alias::new("BLOB"),
+                    )))
+                    .into(),
+                    Expr::val(0).into(),
+                ]))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let link_count = db.query_row(
+            Query::select()
+                .from(LinksTable)
+                .expr(Func::count(Expr::col(LinksCharacters::Id)))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(NotebookStats {
+            date: Local::now().date_naive(),
+            note_count,
+            link_count,
+            total_bytes,
+        })
+    }
+
+    /// Record today's stats unless they already were, returns whether a row was written.
+    pub fn snapshot(db: &Connection) -> Result<bool> {
+        let stats = NotebookStats::current(db)?;
+        let written = db.execute(
+            Query::insert()
+                .into_table(StatsHistoryTable)
+                .columns([
+                    StatsHistoryCharacters::Date,
+                    StatsHistoryCharacters::NoteCount,
+                    StatsHistoryCharacters::LinkCount,
+                    StatsHistoryCharacters::TotalBytes,
+                ])
+                .values([
+                    stats.date.format(DATE_FORMAT).to_string().into(),
+                    stats.note_count.into(),
+                    stats.link_count.into(),
+                    stats.total_bytes.into(),
+                ])?
+                .on_conflict(
+                    OnConflict::column(StatsHistoryCharacters::Date)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+        )?;
+        Ok(written > 0)
+    }
+
+    /// The snapshots, oldest first.
+    pub fn history(db: &Connection) -> Result<Vec<Self>> {
+        db.prepare(
+            Query::select()
+                .from(StatsHistoryTable)
+                .columns([
+                    StatsHistoryCharacters::Date,
+                    StatsHistoryCharacters::NoteCount,
+                    StatsHistoryCharacters::LinkCount,
+                    StatsHistoryCharacters::TotalBytes,
+                ])
+                .order_by(StatsHistoryCharacters::Date, Order::Asc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], NotebookStats::from_row)?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// The last snapshot of each month, oldest first.
+    pub fn monthly(history: &[Self]) -> Vec<Self> {
+        let mut months: Vec<Self> = Vec::new();
+        for stats in history {
+            match months.last_mut() {
+                Some(last)
+                    if (last.date.year(), last.date.month())
+                        == (stats.date.year(), stats.date.month()) =>
+                {
+                    last.clone_from(stats);
+                }
+                _ => months.push(stats.clone()),
+            }
+        }
+        months
+    }
+
+    pub fn csv_header() -> &'static str {
+        "date,note_count,link_count,total_bytes"
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.date.format(DATE_FORMAT),
+            self.note_count,
+            self.link_count,
+            self.total_bytes
+        )
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let date: String = row.get(0)?;
+        Ok(NotebookStats {
+            date: NaiveDate::parse_from_str(date.as_str(), DATE_FORMAT).unwrap_or_default(),
+            note_count: row.get(1)?,
+            link_count: row.get(2)?,
+            total_bytes: row.get(3)?,
+        })
+    }
+}
+
+impl StatsHistoryTable {
+    pub fn create(db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Table::create()
+                .if_not_exists()
+                .table(StatsHistoryTable)
+                .col(
+                    ColumnDef::new(StatsHistoryCharacters::Date)
+                        .string()
+                        .primary_key(),
+                )
+                .col(
+                    ColumnDef::new(StatsHistoryCharacters::NoteCount)
+                        .integer()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(StatsHistoryCharacters::LinkCount)
+                        .integer()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(StatsHistoryCharacters::TotalBytes)
+                        .integer()
+                        .not_null(),
+                )
+                .build(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .discard_result()
+    }
+}
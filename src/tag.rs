@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use thiserror::Error;
 
 use rusqlite::{Connection, OptionalExtension};
 use sea_query::{
@@ -6,19 +10,39 @@ use sea_query::{
     SqliteQueryBuilder, Table,
 };
 
-use crate::helpers::DiscardResult;
+use crate::helpers::{is_unique_violation, DiscardResult};
+use crate::links::LinksTable;
 use crate::note::{Note, NoteSummary, NotesCharacters, NotesTable};
 
+/// Deleted tags are kept this long before being purged.
+pub const TAG_TRASH_DAYS: i64 = 30;
+const MAX_TERMS_BYTES: usize = 1 << 20;
+const MAX_DIGEST_BYTES: usize = 1 << 20;
+const STOPWORDS: &[&str] = &[
+    "about", "after", "all", "also", "and", "any", "are", "because", "been", "before", "but",
+    "can", "could", "did", "does", "each", "for", "from", "had", "has", "have", "her", "his",
+    "how", "its", "into", "just", "like", "more", "most", "not", "now", "one", "only", "other",
+    "our", "out", "over", "she", "should", "some", "such", "than", "that", "the", "their", "them",
+    "then", "there", "these", "they", "this", "those", "through", "too", "very", "was", "were",
+    "what", "when", "where", "which", "while", "who", "why", "will", "with", "would", "you",
+    "your",
+];
+
 #[derive(Iden)]
 pub struct TagsTable;
 
 #[derive(Iden)]
 pub struct TagsJoinTable;
 
+#[derive(Iden)]
+pub struct ImplicationsTable;
+
 #[derive(Iden, Clone, Copy, Debug)]
 pub enum TagsCharacters {
     Id,
     Name,
+    DeletedAt,
+    DefaultTemplate,
 }
 
 #[derive(Iden, Clone, Copy, Debug)]
@@ -28,14 +52,49 @@ pub enum TagsJoinCharacters {
     TagId,
 }
 
-#[derive(Debug)]
+#[derive(Iden, Clone, Copy, Debug)]
+pub enum ImplicationsCharacters {
+    Id,
+    AntecedentId,
+    ConsequentId,
+}
+
+#[derive(Debug, Error)]
+pub enum TagError {
+    #[error("The name is empty")]
+    EmptyName,
+    #[error("A tag named {name:?} already exists")]
+    TagAlreadyExists { name: String },
+    #[error("No tag named {name:?} exists")]
+    TagDoesNotExist { name: String },
+    #[error("The tag {name:?} is in the trash")]
+    TagInTrash { name: String },
+    #[error("The note already has the tag {name:?}")]
+    AlreadyTagged { name: String },
+    #[error("The tag already implies {name:?}")]
+    AlreadyImplied { name: String },
+    #[error("A tag can't imply itself")]
+    SelfImplication,
+    #[error("{name:?} already implies this tag")]
+    ImplicationCycle { name: String },
+}
+
+#[derive(Debug, Clone)]
 pub struct Tag {
     pub id: i64,
     pub name: String,
 }
 
 impl Tag {
+    /// A tag in the trash holds on to its name until restored or purged.
     pub fn new(name: &str, db: &Connection) -> Result<Self> {
+        if Tag::load_trashed(name, db)?.is_some() {
+            return Err(TagError::TagInTrash {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+
         db.execute_batch(
             Query::insert()
                 .into_table(TagsTable)
@@ -44,7 +103,17 @@ impl Tag {
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )
-        .map_err(anyhow::Error::from)?;
+        .map_err(anyhow::Error::from)
+        .map_err(|err| {
+            if is_unique_violation(&err) {
+                TagError::TagAlreadyExists {
+                    name: name.to_owned(),
+                }
+                .into()
+            } else {
+                err
+            }
+        })?;
 
         Ok(Self {
             id: db.last_insert_rowid(),
@@ -58,6 +127,29 @@ impl Tag {
                 .from(TagsTable)
                 .columns([TagsCharacters::Id])
                 .and_where(Expr::col(TagsCharacters::Name).eq(name))
+                .and_where(Expr::col(TagsCharacters::DeletedAt).is_null())
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(anyhow::Error::from)
+        .map(|res| {
+            res.map(|id| Tag {
+                id,
+                name: name.to_string(),
+            })
+        })
+    }
+
+    pub fn load_trashed(name: &str, db: &Connection) -> Result<Option<Tag>> {
+        db.query_row(
+            Query::select()
+                .from(TagsTable)
+                .column(TagsCharacters::Id)
+                .and_where(Expr::col(TagsCharacters::Name).eq(name))
+                .and_where(Expr::col(TagsCharacters::DeletedAt).is_not_null())
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
             [],
@@ -79,6 +171,7 @@ impl Tag {
                 .from(TagsTable)
                 .column(TagsCharacters::Id)
                 .and_where(Expr::col(TagsCharacters::Name).eq(name))
+                .and_where(Expr::col(TagsCharacters::DeletedAt).is_null())
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
@@ -86,10 +179,40 @@ impl Tag {
         .map_err(anyhow::Error::from)
     }
 
-    pub fn delete(self, db: &Connection) -> Result<()> {
+    pub fn validate_name(name: &str, db: &Connection) -> Result<Option<TagError>> {
+        Ok(if name.is_empty() {
+            Some(TagError::EmptyName)
+        } else if Tag::tag_exists(name, db)? {
+            Some(TagError::TagAlreadyExists {
+                name: name.to_owned(),
+            })
+        } else if Tag::load_trashed(name, db)?.is_some() {
+            Some(TagError::TagInTrash {
+                name: name.to_owned(),
+            })
+        } else {
+            None
+        })
+    }
+
+    /// The tag is hidden but keeps its notes and implications until restored or purged.
+    pub fn trash(self, db: &Connection) -> Result<()> {
         db.execute_batch(
-            Query::delete()
-                .from_table(TagsTable)
+            Query::update()
+                .table(TagsTable)
+                .value(TagsCharacters::DeletedAt, Utc::now().timestamp())
+                .and_where(Expr::col(TagsCharacters::Id).eq(self.id))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+        Ok(())
+    }
+
+    pub fn restore(&self, db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Query::update()
+                .table(TagsTable)
+                .value(TagsCharacters::DeletedAt, Option::<i64>::None)
                 .and_where(Expr::col(TagsCharacters::Id).eq(self.id))
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
@@ -97,13 +220,242 @@ impl Tag {
         Ok(())
     }
 
+    pub fn template(&self, db: &Connection) -> Result<Option<Note>> {
+        let note_id: Option<i64> = db.query_row(
+            Query::select()
+                .from(TagsTable)
+                .column(TagsCharacters::DefaultTemplate)
+                .and_where(Expr::col(TagsCharacters::Id).eq(self.id))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+            |row| row.get(0),
+        )?;
+        note_id.map_or(Ok(None), |id| Note::load_by_id(id, db))
+    }
+
+    pub fn set_template(&self, note_id: Option<i64>, db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Query::update()
+                .table(TagsTable)
+                .value(TagsCharacters::DefaultTemplate, note_id)
+                .and_where(Expr::col(TagsCharacters::Id).eq(self.id))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?;
+        Ok(())
+    }
+
+    /// The content of a new note named `name` with this tag, filled from its template.
+    pub fn template_content(&self, name: &str, db: &Connection) -> Result<String> {
+        Ok(self.template(db)?.map_or_else(String::new, |template| {
+            template
+                .content
+                .replace("{{title}}", name)
+                .replace("{{tag}}", self.name.as_str())
+                .replace(
+                    "{{date}}",
+                    Local::now().format("%Y-%m-%d").to_string().as_str(),
+                )
+        }))
+    }
+
+    /// Delete the tag for good, along with its notes and implications.
+    pub fn purge(id: i64, db: &Connection) -> Result<()> {
+        db.execute_batch(
+            [
+                Query::delete()
+                    .from_table(TagsJoinTable)
+                    .and_where(Expr::col(TagsJoinCharacters::TagId).eq(id))
+                    .to_string(SqliteQueryBuilder),
+                Query::delete()
+                    .from_table(ImplicationsTable)
+                    .cond_where(
+                        Expr::col(ImplicationsCharacters::AntecedentId)
+                            .eq(id)
+                            .or(Expr::col(ImplicationsCharacters::ConsequentId).eq(id)),
+                    )
+                    .to_string(SqliteQueryBuilder),
+                Query::delete()
+                    .from_table(TagsTable)
+                    .and_where(Expr::col(TagsCharacters::Id).eq(id))
+                    .to_string(SqliteQueryBuilder),
+            ]
+            .join(";")
+            .as_str(),
+        )?;
+        Ok(())
+    }
+
+    /// The deleted tags with their deletion date, most recent first.
+    pub fn list_trash(db: &Connection) -> Result<Vec<(Tag, DateTime<Utc>)>> {
+        db.prepare(
+            Query::select()
+                .from(TagsTable)
+                .columns([
+                    TagsCharacters::Id,
+                    TagsCharacters::Name,
+                    TagsCharacters::DeletedAt,
+                ])
+                .and_where(Expr::col(TagsCharacters::DeletedAt).is_not_null())
+                .order_by(TagsCharacters::DeletedAt, Order::Desc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], |row| {
+            Ok((
+                Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                },
+                DateTime::from_timestamp(row.get(2)?, 0).unwrap_or_default(),
+            ))
+        })?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// Purge the tags deleted before `before`, returning how many were.
+    pub fn purge_trash(before: DateTime<Utc>, db: &Connection) -> Result<usize> {
+        let expired: Vec<i64> = db
+            .prepare(
+                Query::select()
+                    .from(TagsTable)
+                    .column(TagsCharacters::Id)
+                    .and_where(Expr::col(TagsCharacters::DeletedAt).lt(before.timestamp()))
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        for id in &expired {
+            Tag::purge(*id, db)?;
+        }
+        Ok(expired.len())
+    }
+
+    /// The tags directly implied by this one, ordered by name.
+    pub fn consequents(&self, db: &Connection) -> Result<Vec<Tag>> {
+        db.prepare(
+            Query::select()
+                .from(ImplicationsTable)
+                .columns([
+                    (TagsTable, TagsCharacters::Id),
+                    (TagsTable, TagsCharacters::Name),
+                ])
+                .join(
+                    JoinType::InnerJoin,
+                    TagsTable,
+                    Expr::col((ImplicationsTable, ImplicationsCharacters::ConsequentId))
+                        .equals((TagsTable, TagsCharacters::Id)),
+                )
+                .and_where(Expr::col(ImplicationsCharacters::AntecedentId).eq(self.id))
+                .and_where(Expr::col((TagsTable, TagsCharacters::DeletedAt)).is_null())
+                .order_by((TagsTable, TagsCharacters::Name), Order::Asc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// Every tag implied by this one, directly or through other implications.
+    pub fn implied(&self, db: &Connection) -> Result<Vec<Tag>> {
+        let mut implied: Vec<Tag> = Vec::new();
+        let mut stack = self.consequents(db)?;
+        while let Some(tag) = stack.pop() {
+            if tag.id != self.id && implied.iter().all(|implied| implied.id != tag.id) {
+                stack.extend(tag.consequents(db)?);
+                implied.push(tag);
+            }
+        }
+        Ok(implied)
+    }
+
+    pub fn validate_implication(
+        &self,
+        consequent: &Tag,
+        db: &Connection,
+    ) -> Result<Option<TagError>> {
+        Ok(if consequent.id == self.id {
+            Some(TagError::SelfImplication)
+        } else if consequent.implied(db)?.iter().any(|tag| tag.id == self.id) {
+            Some(TagError::ImplicationCycle {
+                name: consequent.name.clone(),
+            })
+        } else if self
+            .consequents(db)?
+            .iter()
+            .any(|tag| tag.id == consequent.id)
+        {
+            Some(TagError::AlreadyImplied {
+                name: consequent.name.clone(),
+            })
+        } else {
+            None
+        })
+    }
+
+    /// Notes tagged afterwards with this tag also get the consequent, those already tagged are left as is.
+    pub fn add_implication(&self, consequent: &Tag, db: &Connection) -> Result<()> {
+        if let Some(error) = self.validate_implication(consequent, db)? {
+            return Err(error.into());
+        }
+        db.execute_batch(
+            Query::insert()
+                .into_table(ImplicationsTable)
+                .columns([
+                    ImplicationsCharacters::AntecedentId,
+                    ImplicationsCharacters::ConsequentId,
+                ])
+                .values([self.id.into(), consequent.id.into()])?
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .map_err(anyhow::Error::from)
+    }
+
+    pub fn remove_implication(&self, consequent: &Tag, db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Query::delete()
+                .from_table(ImplicationsTable)
+                .and_where(Expr::col(ImplicationsCharacters::AntecedentId).eq(self.id))
+                .and_where(Expr::col(ImplicationsCharacters::ConsequentId).eq(consequent.id))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .map_err(anyhow::Error::from)
+    }
+
+    /// Returns the number of notes untagged.
+    pub fn remove_from_notes(&self, note_ids: &[i64], db: &Connection) -> Result<usize> {
+        db.execute(
+            Query::delete()
+                .from_table(TagsJoinTable)
+                .and_where(Expr::col(TagsJoinCharacters::TagId).eq(self.id))
+                .and_where(Expr::col(TagsJoinCharacters::NoteId).is_in(note_ids.iter().copied()))
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+            [],
+        )
+        .map_err(anyhow::Error::from)
+    }
+
+    /// Tags are ordered by name.
     pub fn search_by_name(pattern: &str, db: &Connection) -> Result<Vec<Tag>> {
         db.prepare(
             Query::select()
                 .from(TagsTable)
                 .columns([TagsCharacters::Id, TagsCharacters::Name])
-                .order_by(TagsCharacters::Id, Order::Desc)
+                .order_by(TagsCharacters::Name, Order::Asc)
                 .and_where(Expr::col(TagsCharacters::Name).like(format!("%{pattern}%")))
+                .and_where(Expr::col(TagsCharacters::DeletedAt).is_null())
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
@@ -113,6 +465,7 @@ impl Tag {
         .collect()
     }
 
+    /// Summaries are ordered by name.
     pub fn fetch_notes(id: i64, db: &Connection) -> Result<Vec<NoteSummary>> {
         db.prepare(
             Query::select()
@@ -121,6 +474,9 @@ impl Tag {
                     (NotesTable, NotesCharacters::Id),
                     (NotesTable, NotesCharacters::Name),
                 ])
+                .expr(LinksTable::outgoing_count())
+                .expr(LinksTable::incoming_count())
+                .expr(NotesTable::content_length())
                 .join(
                     JoinType::InnerJoin,
                     NotesTable,
@@ -128,26 +484,141 @@ impl Tag {
                         .equals((NotesTable, NotesCharacters::Id)),
                 )
                 .and_where(Expr::col(TagsJoinCharacters::TagId).eq(id))
+                .order_by((NotesTable, NotesCharacters::Name), Order::Asc)
                 .to_string(SqliteQueryBuilder)
                 .as_str(),
         )?
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .query_map([], NoteSummary::from_row)?
         .map(|row| row.map_err(anyhow::Error::from))
-        .map(|row| {
-            row.and_then(|(id, name)| {
-                Ok(NoteSummary {
-                    id,
-                    name,
-                    tags: Note::list_tags(id, db)?,
-                })
-            })
-        })
         .collect()
     }
 
     pub fn get_notes(&self, db: &Connection) -> Result<Vec<NoteSummary>> {
         Tag::fetch_notes(self.id, db)
     }
+
+    /// Summaries are ordered by name.
+    pub fn search_notes(&self, pattern: &str, db: &Connection) -> Result<Vec<NoteSummary>> {
+        db.prepare(
+            Query::select()
+                .from(TagsJoinTable)
+                .columns([
+                    (NotesTable, NotesCharacters::Id),
+                    (NotesTable, NotesCharacters::Name),
+                ])
+                .expr(LinksTable::outgoing_count())
+                .expr(LinksTable::incoming_count())
+                .expr(NotesTable::content_length())
+                .join(
+                    JoinType::InnerJoin,
+                    NotesTable,
+                    Expr::col((TagsJoinTable, TagsJoinCharacters::NoteId))
+                        .equals((NotesTable, NotesCharacters::Id)),
+                )
+                .and_where(Expr::col(TagsJoinCharacters::TagId).eq(self.id))
+                .and_where(
+                    Expr::col((NotesTable, NotesCharacters::Content)).like(format!("%{pattern}%")),
+                )
+                .order_by((NotesTable, NotesCharacters::Name), Order::Asc)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], NoteSummary::from_row)?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
+
+    /// Terms are ordered by decreasing count, then by name.
+    /// Only the first megabyte of content is processed.
+    pub fn frequent_terms(&self, limit: usize, db: &Connection) -> Result<Vec<(String, usize)>> {
+        let contents: Vec<String> = db
+            .prepare(
+                Query::select()
+                    .from(TagsJoinTable)
+                    .column((NotesTable, NotesCharacters::Content))
+                    .join(
+                        JoinType::InnerJoin,
+                        NotesTable,
+                        Expr::col((TagsJoinTable, TagsJoinCharacters::NoteId))
+                            .equals((NotesTable, NotesCharacters::Id)),
+                    )
+                    .and_where(Expr::col(TagsJoinCharacters::TagId).eq(self.id))
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut processed = 0;
+        for content in &contents {
+            if processed >= MAX_TERMS_BYTES {
+                break;
+            }
+            processed += content.len();
+
+            for term in significant_terms(content) {
+                *counts.entry(term).or_default() += 1;
+            }
+        }
+
+        Ok(rank_terms(counts, limit))
+    }
+
+    /// Build a markdown document of the tag notes, ordered by name, with their headings demoted.
+    /// Notes are left out once the digest exceeds a megabyte, in which case the flag is set.
+    pub fn digest(&self, db: &Connection) -> Result<(String, bool)> {
+        let notes: Vec<(String, String)> = db
+            .prepare(
+                Query::select()
+                    .from(TagsJoinTable)
+                    .columns([
+                        (NotesTable, NotesCharacters::Name),
+                        (NotesTable, NotesCharacters::Content),
+                    ])
+                    .join(
+                        JoinType::InnerJoin,
+                        NotesTable,
+                        Expr::col((TagsJoinTable, TagsJoinCharacters::NoteId))
+                            .equals((NotesTable, NotesCharacters::Id)),
+                    )
+                    .and_where(Expr::col(TagsJoinCharacters::TagId).eq(self.id))
+                    .order_by((NotesTable, NotesCharacters::Name), Order::Asc)
+                    .to_string(SqliteQueryBuilder)
+                    .as_str(),
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        let mut digest = format!("# {}\n", self.name);
+        for (name, content) in notes {
+            if digest.len() >= MAX_DIGEST_BYTES {
+                return Ok((digest, true));
+            }
+            digest.push_str(format!("\n## {name}\n\n").as_str());
+            digest.push_str(demote_headings(content.as_str()).trim_end());
+            digest.push('\n');
+        }
+
+        Ok((digest, false))
+    }
+
+    /// Tags without any note are missing from the counts.
+    pub fn count_notes(ids: &[i64], db: &Connection) -> Result<HashMap<i64, usize>> {
+        db.prepare(
+            Query::select()
+                .from(TagsJoinTable)
+                .column(TagsJoinCharacters::TagId)
+                .expr(Expr::col(TagsJoinCharacters::NoteId).count())
+                .and_where(Expr::col(TagsJoinCharacters::TagId).is_in(ids.iter().copied()))
+                .group_by_col(TagsJoinCharacters::TagId)
+                .to_string(SqliteQueryBuilder)
+                .as_str(),
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .map(|row| row.map_err(anyhow::Error::from))
+        .collect()
+    }
 }
 
 impl TagsTable {
@@ -168,11 +639,61 @@ impl TagsTable {
                         .unique_key()
                         .not_null(),
                 )
+                .col(ColumnDef::new(TagsCharacters::DeletedAt).integer())
+                .col(ColumnDef::new(TagsCharacters::DefaultTemplate).integer())
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(TagsTable, TagsCharacters::DefaultTemplate)
+                        .to(NotesTable, NotesCharacters::Id)
+                        .on_update(ForeignKeyAction::Cascade)
+                        .on_delete(ForeignKeyAction::SetNull),
+                )
                 .build(SqliteQueryBuilder)
                 .as_str(),
         )
         .discard_result()
     }
+
+    /// Add the columns missing from the tables created by older versions.
+    pub fn migrate(db: &Connection) -> Result<()> {
+        for (column, extra) in [
+            (TagsCharacters::DeletedAt, None),
+            (
+                TagsCharacters::DefaultTemplate,
+                Some(format!(
+                    "REFERENCES {}({}) ON UPDATE CASCADE ON DELETE SET NULL",
+                    NotesTable.to_string(),
+                    NotesCharacters::Id.to_string()
+                )),
+            ),
+        ] {
+            let exists = db
+                .prepare(
+                    format!(
+                        "SELECT 1 FROM pragma_table_info('{}') WHERE name = '{}'",
+                        TagsTable.to_string(),
+                        column.to_string()
+                    )
+                    .as_str(),
+                )?
+                .exists([])?;
+            if !exists {
+                let mut column_def = ColumnDef::new(column);
+                column_def.integer();
+                if let Some(extra) = extra {
+                    column_def.extra(extra);
+                }
+                db.execute_batch(
+                    Table::alter()
+                        .table(TagsTable)
+                        .add_column(&mut column_def)
+                        .build(SqliteQueryBuilder)
+                        .as_str(),
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl TagsJoinTable {
@@ -217,3 +738,300 @@ impl TagsJoinTable {
         .discard_result()
     }
 }
+
+fn demote_headings(content: &str) -> String {
+    let mut fence: Option<&str> = None;
+    content
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            match fence {
+                Some(marker) if trimmed.starts_with(marker) => fence = None,
+                Some(_) => return line.to_string(),
+                None if trimmed.starts_with("```") => fence = Some("```"),
+                None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+                None => {
+                    let level = trimmed.chars().take_while(|&c| c == '#').count();
+                    let is_heading = (1..6).contains(&level)
+                        && trimmed[level..]
+                            .chars()
+                            .next()
+                            .is_none_or(char::is_whitespace);
+                    if is_heading {
+                        return format!("#{trimmed}");
+                    }
+                }
+            }
+            line.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The most frequent terms of a note that aren't already one of its tags, as tag candidates.
+pub fn suggest_tags(content: &str, tags: &[Tag], limit: usize) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for term in significant_terms(content) {
+        if !tags.iter().any(|tag| tag.name.to_lowercase() == term) {
+            *counts.entry(term).or_default() += 1;
+        }
+    }
+
+    rank_terms(counts, limit)
+        .into_iter()
+        .map(|(term, _)| term)
+        .collect()
+}
+
+// Lowercased words of at least three characters, stopwords and numbers left out.
+fn significant_terms(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()) && !word.chars().all(char::is_numeric))
+}
+
+fn rank_terms(counts: HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut terms: Vec<(String, usize)> = counts.into_iter().collect();
+    terms.sort_by(|(a_term, a_count), (b_term, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_term.cmp(b_term))
+    });
+    terms.truncate(limit);
+    terms
+}
+
+impl ImplicationsTable {
+    pub fn create(db: &Connection) -> Result<()> {
+        db.execute_batch(
+            Table::create()
+                .if_not_exists()
+                .table(ImplicationsTable)
+                .col(
+                    ColumnDef::new(ImplicationsCharacters::Id)
+                        .integer()
+                        .primary_key()
+                        .auto_increment(),
+                )
+                .col(
+                    ColumnDef::new(ImplicationsCharacters::AntecedentId)
+                        .integer()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(ImplicationsCharacters::ConsequentId)
+                        .integer()
+                        .not_null(),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(ImplicationsTable, ImplicationsCharacters::AntecedentId)
+                        .to(TagsTable, TagsCharacters::Id)
+                        .on_update(ForeignKeyAction::Cascade)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .foreign_key(
+                    ForeignKey::create()
+                        .from(ImplicationsTable, ImplicationsCharacters::ConsequentId)
+                        .to(TagsTable, TagsCharacters::Id)
+                        .on_update(ForeignKeyAction::Cascade)
+                        .on_delete(ForeignKeyAction::Cascade),
+                )
+                .build(SqliteQueryBuilder)
+                .as_str(),
+        )
+        .discard_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TryFromDatabase;
+    use crate::note::{Note, NoteData};
+    use crate::notebook::test_database;
+
+    fn tag_note(name: &str, content: String, tag: &Tag, db: &Connection) {
+        let note = Note::new(name, content, db).unwrap();
+        NoteData::try_from_database(note, db)
+            .unwrap()
+            .add_tag(tag.clone(), db)
+            .unwrap();
+    }
+
+    #[test]
+    fn duplicate_names_are_typed_errors() {
+        let db = test_database();
+        Tag::new("work", &db).unwrap();
+
+        let err = Tag::new("work", &db).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TagError>(),
+            Some(TagError::TagAlreadyExists { name }) if name == "work"
+        ));
+    }
+
+    #[test]
+    fn rejected_names_explain_why() {
+        let db = test_database();
+        Tag::new("work", &db).unwrap();
+
+        let empty = Tag::validate_name("", &db).unwrap().unwrap();
+        assert!(matches!(empty, TagError::EmptyName));
+        assert_eq!(empty.to_string(), "The name is empty");
+
+        let taken = Tag::validate_name("work", &db).unwrap().unwrap();
+        assert_eq!(taken.to_string(), "A tag named \"work\" already exists");
+
+        assert!(Tag::validate_name("home", &db).unwrap().is_none());
+    }
+
+    #[test]
+    fn digest_demotes_headings_outside_code() {
+        assert_eq!(
+            demote_headings(
+                "# Title\n###### Deepest\n```\n# Comment\n```\n#hashtag\n  ## Indented"
+            ),
+            "## Title\n###### Deepest\n```\n# Comment\n```\n#hashtag\n### Indented"
+        );
+
+        let db = test_database();
+        let tag = Tag::new("work", &db).unwrap();
+        tag_note("Plan", String::from("# Goals\n\nShip it.\n\n"), &tag, &db);
+        tag_note("Other", String::new(), &Tag::new("home", &db).unwrap(), &db);
+        assert_eq!(
+            tag.digest(&db).unwrap(),
+            (
+                String::from("# work\n\n## Plan\n\n## Goals\n\nShip it.\n"),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn digest_stops_past_the_size_cap() {
+        let db = test_database();
+        let tag = Tag::new("big", &db).unwrap();
+        let content = "a".repeat(MAX_DIGEST_BYTES / 2);
+        for name in ["First", "Second", "Third"] {
+            tag_note(name, content.clone(), &tag, &db);
+        }
+
+        let (digest, truncated) = tag.digest(&db).unwrap();
+        assert!(truncated);
+        assert!(digest.contains("## Second") && !digest.contains("## Third"));
+        assert!(digest.len() < MAX_DIGEST_BYTES + content.len() + 64);
+    }
+
+    fn implied_names(tag: &Tag, db: &Connection) -> Vec<String> {
+        let mut names: Vec<String> = tag
+            .implied(db)
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn implications_follow_chains_and_diamonds() {
+        let db = test_database();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| Tag::new(name, &db).unwrap());
+
+        a.add_implication(&b, &db).unwrap();
+        b.add_implication(&c, &db).unwrap();
+        assert_eq!(implied_names(&a, &db), ["b", "c"]);
+        assert_eq!(implied_names(&c, &db), Vec::<String>::new());
+
+        a.add_implication(&d, &db).unwrap();
+        d.add_implication(&c, &db).unwrap();
+        assert_eq!(implied_names(&a, &db), ["b", "c", "d"]);
+    }
+
+    #[test]
+    fn implication_cycles_are_rejected() {
+        let db = test_database();
+        let [a, b, c] = ["a", "b", "c"].map(|name| Tag::new(name, &db).unwrap());
+        a.add_implication(&b, &db).unwrap();
+        b.add_implication(&c, &db).unwrap();
+
+        assert!(matches!(
+            c.validate_implication(&a, &db).unwrap(),
+            Some(TagError::ImplicationCycle { name }) if name == "a"
+        ));
+        assert!(matches!(
+            a.validate_implication(&a, &db).unwrap(),
+            Some(TagError::SelfImplication)
+        ));
+        assert!(matches!(
+            a.validate_implication(&b, &db).unwrap(),
+            Some(TagError::AlreadyImplied { name }) if name == "b"
+        ));
+        assert!(a.validate_implication(&c, &db).unwrap().is_none());
+        assert!(c.add_implication(&a, &db).is_err());
+        assert_eq!(implied_names(&c, &db), Vec::<String>::new());
+    }
+
+    #[test]
+    fn trashed_names_are_not_reused() {
+        let db = test_database();
+        let tag = Tag::new("old", &db).unwrap();
+        tag_note("Kept", String::new(), &tag, &db);
+        tag.clone().trash(&db).unwrap();
+
+        assert!(matches!(
+            Tag::validate_name("old", &db).unwrap(),
+            Some(TagError::TagInTrash { name }) if name == "old"
+        ));
+        let created = Tag::new("old", &db).unwrap_err();
+        assert!(matches!(
+            created.downcast_ref::<TagError>(),
+            Some(TagError::TagInTrash { .. })
+        ));
+
+        let trashed = Tag::load_trashed("old", &db).unwrap().unwrap();
+        assert_eq!(trashed.id, tag.id);
+        trashed.restore(&db).unwrap();
+        assert_eq!(tag.get_notes(&db).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn deleting_a_template_clears_it() {
+        let db = test_database();
+        let tag = Tag::new("meeting", &db).unwrap();
+        let template = Note::new("Template", String::from("{{tag}}: {{title}}"), &db).unwrap();
+        tag.set_template(Some(template.id), &db).unwrap();
+        assert_eq!(
+            tag.template_content("Monday", &db).unwrap(),
+            "meeting: Monday"
+        );
+
+        template.delete(&db).unwrap();
+        assert!(tag.template(&db).unwrap().is_none());
+        assert_eq!(tag.template_content("Monday", &db).unwrap(), "");
+    }
+
+    #[test]
+    fn migrated_template_references_are_cleared() {
+        let db = test_database();
+        db.execute_batch(
+            "DROP TABLE tags_join_table; DROP TABLE tags_table;
+             CREATE TABLE tags_table (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT UNIQUE NOT NULL);",
+        )
+        .unwrap();
+        TagsTable::migrate(&db).unwrap();
+        TagsJoinTable::create(&db).unwrap();
+
+        let tag = Tag::new("meeting", &db).unwrap();
+        let template = Note::new("Template", String::from("{{title}}"), &db).unwrap();
+        tag.set_template(Some(template.id), &db).unwrap();
+        template.delete(&db).unwrap();
+        let template_id: Option<i64> = db
+            .query_row("SELECT default_template FROM tags_table", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(template_id.is_none());
+    }
+}
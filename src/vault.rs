@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::helpers::TryFromDatabase;
+use crate::markdown::code_ranges;
+use crate::note::{Note, NoteData, NotesTable};
+use crate::notebook::Notebook;
+use crate::tag::Tag;
+
+#[derive(Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub unchanged: usize,
+    /// Files whose name was already taken by another file of the vault.
+    pub renamed: Vec<(PathBuf, String)>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// The (note, target) of the embeds turned into cross-references.
+    pub embeds: Vec<(String, String)>,
+}
+
+struct VaultFile {
+    path: PathBuf,
+    folder: Vec<String>,
+    name: String,
+}
+
+/// Import the markdown files of an Obsidian vault, their folders becoming tags.
+/// Files already imported with the same content are skipped, so the import can be resumed.
+pub fn import_vault(vault: &Path, notebook: &Notebook) -> Result<ImportReport> {
+    let mut files = Vec::new();
+    collect_files(vault, &mut Vec::new(), &mut files)?;
+    // The shallowest file keeps its name on collisions
+    files.sort_by(|a, b| (a.folder.len(), &a.path).cmp(&(b.folder.len(), &b.path)));
+
+    let mut report = ImportReport::default();
+    let mut taken_names = HashSet::new();
+    let mut tags: HashMap<String, Tag> = HashMap::new();
+
+    let transaction = notebook.db().unchecked_transaction()?;
+    for file in files {
+        let mut name = Note::normalize_name(file.name.as_str());
+        if !taken_names.insert(name.clone()) {
+            name = Note::normalize_name(format!("{name} ({})", file.folder.join("/")).as_str());
+            taken_names.insert(name.clone());
+            report.renamed.push((file.path.clone(), name.clone()));
+        }
+
+        let content = match fs::read_to_string(&file.path) {
+            Ok(content) => content,
+            Err(err) => {
+                report.failed.push((file.path, err.to_string()));
+                continue;
+            }
+        };
+        let (content, embeds) = convert_embeds(content.as_str());
+
+        if let Some(existing) = Note::load_by_name(name.as_str(), &transaction)? {
+            if existing.content == content {
+                report.unchanged += 1;
+            } else {
+                warn!("A different note named {name} already exists.");
+                report.failed.push((
+                    file.path,
+                    format!("a different note named {name:?} already exists"),
+                ));
+            }
+            continue;
+        }
+        if let Some(error) = Note::validate_name(name.as_str(), &transaction)? {
+            report.failed.push((file.path, error.to_string()));
+            continue;
+        }
+
+        info!("Import note {name} from {}.", file.path.display());
+        let mut note_data = NoteData::try_from_database(
+            Note::new(name.as_str(), content, &transaction)?,
+            &transaction,
+        )?;
+
+        let folder_tags = (1..=file.folder.len()).map(|depth| file.folder[..depth].join("/"));
+        let mut note_tags: Vec<String> = folder_tags
+            .chain(front_matter_tags(note_data.note.content.as_str()))
+            .collect();
+        note_tags.dedup();
+        for tag_name in note_tags {
+            if note_data.tags.iter().any(|tag| tag.name == tag_name) {
+                continue;
+            }
+            let tag = if let Some(tag) = tags.get(&tag_name) {
+                tag.clone()
+            } else {
+                let tag = match Tag::load_by_name(tag_name.as_str(), &transaction)? {
+                    Some(tag) => tag,
+                    None if Tag::load_trashed(tag_name.as_str(), &transaction)?.is_some() => {
+                        warn!("The tag {tag_name} is in the trash, leave it off {name}.");
+                        continue;
+                    }
+                    None => Tag::new(tag_name.as_str(), &transaction)?,
+                };
+                tags.insert(tag_name, tag.clone());
+                tag
+            };
+            note_data.add_tag(tag, &transaction)?;
+        }
+
+        report
+            .embeds
+            .extend(embeds.into_iter().map(|target| (name.clone(), target)));
+        report.imported += 1;
+    }
+
+    transaction.commit()?;
+    // Links may point forward to notes imported later
+    NotesTable::reindex_links(notebook.db())?;
+
+    Ok(report)
+}
+
+fn collect_files(dir: &Path, folder: &mut Vec<String>, files: &mut Vec<VaultFile>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            warn!("Skip {}, its name isn't valid unicode.", path.display());
+            continue;
+        };
+        // Obsidian keeps its configuration and trash in hidden folders
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            folder.push(file_name.to_owned());
+            collect_files(&path, folder, files)?;
+            folder.pop();
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            files.push(VaultFile {
+                name: file_name.trim_end_matches(".md").to_owned(),
+                folder: folder.clone(),
+                path,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Turn the `![[embeds]]` outside of code into plain cross-references, returning their targets.
+fn convert_embeds(content: &str) -> (String, Vec<String>) {
+    let code = code_ranges(content);
+    let mut converted = String::with_capacity(content.len());
+    let mut embeds = Vec::new();
+    let mut last = 0;
+
+    for (start, _) in content.match_indices("![[") {
+        if code.iter().any(|range| range.contains(&start)) {
+            continue;
+        }
+        converted.push_str(&content[last..start]);
+        last = start + 1;
+        if let Some(end) = content[start + 3..].find("]]") {
+            embeds.push(content[start + 3..start + 3 + end].to_owned());
+        }
+    }
+    converted.push_str(&content[last..]);
+
+    (converted, embeds)
+}
+
+/// The tags of the YAML front-matter, written inline or as a list.
+fn front_matter_tags(content: &str) -> Vec<String> {
+    let Some(front_matter) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+        .and_then(|rest| rest.find("\n---").map(|end| &rest[..end]))
+    else {
+        return Vec::new();
+    };
+
+    let clean = |tag: &str| {
+        tag.trim()
+            .trim_matches(['"', '\''])
+            .trim_start_matches('#')
+            .to_owned()
+    };
+
+    let mut tags = Vec::new();
+    let mut in_list = false;
+    for line in front_matter.lines() {
+        if let Some(value) = line.strip_prefix("tags:") {
+            let value = value.trim();
+            in_list = value.is_empty();
+            tags.extend(
+                value
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(clean),
+            );
+        } else if in_list {
+            match line.trim().strip_prefix("- ") {
+                Some(tag) => tags.push(clean(tag)),
+                None => in_list = false,
+            }
+        }
+    }
+
+    tags.retain(|tag| !tag.is_empty());
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn note_tags(name: &str, notebook: &Notebook) -> Vec<String> {
+        let note = Note::load_by_name(name, notebook.db()).unwrap().unwrap();
+        let mut tags: Vec<String> = NoteData::try_from_database(note, notebook.db())
+            .unwrap()
+            .tags
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        tags.sort();
+        tags
+    }
+
+    #[test]
+    fn vaults_are_imported_and_resumed() {
+        let dir = env::temp_dir().join(format!("foucault-test-{}-vault", process::id()));
+        fs::remove_dir_all(&dir).ok();
+        let vault = dir.join("vault");
+        fs::create_dir_all(vault.join("Projects/Old")).unwrap();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+
+        fs::write(
+            vault.join("Home.md"),
+            "---\ntags: [daily, \"#work\"]\n---\n![[Plan]], `![[inline]]`\n\n```\n![[fenced]]\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            vault.join("Projects/Plan.md"),
+            "---\ntags:\n  - work\n  - 'idea'\n---\nBack to [[Home]].\n",
+        )
+        .unwrap();
+        fs::write(vault.join("Projects/Home.md"), "Another home.").unwrap();
+        fs::write(vault.join("Projects/Old/Notes.md"), "Old notes.").unwrap();
+        fs::write(vault.join("Broken.md"), b"\xff").unwrap();
+        fs::write(vault.join(".obsidian/Hidden.md"), "Hidden.").unwrap();
+        fs::write(vault.join("image.png"), "Not markdown.").unwrap();
+
+        let notebook = Notebook::new_notebook("vault", &dir).unwrap();
+        let report = import_vault(&vault, &notebook).unwrap();
+        assert_eq!(report.imported, 4);
+        assert_eq!(report.unchanged, 0);
+        assert_eq!(
+            report.renamed,
+            vec![(
+                vault.join("Projects/Home.md"),
+                String::from("Home (Projects)")
+            )]
+        );
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, vault.join("Broken.md"));
+        assert_eq!(
+            report.embeds,
+            vec![(String::from("Home"), String::from("Plan"))]
+        );
+        assert!(Note::load_by_name("Hidden", notebook.db())
+            .unwrap()
+            .is_none());
+
+        let home = Note::load_by_name("Home", notebook.db()).unwrap().unwrap();
+        assert_eq!(
+            home.content,
+            "---\ntags: [daily, \"#work\"]\n---\n[[Plan]], `![[inline]]`\n\n```\n![[fenced]]\n```\n"
+        );
+        assert_eq!(note_tags("Home", &notebook), ["daily", "work"]);
+        assert_eq!(note_tags("Plan", &notebook), ["Projects", "idea", "work"]);
+        assert_eq!(note_tags("Home (Projects)", &notebook), ["Projects"]);
+        assert_eq!(note_tags("Notes", &notebook), ["Projects", "Projects/Old"]);
+        assert_eq!(
+            Note::list_backlinks("Plan", notebook.db()).unwrap(),
+            vec![String::from("Home")]
+        );
+
+        fs::write(vault.join("Projects/Old/Notes.md"), "Edited notes.").unwrap();
+        let report = import_vault(&vault, &notebook).unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.unchanged, 3);
+        let failed: Vec<&PathBuf> = report.failed.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            failed,
+            [
+                &vault.join("Broken.md"),
+                &vault.join("Projects/Old/Notes.md")
+            ]
+        );
+
+        drop(notebook);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}